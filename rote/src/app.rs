@@ -1,4 +1,9 @@
-use std::{collections::HashMap, io, io::Write, path::PathBuf, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use crossterm::{
     event::{self, Event, KeyCode},
@@ -6,93 +11,284 @@ use crossterm::{
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
 use ratatui::{Terminal, prelude::CrosstermBackend};
+use tokio::signal::unix::{SignalKind, signal};
 
 const UI_EVENT_CHANNEL_SIZE: usize = 1024;
 const SHUTDOWN_CHANNEL_SIZE: usize = 16;
 const STATUS_CHECK_INTERVAL_MS: u64 = 250;
 const KEYBOARD_POLL_INTERVAL_MS: u64 = 250;
+/// How long tasks get to exit after `SIGTERM` when rote panics.
+const PANIC_STOP_TIMEOUT: Duration = Duration::from_secs(1);
+/// How long to wait for a stopped task's output to close.
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+/// A task that stays up this long before failing starts its restart count over.
+const RESTART_RESET_AFTER: Duration = Duration::from_secs(10);
 
 use crate::{
-    config::{Config, Healthcheck, HealthcheckMethod, HealthcheckTool, TaskAction},
-    panel::{MessageKind, Panel, PanelIndex, StatusPanel, StreamKind},
-    process::TaskInstance,
+    config::{
+        Config, DEFAULT_STOP_SIGNAL, DEFAULT_STOP_TIMEOUT, Healthcheck, HealthcheckMethod,
+        HealthcheckMode, HealthcheckTool, STOP_SIGNAL_TIMEOUT, TaskAction, TaskConfiguration,
+    },
+    freshness::{STATE_FILE_NAME, StateFile, fingerprint, outputs_exist},
+    input::{Input, InputAction, InputMode, is_leave_key},
+    leftovers::{PID_FILE_NAME, PidFile},
+    loader::{LoadedConfig, load_config},
+    panel::{HealthcheckState, MessageKind, Panel, PanelIndex, StatusPanel, StreamKind},
+    process::{self, StopPolicy, StopStage, TaskInstance},
     render,
-    signals::is_process_exited_by_pid,
     task_manager::{TaskManager, resolve_dependencies},
     tools,
     ui::{ProcessStatus, UiEvent},
+    watch::{FileWatcher, describe_changes, spawn_config_watcher, spawn_watcher},
 };
 
+/// Report an `ensure` task's process as timed out if it is still running
+/// when the task's `timeout` runs out.
+fn spawn_ensure_timeout(
+    panel: PanelIndex,
+    proc: &TaskInstance,
+    task_config: &TaskConfiguration,
+    tx: &tokio::sync::mpsc::Sender<UiEvent>,
+) {
+    if let Some(timeout) = task_config.timeout
+        && matches!(task_config.action, Some(TaskAction::Ensure { .. }))
+    {
+        let tx = tx.clone();
+        let generation = proc.generation;
+        tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            let _ = tx.send(UiEvent::TimedOut { panel, generation }).await;
+        });
+    }
+}
+
+/// What happens to a task's panel once its process, stopped in the
+/// background, is gone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AfterStop {
+    /// Nothing, the task was stopped by hand
+    Stay,
+    /// Start the task again, as `r` does
+    Restart,
+    /// Start the task again because of its `restart` policy or `retries`
+    AutoRestart,
+    /// Hide the panel, the task was removed from the config
+    Retire,
+}
+
+/// Stop a task's process in the background, so its stop timeout doesn't
+/// hold up the event loop, and send [`UiEvent::Stopped`] once it's gone.
+fn spawn_stop(
+    panel: PanelIndex,
+    proc: TaskInstance,
+    tx: &tokio::sync::mpsc::Sender<UiEvent>,
+) -> tokio::task::JoinHandle<()> {
+    let tx = tx.clone();
+    tokio::spawn(async move {
+        let exit_status_arc = proc.exit_status_arc();
+        proc.terminate().await;
+        let _ = proc.wait_task.await;
+        // Output stays open if the task left background processes behind, so
+        // don't wait for it forever
+        for mut reader in std::iter::once(proc.stdout_task).chain(proc.stderr_task) {
+            if tokio::time::timeout(OUTPUT_DRAIN_TIMEOUT, &mut reader)
+                .await
+                .is_err()
+            {
+                reader.abort();
+            }
+        }
+
+        let exit_code = match exit_status_arc.lock().unwrap().as_ref() {
+            Some(Ok(status)) => {
+                use std::os::unix::process::ExitStatusExt;
+                status.code().or_else(|| status.signal().map(|s| 128 + s))
+            }
+            _ => None,
+        };
+        let _ = tx.send(UiEvent::Stopped { panel, exit_code }).await;
+    })
+}
+
+/// If the event loop panics, restore the terminal and stop every task before
+/// the panic is reported, since the normal shutdown can't run any more.
+/// Panics on other threads, like in a healthcheck, leave the tasks alone.
+fn install_panic_hook() {
+    let event_loop = std::thread::current().id();
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if std::thread::current().id() == event_loop {
+            let _ = execute!(io::stdout(), LeaveAlternateScreen);
+            let _ = disable_raw_mode();
+            eprintln!("rote crashed, stopping all tasks");
+            process::stop_all(PANIC_STOP_TIMEOUT);
+        }
+        previous(info);
+    }));
+}
+
+/// Whether rote still has its terminal. A closed terminal can't be opened
+/// again. This is checked when `SIGHUP` arrives, to tell a closed terminal
+/// from a request to reload: crossterm never reports one, since it keeps
+/// reading a terminal that was closed without returning an error or end of
+/// file.
+fn terminal_attached() -> bool {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .is_ok()
+}
+
+/// Remember that `proc` is running `panel`'s task, so a later run can stop it
+/// if rote dies first. Failing to is not worth interrupting anything for.
+fn record_pid(pid_path: &Path, panel: &Panel, proc: &TaskInstance) {
+    if let Some(pid) = proc.pid {
+        let _ = PidFile::record(pid_path, &panel.task_name, pid, &panel.cmd);
+    }
+}
+
+/// What starting a task's process needs from the event loop, besides the
+/// task itself.
+struct SpawnContext<'a> {
+    tx: &'a tokio::sync::mpsc::Sender<UiEvent>,
+    shutdown_tx: &'a tokio::sync::broadcast::Sender<()>,
+    pid_path: &'a Path,
+    terminal_size: crate::pty::TerminalSize,
+}
+
+/// Start a task's process in `panel`, along with its `timeout` and
+/// healthcheck, and show it as running. Messages about a failure are left to
+/// the caller.
+fn start_task(
+    panel_idx: PanelIndex,
+    panel: &Panel,
+    task_config: &TaskConfiguration,
+    procs: &mut [Option<TaskInstance>],
+    status_panel: &mut StatusPanel,
+    healthcheck_tasks: &mut HashMap<String, tokio::task::JoinHandle<()>>,
+    ctx: &SpawnContext,
+) -> io::Result<()> {
+    let proc = TaskInstance::spawn_with_io(
+        panel_idx,
+        &panel.cmd,
+        panel.cwd.as_deref(),
+        &panel.env,
+        panel.process_io(ctx.terminal_size),
+        ctx.tx.clone(),
+        ctx.shutdown_tx.subscribe(),
+    )?
+    .with_stop_policy(panel.stop_policy);
+
+    spawn_ensure_timeout(panel_idx, &proc, task_config, ctx.tx);
+    record_pid(ctx.pid_path, panel, &proc);
+    procs[*panel_idx] = Some(proc);
+    status_panel.update_entry(panel.task_name.clone(), ProcessStatus::Running);
+
+    if let Some(healthcheck) = &task_config.healthcheck {
+        // Reset healthcheck status to pending
+        status_panel.set_has_healthcheck(&panel.task_name);
+        let hc_task = spawn_healthcheck(
+            panel.task_name.clone(),
+            healthcheck.clone(),
+            panel.env.clone(),
+            ctx.tx.clone(),
+            ctx.shutdown_tx.subscribe(),
+        );
+        healthcheck_tasks.insert(panel.task_name.clone(), hc_task);
+    }
+    Ok(())
+}
+
 /// Spawn a healthcheck task that periodically runs the healthcheck.
 /// Returns the spawned task handle.
 fn spawn_healthcheck(
     task_name: String,
     healthcheck: Healthcheck,
+    env: Vec<(String, String)>,
     tx: tokio::sync::mpsc::Sender<UiEvent>,
     mut shutdown_rx: tokio::sync::broadcast::Receiver<()>,
 ) -> tokio::task::JoinHandle<()> {
     tokio::spawn(async move {
         let mut interval = tokio::time::interval(healthcheck.interval);
+        let started = tokio::time::Instant::now();
+        let continuous = healthcheck.mode == HealthcheckMode::Continuous;
+        let mut failures = 0;
+        // Whether the check has passed before, and whether it has since
+        // been reported as unhealthy
+        let mut was_healthy = false;
+        let mut unhealthy = false;
 
         loop {
             tokio::select! {
                 _ = interval.tick() => {
-                    let passed = match &healthcheck.method {
-                        HealthcheckMethod::Cmd(cmd) => {
-                            // Run the healthcheck command via shell, capturing output
-                            let result = tokio::process::Command::new("sh")
-                                .arg("-c")
-                                .arg(cmd)
-                                .stdout(std::process::Stdio::piped())
-                                .stderr(std::process::Stdio::piped())
-                                .output()
-                                .await;
-
-                            match result {
-                                Ok(output) => {
-                                    // Send stdout lines
-                                    let stdout = String::from_utf8_lossy(&output.stdout);
-                                    for line in stdout.lines() {
-                                        let _ = tx.send(UiEvent::HealthcheckLine {
-                                            task_name: task_name.clone(),
-                                            text: line.to_string(),
-                                        }).await;
-                                    }
-                                    // Send stderr lines
-                                    let stderr = String::from_utf8_lossy(&output.stderr);
-                                    for line in stderr.lines() {
-                                        let _ = tx.send(UiEvent::HealthcheckLine {
-                                            task_name: task_name.clone(),
-                                            text: line.to_string(),
-                                        }).await;
-                                    }
-                                    output.status.success()
-                                }
-                                Err(_) => false,
-                            }
-                        }
-                        HealthcheckMethod::Tool(tool) => {
-                            // Call the tool directly without spawning a process
-                            run_healthcheck_tool(tool).await.is_ok()
+                    let check = run_healthcheck(&task_name, &healthcheck.method, &env, &tx);
+                    let passed = match tokio::time::timeout(healthcheck.timeout, check).await {
+                        Ok(passed) => passed,
+                        Err(_) => {
+                            let _ = tx.send(UiEvent::HealthcheckLine {
+                                task_name: task_name.clone(),
+                                text: format!(
+                                    "[healthcheck timed out after {:.1}s]",
+                                    healthcheck.timeout.as_secs_f64()
+                                ),
+                            }).await;
+                            false
                         }
                     };
 
                     if passed {
-                        // Healthcheck passed!
-                        let _ = tx
-                            .send(UiEvent::HealthcheckPassed {
-                                task_name: task_name.clone(),
-                            })
-                            .await;
-                        break;
-                    } else {
-                        // Healthcheck failed, notify and try again after interval
+                        failures = 0;
+                        // Only report changes, so a continuous check doesn't
+                        // repeat itself
+                        if !was_healthy || unhealthy {
+                            let _ = tx
+                                .send(UiEvent::HealthcheckPassed {
+                                    task_name: task_name.clone(),
+                                })
+                                .await;
+                        }
+                        was_healthy = true;
+                        unhealthy = false;
+                        if !continuous {
+                            break;
+                        }
+                        continue;
+                    }
+
+                    if unhealthy {
+                        // Already reported, keep checking for a recovery
+                        continue;
+                    }
+
+                    // Failures while the task is still starting up don't count
+                    if was_healthy || started.elapsed() >= healthcheck.start_period {
+                        failures += 1;
+                    }
+                    let retries_left = healthcheck
+                        .retry_limit(was_healthy)
+                        .map(|retries| retries.saturating_sub(failures));
+                    if retries_left == Some(0) {
                         let _ = tx
-                            .send(UiEvent::HealthcheckFailed {
+                            .send(UiEvent::HealthcheckUnhealthy {
                                 task_name: task_name.clone(),
                             })
                             .await;
+                        if !continuous {
+                            break;
+                        }
+                        unhealthy = true;
+                        failures = 0;
+                        continue;
                     }
+
+                    // Healthcheck failed, notify and try again after interval
+                    let _ = tx
+                        .send(UiEvent::HealthcheckFailed {
+                            task_name: task_name.clone(),
+                            retries_left,
+                        })
+                        .await;
                 }
                 _ = shutdown_rx.recv() => {
                     break;
@@ -102,6 +298,53 @@ fn spawn_healthcheck(
     })
 }
 
+/// Run a healthcheck once, forwarding any command output to the task's
+/// panel. Returns whether it passed.
+async fn run_healthcheck(
+    task_name: &str,
+    method: &HealthcheckMethod,
+    env: &[(String, String)],
+    tx: &tokio::sync::mpsc::Sender<UiEvent>,
+) -> bool {
+    match method {
+        HealthcheckMethod::Cmd(cmd) => {
+            // Run the healthcheck command via shell, capturing output. The
+            // command is killed if it times out.
+            let result = tokio::process::Command::new("sh")
+                .arg("-c")
+                .arg(cmd)
+                .envs(env.iter().map(|(k, v)| (k, v)))
+                .stdout(std::process::Stdio::piped())
+                .stderr(std::process::Stdio::piped())
+                .kill_on_drop(true)
+                .output()
+                .await;
+
+            match result {
+                Ok(output) => {
+                    // Send stdout lines, then stderr lines
+                    let stdout = String::from_utf8_lossy(&output.stdout);
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+                    for line in stdout.lines().chain(stderr.lines()) {
+                        let _ = tx
+                            .send(UiEvent::HealthcheckLine {
+                                task_name: task_name.to_string(),
+                                text: line.to_string(),
+                            })
+                            .await;
+                    }
+                    output.status.success()
+                }
+                Err(_) => false,
+            }
+        }
+        HealthcheckMethod::Tool(tool) => {
+            // Call the tool directly without spawning a process
+            run_healthcheck_tool(tool).await.is_ok()
+        }
+    }
+}
+
 /// Run a built-in healthcheck tool directly.
 async fn run_healthcheck_tool(tool: &HealthcheckTool) -> anyhow::Result<()> {
     match tool {
@@ -122,24 +365,380 @@ fn format_timestamp(timestamps: bool) -> Option<String> {
     }
 }
 
+/// Where a running config was loaded from, so that it can be loaded again.
+struct ConfigSource {
+    /// The files given on the command line (or discovered).
+    paths: Vec<PathBuf>,
+    /// Every file that went into the config, including includes. These are
+    /// watched for changes.
+    files: Vec<PathBuf>,
+}
+
+/// Build a panel for every task with a `run` or `ensure` action, in the order
+/// the tasks appear in the config.
+fn build_panels(config: &Config, config_dir: &Path) -> io::Result<Vec<Panel>> {
+    let mut panels = Vec::new();
+    for (task_name, task_config) in &config.tasks {
+        let (Some(TaskAction::Run { command }) | Some(TaskAction::Ensure { command })) =
+            &task_config.action
+        else {
+            continue;
+        };
+
+        let shell = config.task_shell(task_config);
+        let cmd = command.to_argv(shell.as_deref()).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("task '{task_name}': {e}"),
+            )
+        })?;
+
+        // Tasks run relative to the config file, not to wherever rote was
+        // started from (which may be a subdirectory of the project)
+        let cwd = match &task_config.cwd {
+            Some(c) => Some(config_dir.join(c)),
+            None if !config_dir.as_os_str().is_empty() => Some(config_dir.to_path_buf()),
+            None => None,
+        }
+        .map(|path| path.to_string_lossy().to_string());
+
+        // Determine which streams to show
+        let (show_stdout, show_stderr) = match &task_config.display {
+            None => (true, true), // Show both by default
+            Some(streams) => {
+                if streams.is_empty() {
+                    (false, false) // Empty list means show nothing
+                } else {
+                    let show_stdout = streams.iter().any(|s| s == "stdout");
+                    let show_stderr = streams.iter().any(|s| s == "stderr");
+                    (show_stdout, show_stderr)
+                }
+            }
+        };
+
+        let env = config.task_env(task_name, config_dir)?;
+
+        panels.push(
+            Panel::new(
+                task_name.clone(),
+                cmd,
+                cwd,
+                show_stdout,
+                show_stderr,
+                task_config.timestamps,
+            )
+            .with_env(env)
+            .with_stop_policy(stop_policy(task_config))
+            .with_stdin(task_config.stdin)
+            .with_tty(task_config.tty),
+        );
+    }
+    Ok(panels)
+}
+
+/// How a task's process is stopped, from its `stop_signal` and `stop_timeout`.
+fn stop_policy(task_config: &TaskConfiguration) -> StopPolicy {
+    let default_timeout = match task_config.stop_signal {
+        Some(_) => STOP_SIGNAL_TIMEOUT,
+        None => DEFAULT_STOP_TIMEOUT,
+    };
+    StopPolicy {
+        signal: task_config.stop_signal.unwrap_or(DEFAULT_STOP_SIGNAL),
+        timeout: task_config.stop_timeout.unwrap_or(default_timeout),
+    }
+}
+
+/// Add a status message to a panel, keeping it in view if the panel follows
+/// its output.
+fn push_status_message(panel: &mut Panel, msg: &str) {
+    let timestamp = format_timestamp(panel.timestamps);
+    panel
+        .messages
+        .push(MessageKind::Status, msg, timestamp.as_deref());
+    if panel.follow {
+        panel.scroll = panel.visible_len().saturating_sub(1);
+    }
+}
+
+/// Show a task's action, dependencies and healthcheck in the status panel.
+fn update_status_entry(
+    status_panel: &mut StatusPanel,
+    task_name: &str,
+    task_config: &TaskConfiguration,
+    status: ProcessStatus,
+) {
+    if let Some(action) = &task_config.action {
+        status_panel.update_entry_with_action(task_name.to_string(), status, action.clone());
+    }
+    status_panel
+        .entry_indices
+        .insert(task_name.to_string(), usize::MAX);
+    status_panel.update_dependencies(
+        task_name.to_string(),
+        task_config
+            .require
+            .iter()
+            .map(|dep| dep.task.clone())
+            .collect(),
+    );
+    // Mark the healthcheck as pending if the task has one
+    if task_config.healthcheck.is_some() {
+        status_panel.set_has_healthcheck(task_name);
+    } else {
+        status_panel.clear_healthcheck(task_name);
+    }
+}
+
+/// The tasks to start: the ones asked for, or else the config's default.
+fn target_tasks(config: &Config, requested: &[String]) -> Vec<String> {
+    if requested.is_empty() {
+        config.default.iter().cloned().collect()
+    } else {
+        requested.to_vec()
+    }
+}
+
+/// Load the config again for a reload, along with its panels and the tasks
+/// to run. Fails without side effects, so the old config can keep running.
+fn reload_config(
+    source: &ConfigSource,
+    requested: &[String],
+) -> Result<(LoadedConfig, Vec<Panel>, Vec<String>), String> {
+    let loaded = load_config(&source.paths).map_err(|e| e.to_string())?;
+    let tasks_list = resolve_dependencies(&loaded.config, &target_tasks(&loaded.config, requested))
+        .map_err(|e| e.to_string())?;
+    let panels = build_panels(&loaded.config, &loaded.config_dir).map_err(|e| e.to_string())?;
+    Ok((loaded, panels, tasks_list))
+}
+
+/// Start watching a task's `watch` patterns, reporting failures in its panel.
+fn start_watcher(
+    panel: &mut Panel,
+    task_config: &TaskConfiguration,
+    tx: tokio::sync::mpsc::Sender<UiEvent>,
+) -> Option<FileWatcher> {
+    let root = Path::new(panel.cwd.as_deref().unwrap_or("."));
+    match spawn_watcher(
+        panel.task_name.clone(),
+        root,
+        &task_config.watch,
+        &task_config.watch_ignore,
+        tx,
+    ) {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            let timestamp = format_timestamp(panel.timestamps);
+            panel.messages.push(
+                MessageKind::Status,
+                &format!("[watch failed: {e:#}]"),
+                timestamp.as_deref(),
+            );
+            None
+        }
+    }
+}
+
+/// How long a `skip_if` command may run before its task runs anyway.
+const SKIP_IF_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// What deciding whether an `ensure` task can be skipped looks at, taken from
+/// its config and panel so the decision can be made away from the event loop.
+struct FreshnessCheck {
+    /// The task's directory, which `sources` and `outputs` are relative to
+    root: PathBuf,
+    command: Vec<String>,
+    env: Vec<(String, String)>,
+    sources: Vec<String>,
+    outputs: Vec<String>,
+    /// The fingerprint of the task's last successful run, if it tracks freshness
+    previous: Option<String>,
+    skip_if: Option<String>,
+}
+
+impl FreshnessCheck {
+    fn new(
+        task_name: &str,
+        task_config: &TaskConfiguration,
+        panel: &Panel,
+        state: &StateFile,
+    ) -> Self {
+        Self {
+            root: PathBuf::from(panel.cwd.as_deref().unwrap_or(".")),
+            command: panel.cmd.clone(),
+            env: panel.env.clone(),
+            sources: task_config.sources.clone(),
+            outputs: task_config.outputs.clone(),
+            previous: task_config
+                .tracks_freshness()
+                .then(|| state.fingerprints.get(task_name).cloned())
+                .flatten(),
+            skip_if: task_config.skip_if.clone(),
+        }
+    }
+
+    /// Find out in the background whether the task can be skipped, and send
+    /// the answer as [`UiEvent::FreshnessChecked`].
+    fn spawn(self, task_name: String, tx: &tokio::sync::mpsc::Sender<UiEvent>) {
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            let up_to_date = self.run().await;
+            let _ = tx
+                .send(UiEvent::FreshnessChecked {
+                    task_name,
+                    up_to_date,
+                })
+                .await;
+        });
+    }
+
+    /// Whether the task can be skipped: its `sources` haven't changed since
+    /// it last succeeded and its `outputs` exist, or its `skip_if` command
+    /// succeeds.
+    async fn run(self) -> Result<bool, String> {
+        if let Some(previous) = self.previous {
+            // Walking a large source tree takes a while
+            let root = self.root.clone();
+            let command = self.command.clone();
+            let env = self.env.clone();
+            let unchanged = tokio::task::spawn_blocking(move || {
+                fingerprint(&root, &self.sources, &command, &env)
+                    .is_ok_and(|current| current == previous)
+                    && outputs_exist(&root, &self.outputs)
+            })
+            .await
+            .unwrap_or(false);
+            if unchanged {
+                return Ok(true);
+            }
+        }
+
+        let Some(cmd) = self.skip_if else {
+            return Ok(false);
+        };
+        let status = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(cmd)
+            .current_dir(&self.root)
+            .envs(self.env.iter().map(|(k, v)| (k, v)))
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .status();
+        match tokio::time::timeout(SKIP_IF_TIMEOUT, status).await {
+            Ok(status) => Ok(status.is_ok_and(|status| status.success())),
+            Err(_) => Err(format!(
+                "skip_if timed out after {}s",
+                SKIP_IF_TIMEOUT.as_secs()
+            )),
+        }
+    }
+}
+
+/// Take the fingerprint of what a successful `ensure` task ran against in the
+/// background, and send it as [`UiEvent::Fingerprinted`].
+fn spawn_fingerprint(
+    panel_idx: PanelIndex,
+    generation: u64,
+    task_config: &TaskConfiguration,
+    panel: &Panel,
+    tx: &tokio::sync::mpsc::Sender<UiEvent>,
+) {
+    let root = PathBuf::from(panel.cwd.as_deref().unwrap_or("."));
+    let sources = task_config.sources.clone();
+    let command = panel.cmd.clone();
+    let env = panel.env.clone();
+    let tx = tx.clone();
+    tokio::spawn(async move {
+        let fingerprint =
+            tokio::task::spawn_blocking(move || fingerprint(&root, &sources, &command, &env).ok())
+                .await
+                .ok()
+                .flatten();
+        let _ = tx
+            .send(UiEvent::Fingerprinted {
+                panel: panel_idx,
+                generation,
+                fingerprint,
+            })
+            .await;
+    });
+}
+
+/// Save which `ensure` tasks are up to date, noting a failure in `panel`.
+fn save_state(state: &StateFile, state_path: &Path, panel: &mut Panel) {
+    if let Err(e) = state.save(state_path) {
+        let timestamp = format_timestamp(panel.timestamps);
+        panel.messages.push(
+            MessageKind::Status,
+            &format!("[failed to save {}: {e}]", state_path.display()),
+            timestamp.as_deref(),
+        );
+    }
+}
+
+/// The panels of tasks that are still in the config, in display order.
+fn live_panels(panels: &[Panel]) -> Vec<PanelIndex> {
+    (0..panels.len())
+        .filter(|&i| !panels[i].retired)
+        .map(PanelIndex::new)
+        .collect()
+}
+
 pub async fn run(config: Config, tasks_to_run: Vec<String>, config_dir: PathBuf) -> io::Result<()> {
     run_with_input(config, tasks_to_run, config_dir, None).await
 }
 
+/// Run a config loaded from `paths`, loading it again whenever one of its
+/// files changes, `R` is pressed or rote receives `SIGHUP` while it still has
+/// its terminal.
+pub async fn run_reloadable(
+    loaded: LoadedConfig,
+    paths: Vec<PathBuf>,
+    tasks_to_run: Vec<String>,
+    external_rx: Option<tokio::sync::mpsc::Receiver<UiEvent>>,
+) -> io::Result<()> {
+    let source = ConfigSource {
+        paths,
+        files: loaded.files,
+    };
+    run_app(
+        loaded.config,
+        tasks_to_run,
+        loaded.config_dir,
+        Some(source),
+        external_rx,
+    )
+    .await
+}
+
 pub async fn run_with_input(
     config: Config,
     tasks_to_run: Vec<String>,
     config_dir: PathBuf,
+    external_rx: Option<tokio::sync::mpsc::Receiver<UiEvent>>,
+) -> io::Result<()> {
+    run_app(config, tasks_to_run, config_dir, None, external_rx).await
+}
+
+async fn run_app(
+    mut config: Config,
+    tasks_to_run: Vec<String>,
+    config_dir: PathBuf,
+    mut source: Option<ConfigSource>,
     mut external_rx: Option<tokio::sync::mpsc::Receiver<UiEvent>>,
 ) -> io::Result<()> {
     let enable_terminal = external_rx.is_none();
     if enable_terminal {
         enable_raw_mode()?;
         execute!(io::stdout(), EnterAlternateScreen)?;
+        install_panic_hook();
     }
 
     let backend = CrosstermBackend::new(io::stdout());
     let mut terminal = Terminal::new(backend)?;
+    // The size of the pseudo-terminals of tasks with `tty: true`
+    let mut terminal_size = render::output_size(terminal.size().unwrap_or_default(), false);
 
     let (internal_tx, mut internal_rx) =
         tokio::sync::mpsc::channel::<UiEvent>(UI_EVENT_CHANNEL_SIZE);
@@ -148,82 +747,18 @@ pub async fn run_with_input(
     let tx = internal_tx.clone();
     let (shutdown_tx, _) = tokio::sync::broadcast::channel::<()>(SHUTDOWN_CHANNEL_SIZE);
 
-    // Resolve which tasks to run
-    let target_tasks = if tasks_to_run.is_empty() {
-        if let Some(default) = &config.default {
-            vec![default.clone()]
-        } else {
-            vec![]
-        }
-    } else {
-        tasks_to_run
-    };
-
     // Resolve all dependencies to get the full list of tasks to start
-    let tasks_list = resolve_dependencies(&config, &target_tasks)?;
+    let mut tasks_list = resolve_dependencies(&config, &target_tasks(&config, &tasks_to_run))?;
 
     // Create panels for ALL tasks with actions (not just those being started)
     // Panels are ordered according to their order in the YAML config file
-    let mut panels = Vec::new();
-    let mut task_to_panel: HashMap<String, PanelIndex> = HashMap::new();
-
-    // Collect task names, preserving YAML file order (IndexMap preserves insertion order)
-    let task_names: Vec<_> = config
-        .tasks
+    let mut panels = build_panels(&config, &config_dir)?;
+    let mut task_to_panel: HashMap<String, PanelIndex> = panels
         .iter()
-        .filter(|(_, cfg)| {
-            matches!(
-                cfg.action,
-                Some(TaskAction::Run { .. }) | Some(TaskAction::Ensure { .. })
-            )
-        })
-        .map(|(name, _)| name.clone())
+        .enumerate()
+        .map(|(i, panel)| (panel.task_name.clone(), PanelIndex::new(i)))
         .collect();
 
-    for task_name in &task_names {
-        let task_config = config.tasks.get(task_name).unwrap();
-        // Create panels for tasks with "run" or "ensure" actions
-        if let Some(TaskAction::Run { command }) | Some(TaskAction::Ensure { command }) =
-            &task_config.action
-        {
-            let cmd = shell_words::split(&command.as_command()).map_err(|e| {
-                io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    format!("Failed to parse command: {e}"),
-                )
-            })?;
-
-            let cwd = task_config.cwd.as_ref().map(|c| {
-                let path = config_dir.join(c);
-                path.to_string_lossy().to_string()
-            });
-
-            // Determine which streams to show
-            let (show_stdout, show_stderr) = match &task_config.display {
-                None => (true, true), // Show both by default
-                Some(streams) => {
-                    if streams.is_empty() {
-                        (false, false) // Empty list means show nothing
-                    } else {
-                        let show_stdout = streams.iter().any(|s| s == "stdout");
-                        let show_stderr = streams.iter().any(|s| s == "stderr");
-                        (show_stdout, show_stderr)
-                    }
-                }
-            };
-
-            task_to_panel.insert(task_name.clone(), PanelIndex::new(panels.len()));
-            panels.push(Panel::new(
-                task_name.clone(),
-                cmd,
-                cwd,
-                show_stdout,
-                show_stderr,
-                task_config.timestamps,
-            ));
-        }
-    }
-
     if panels.is_empty() {
         disable_raw_mode()?;
         eprintln!("No tasks with 'run' or 'ensure' action to display");
@@ -232,29 +767,20 @@ pub async fn run_with_input(
 
     // Initialize status panel with all tasks that have actions (YAML file order)
     let mut status_panel = StatusPanel::new();
-    for task_name in &task_names {
-        let task_config = config.tasks.get(task_name).unwrap();
-        if let Some(action) = &task_config.action {
-            // Tasks in tasks_list are being started, others show as "Not started"
-            let initial_status = if tasks_list.contains(task_name) {
-                ProcessStatus::Running
-            } else {
-                ProcessStatus::NotStarted
-            };
-            status_panel.update_entry_with_action(
-                task_name.clone(),
-                initial_status,
-                action.clone(),
-            );
-            status_panel
-                .entry_indices
-                .insert(task_name.clone(), usize::MAX);
-            status_panel.update_dependencies(task_name.clone(), task_config.require.clone());
-            // Initialize healthcheck_passed to Some(false) if task has a healthcheck
-            if task_config.healthcheck.is_some() {
-                status_panel.set_has_healthcheck(task_name);
-            }
-        }
+    for panel in &panels {
+        // Tasks in tasks_list wait for their dependencies, others show as
+        // "Not started"
+        let initial_status = if tasks_list.contains(&panel.task_name) {
+            ProcessStatus::Pending
+        } else {
+            ProcessStatus::NotStarted
+        };
+        update_status_entry(
+            &mut status_panel,
+            &panel.task_name,
+            &config.tasks[&panel.task_name],
+            initial_status,
+        );
     }
 
     // Initialize process slots
@@ -266,6 +792,93 @@ pub async fn run_with_input(
     // Task manager tracks pending tasks and completed Run tasks
     let mut task_manager = TaskManager::new(tasks_list.clone(), task_to_panel.clone());
 
+    // Watch the files of the tasks being started that ask for it
+    let mut watchers: HashMap<String, FileWatcher> = HashMap::new();
+    for task_name in &tasks_list {
+        if let Some(panel_idx) = task_to_panel.get(task_name).copied()
+            && !config.tasks[task_name].watch.is_empty()
+            && let Some(watcher) = start_watcher(
+                &mut panels[*panel_idx],
+                &config.tasks[task_name],
+                tx.clone(),
+            )
+        {
+            watchers.insert(task_name.clone(), watcher);
+        }
+    }
+
+    // Reload the config when one of its files changes, or on SIGHUP
+    let mut config_watcher = source.as_ref().and_then(|source| {
+        spawn_config_watcher(&source.files, tx.clone())
+            .map_err(|e| status_panel.config_error = Some(format!("{e:#}")))
+            .ok()
+    });
+
+    // Whether the terminal rote started with has since been closed, which
+    // the kernel reports with SIGHUP
+    let had_terminal = enable_terminal && terminal_attached();
+    let terminal_closed = move || had_terminal && !terminal_attached();
+
+    // Shut down normally on SIGTERM, SIGINT, and on a SIGHUP that comes from
+    // the terminal being closed, rather than leave the tasks running
+    let reloadable = source.is_some();
+    let signal_task = {
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            let (Ok(mut hangup), Ok(mut terminate), Ok(mut interrupt)) = (
+                signal(SignalKind::hangup()),
+                signal(SignalKind::terminate()),
+                signal(SignalKind::interrupt()),
+            ) else {
+                return;
+            };
+            loop {
+                let ev = tokio::select! {
+                    Some(()) = hangup.recv() => {
+                        if reloadable && !terminal_closed() {
+                            UiEvent::ReloadConfig
+                        } else {
+                            UiEvent::Exit
+                        }
+                    }
+                    Some(()) = terminate.recv() => UiEvent::Exit,
+                    Some(()) = interrupt.recv() => UiEvent::Exit,
+                    else => break,
+                };
+                let _ = tx.send(ev).await;
+            }
+        })
+    };
+
+    // The processes this run starts, so that a later run can stop them if
+    // rote dies without doing so
+    let pid_path = config_dir.join(PID_FILE_NAME);
+
+    // Fingerprints of the ensure tasks that last succeeded, for skipping them
+    let state_path = config_dir.join(STATE_FILE_NAME);
+    let mut state = StateFile::load(&state_path);
+
+    // The attempt each ensure task being retried is on
+    let mut ensure_attempts: HashMap<String, u32> = HashMap::new();
+
+    // The generations of ensure task processes stopped because they ran out
+    // of time, which count as failed however they exit
+    let mut timed_out: HashSet<u64> = HashSet::new();
+
+    // Ensure tasks re-run because of a change, whose dependents restart once
+    // they succeed
+    let mut rerun_dependents: HashSet<String> = HashSet::new();
+
+    // Tasks whose processes are being stopped in the background, what happens
+    // once they're gone, and the stops themselves, which exiting waits for
+    let mut stopping: HashMap<PanelIndex, (AfterStop, tokio::task::JoinHandle<()>)> =
+        HashMap::new();
+
+    // Ensure tasks being checked for whether they're up to date before they
+    // start, and those found not to be, which start without another check
+    let mut freshness_checks: HashSet<String> = HashSet::new();
+    let mut out_of_date: HashSet<String> = HashSet::new();
+
     let mut active = PanelIndex::new(0);
     let mut showing_status = true;
     let mut prev_statuses_storage: Option<Vec<ProcessStatus>> = None;
@@ -284,29 +897,70 @@ pub async fn run_with_input(
         }
     });
 
+    // Input mode, if keys are being sent to the focused task. The keyboard
+    // task switches it as soon as it reads the key, so the keys typed after
+    // it go to the right place, and the event loop turns it off again if the
+    // task can't take input.
+    let mut input: Option<Input> = None;
+    let input_mode: std::sync::Arc<std::sync::Mutex<Option<InputMode>>> = Default::default();
+
     // keyboard - spawn if we created internal_tx (i.e., no external input)
     let keyboard_shutdown = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
-    let keyboard_task = if external_rx.is_none() {
+    if external_rx.is_none() {
         let tx_kb = tx.clone();
         let shutdown_flag = keyboard_shutdown.clone();
-        Some(tokio::spawn(async move {
+        let input_mode = input_mode.clone();
+        // Reading the terminal blocks, so it gets a thread of its own. It is
+        // never waited for: if the terminal is closed, crossterm keeps trying
+        // to read it, and the SIGHUP handler shuts rote down instead.
+        std::thread::spawn(move || {
             loop {
                 // Check shutdown flag before polling
                 if shutdown_flag.load(std::sync::atomic::Ordering::Relaxed) {
                     break;
                 }
-                let poll_result = event::poll(Duration::from_millis(KEYBOARD_POLL_INTERVAL_MS));
-                match poll_result {
+                match event::poll(Duration::from_millis(KEYBOARD_POLL_INTERVAL_MS)) {
                     Ok(true) => {
-                        if let Ok(Event::Key(k)) = event::read() {
-                            let ev = match k.code {
+                        let k = match event::read() {
+                            Ok(Event::Key(k)) => k,
+                            Ok(Event::Resize(..)) => {
+                                let _ = tx_kb.blocking_send(UiEvent::Resize);
+                                continue;
+                            }
+                            Ok(_) => continue,
+                            Err(_) => {
+                                // The terminal went away, which counts as quitting
+                                let _ = tx_kb.blocking_send(UiEvent::Exit);
+                                break;
+                            }
+                        };
+                        // While in input mode, keys go to the focused task
+                        let mut mode = input_mode.lock().unwrap();
+                        let ev = if let Some(current) = *mode {
+                            if is_leave_key(current, &k) {
+                                *mode = None;
+                                UiEvent::LeaveInputMode
+                            } else {
+                                UiEvent::Input(k)
+                            }
+                        } else {
+                            match k.code {
                                 KeyCode::Char('q') => UiEvent::Exit,
                                 KeyCode::Char('r') => UiEvent::Restart,
+                                KeyCode::Char('R') => UiEvent::ReloadConfig,
                                 KeyCode::Char('t') => UiEvent::Stop,
                                 KeyCode::Char('o') => UiEvent::ToggleStdout,
                                 KeyCode::Char('e') => UiEvent::ToggleStderr,
                                 KeyCode::Char('h') => UiEvent::ToggleHealthcheck,
                                 KeyCode::Char('s') => UiEvent::SwitchToStatus,
+                                KeyCode::Char('i') => {
+                                    *mode = Some(InputMode::Line);
+                                    UiEvent::EnterInputMode(InputMode::Line)
+                                }
+                                KeyCode::Char('I') => {
+                                    *mode = Some(InputMode::Raw);
+                                    UiEvent::EnterInputMode(InputMode::Raw)
+                                }
                                 KeyCode::Char(c @ '1'..='9') => {
                                     UiEvent::SwitchPanel(PanelIndex::new((c as u8 - b'1') as usize))
                                 }
@@ -317,24 +971,26 @@ pub async fn run_with_input(
                                 KeyCode::Left => UiEvent::PrevPanel,
                                 KeyCode::Right => UiEvent::NextPanel,
                                 _ => continue,
-                            };
-                            // Ignore send errors - if channel is closed, we're shutting down
-                            let _ = tx_kb.send(ev).await;
-                        }
+                            }
+                        };
+                        drop(mode);
+                        // Ignore send errors - if channel is closed, we're shutting down
+                        let _ = tx_kb.blocking_send(ev);
+                    }
+                    Ok(false) => {} // No event available
+                    Err(_) => {
+                        let _ = tx_kb.blocking_send(UiEvent::Exit);
+                        break;
                     }
-                    Ok(false) => {}  // No event available
-                    Err(_) => break, // Terminal error, exit keyboard loop
                 }
             }
-        }))
-    } else {
-        None
-    };
+        });
+    }
 
     if showing_status {
         render::draw_status(&mut terminal, &panels, &status_panel)?;
     } else {
-        render::draw(&mut terminal, &panels[*active], &status_panel)?;
+        render::draw(&mut terminal, &panels[*active], &status_panel, None)?;
     }
 
     loop {
@@ -362,7 +1018,8 @@ pub async fn run_with_input(
                 let at_bottom = p.follow;
 
                 let kind = match stream {
-                    StreamKind::Stdout => MessageKind::Stdout,
+                    // Terminal output is shown and toggled like stdout
+                    StreamKind::Stdout | StreamKind::Terminal => MessageKind::Stdout,
                     StreamKind::Stderr => MessageKind::Stderr,
                 };
                 let timestamp = format_timestamp(p.timestamps);
@@ -379,121 +1036,402 @@ pub async fn run_with_input(
 
             UiEvent::Exited {
                 panel,
+                generation,
                 status,
                 exit_code,
             } => {
-                // Skip if a new process is already running (restart handler already added exit message)
-                let new_process_running = procs[*panel]
-                    .as_ref()
-                    .map(|p| !is_process_exited_by_pid(p.pid))
-                    .unwrap_or(false);
+                let exit_code = if timed_out.remove(&generation) {
+                    None
+                } else {
+                    exit_code
+                };
 
-                if !new_process_running {
-                    let p = &mut panels[*panel];
-                    let was_following = p.follow;
+                // Ignore a process that has already been replaced, or is being
+                // stopped to be replaced, whose exit the restart reports
+                if procs[*panel]
+                    .as_ref()
+                    .is_some_and(|p| p.generation != generation)
+                    || stopping
+                        .get(&panel)
+                        .is_some_and(|(after, _)| *after == AfterStop::Restart)
+                {
+                    continue;
+                }
 
-                    let msg = format!(
-                        "[exited: {}]",
-                        status.map(|s| s.to_string()).unwrap_or("unknown".into())
-                    );
-                    let timestamp = format_timestamp(p.timestamps);
-                    p.messages
-                        .push(MessageKind::Status, &msg, timestamp.as_deref());
+                let p = &mut panels[*panel];
+                let was_following = p.follow;
 
-                    // Update scroll to show the exit message if following
-                    if was_following {
-                        let max_len = p.visible_len();
-                        if max_len > 0 {
-                            p.scroll = max_len - 1;
-                        }
+                let msg = format!(
+                    "[exited: {}]",
+                    status.map(|s| s.to_string()).unwrap_or("unknown".into())
+                );
+                let timestamp = format_timestamp(p.timestamps);
+                p.messages
+                    .push(MessageKind::Status, &msg, timestamp.as_deref());
+
+                // Update scroll to show the exit message if following
+                if was_following {
+                    let max_len = p.visible_len();
+                    if max_len > 0 {
+                        p.scroll = max_len - 1;
                     }
                 }
 
                 status_panel.update_exit_code(panels[*panel].task_name.clone(), exit_code);
 
-                // If this was an Ensure task, mark it as completed and try to start more tasks
                 let task_name = panels[*panel].task_name.clone();
+
+                // A continuous check has nothing left to watch until the task is
+                // started again
+                let continuous_check = config
+                    .tasks
+                    .get(&task_name)
+                    .and_then(|task_config| task_config.healthcheck.as_ref())
+                    .is_some_and(|healthcheck| healthcheck.mode == HealthcheckMode::Continuous);
+                if continuous_check && let Some(hc_task) = healthcheck_tasks.remove(&task_name) {
+                    hc_task.abort();
+                }
+
+                // If the task succeeded, mark it as completed and try to start more tasks
                 if let Some(task_config) = config.tasks.get(&task_name) {
-                    if matches!(task_config.action, Some(TaskAction::Ensure { .. })) {
-                        // Only mark as completed if it succeeded
+                    if exit_code == Some(0) {
+                        task_manager.mark_completed(&task_name);
+                        let _ = tx.send(UiEvent::StartNextTask).await;
+                    }
+
+                    // Remember what a successful run was based on, so an
+                    // unchanged task can be skipped next time
+                    if matches!(task_config.action, Some(TaskAction::Ensure { .. }))
+                        && task_config.tracks_freshness()
+                    {
                         if exit_code == Some(0) {
-                            task_manager.mark_ensure_completed(&task_name);
-                            // Try to start more tasks
-                            let _ = tx.send(UiEvent::StartNextTask).await;
+                            spawn_fingerprint(panel, generation, task_config, &panels[*panel], &tx);
+                        } else if state.fingerprints.remove(&task_name).is_some() {
+                            save_state(&state, &state_path, &mut panels[*panel]);
                         }
                     }
 
-                    // Auto-restart if configured (only for Run tasks, not Ensure tasks)
-                    // Skip if a new process is already running (e.g., manual restart already happened)
-                    let should_auto_restart = task_config.autorestart
-                        && matches!(task_config.action, Some(TaskAction::Run { .. }))
-                        && procs[*panel]
-                            .as_ref()
-                            .map(|p| is_process_exited_by_pid(p.pid))
-                            .unwrap_or(true);
-
-                    if should_auto_restart {
-                        // Wait for the old process to fully clean up
-                        if let Some(proc) = procs[*panel].take() {
-                            let _ = proc.wait_task.await;
-                            let _ = proc.stdout_task.await;
-                            let _ = proc.stderr_task.await;
+                    // A re-run triggered by `watch` passes its changes on to the
+                    // tasks that depend on it, unless it failed
+                    if rerun_dependents.remove(&task_name) && exit_code == Some(0) {
+                        for dependent in task_manager.dependents(&task_name, &config) {
+                            if let Some(dependent_panel) = task_manager.get_panel_index(&dependent)
+                                && procs[*dependent_panel].is_some()
+                            {
+                                let _ = tx
+                                    .send(UiEvent::RestartTask {
+                                        panel: dependent_panel,
+                                    })
+                                    .await;
+                            }
                         }
+                    }
 
-                        // Cancel any existing healthcheck for this task
-                        if let Some(hc_task) = healthcheck_tasks.remove(&task_name) {
-                            hc_task.abort();
-                        }
+                    // Restart according to the task's policy (only for Run tasks, not
+                    // Ensure tasks). Skip if the process was stopped by hand, which
+                    // takes it out of `procs`.
+                    let uptime = procs[*panel].as_ref().map(|p| p.started_at.elapsed());
+
+                    if matches!(task_config.action, Some(TaskAction::Run { .. }))
+                        && task_config.restart.should_restart(exit_code)
+                        && let Some(uptime) = uptime
+                    {
+                        // Only quick failures count towards the backoff and the
+                        // restart limit
+                        let previous = status_panel.get_entry(&task_name).map_or(0, |e| e.restarts);
+                        let failures = if exit_code == Some(0) || uptime >= RESTART_RESET_AFTER {
+                            0
+                        } else {
+                            previous + 1
+                        };
+
+                        let msg = if failures > task_config.max_restarts() {
+                            status_panel.set_crash_looping(&task_name);
+                            format!("[crash looping: gave up after {previous} restarts]")
+                        } else {
+                            status_panel.update_restarts(&task_name, failures);
+                            let delay = task_config.restart_delay(failures);
+                            let tx = tx.clone();
+                            tokio::spawn(async move {
+                                tokio::time::sleep(delay).await;
+                                let _ = tx.send(UiEvent::AutoRestart { panel }).await;
+                            });
+                            format!("[restarting in {:.1}s]", delay.as_secs_f64())
+                        };
 
                         let p = &mut panels[*panel];
-                        let was_following = p.follow;
                         let timestamp = format_timestamp(p.timestamps);
-                        p.messages.push(
-                            MessageKind::Status,
-                            "[auto-restarting]",
-                            timestamp.as_deref(),
-                        );
-                        let max_len = p.visible_len();
-                        if max_len > 0 && was_following {
-                            p.scroll = max_len - 1;
+                        p.messages
+                            .push(MessageKind::Status, &msg, timestamp.as_deref());
+                        if p.follow {
+                            p.scroll = p.visible_len().saturating_sub(1);
                         }
-                        p.follow = was_following;
-
-                        let cwd = panels[*panel].cwd.as_deref();
-                        match TaskInstance::spawn(
-                            panel,
-                            &panels[*panel].cmd,
-                            cwd,
-                            tx.clone(),
-                            shutdown_tx.subscribe(),
-                        ) {
-                            Ok(proc) => {
-                                procs[*panel] = Some(proc);
-                                status_panel
-                                    .update_entry(task_name.clone(), ProcessStatus::Running);
-
-                                // Spawn healthcheck task if configured
-                                if let Some(healthcheck) = &task_config.healthcheck {
-                                    // Reset healthcheck status to pending
-                                    status_panel.set_has_healthcheck(&task_name);
-
-                                    let hc_task = spawn_healthcheck(
-                                        task_name.clone(),
-                                        healthcheck.clone(),
-                                        tx.clone(),
-                                        shutdown_tx.subscribe(),
+                    }
+
+                    // Retry failed Ensure tasks, which share the restart machinery
+                    let mut retrying = false;
+                    if matches!(task_config.action, Some(TaskAction::Ensure { .. })) {
+                        if exit_code == Some(0) || uptime.is_none() {
+                            ensure_attempts.remove(&task_name);
+                        } else if task_config.retries > 0 {
+                            let attempt = ensure_attempts.get(&task_name).copied().unwrap_or(1);
+                            retrying = attempt <= task_config.retries;
+                            let msg = if !retrying {
+                                ensure_attempts.remove(&task_name);
+                                format!("[failed after {attempt} attempts]")
+                            } else {
+                                ensure_attempts.insert(task_name.clone(), attempt + 1);
+                                let delay = task_config.retry_delay();
+                                let tx = tx.clone();
+                                tokio::spawn(async move {
+                                    tokio::time::sleep(delay).await;
+                                    let _ = tx.send(UiEvent::AutoRestart { panel }).await;
+                                });
+                                format!("[retrying in {:.1}s]", delay.as_secs_f64())
+                            };
+
+                            let p = &mut panels[*panel];
+                            let timestamp = format_timestamp(p.timestamps);
+                            p.messages
+                                .push(MessageKind::Status, &msg, timestamp.as_deref());
+                            if p.follow {
+                                p.scroll = p.visible_len().saturating_sub(1);
+                            }
+                        }
+                    }
+
+                    // Tasks waiting for an Ensure task that failed for good
+                    // would never start, so say so
+                    if matches!(task_config.action, Some(TaskAction::Ensure { .. })) {
+                        if exit_code == Some(0) {
+                            status_panel.clear_blocked_by(&task_name);
+                        } else if !retrying {
+                            for dependent in task_manager.pending_dependents(&task_name, &config) {
+                                status_panel.set_blocked(&dependent, Some(task_name.clone()));
+                            }
+                        }
+                    }
+                }
+
+                redraw = true;
+            }
+
+            UiEvent::AutoRestart { panel } => {
+                // Skip if the task was stopped or restarted by hand while waiting.
+                // Otherwise stop anything the old process left running in its
+                // group, which would hold on to its ports and output, and start
+                // the task again once it has cleaned up.
+                let still_exited = procs[*panel].as_ref().is_some_and(|p| p.has_exited());
+                if still_exited && let Some(proc) = procs[*panel].take() {
+                    let stop = spawn_stop(panel, proc, &tx);
+                    stopping.insert(panel, (AfterStop::AutoRestart, stop));
+                }
+            }
+
+            UiEvent::FilesChanged { task_name, paths } => {
+                // Tasks that haven't started yet or were stopped by hand stay put
+                let skipped = status_panel
+                    .get_entry(&task_name)
+                    .is_some_and(|entry| entry.skipped);
+                if let Some(panel_idx) = task_manager.get_panel_index(&task_name)
+                    && (procs[*panel_idx].is_some() || skipped)
+                {
+                    let p = &mut panels[*panel_idx];
+                    let timestamp = format_timestamp(p.timestamps);
+                    p.messages.push(
+                        MessageKind::Status,
+                        &describe_changes(&paths),
+                        timestamp.as_deref(),
+                    );
+                    if p.follow {
+                        p.scroll = p.visible_len().saturating_sub(1);
+                    }
+
+                    if let Some(task_config) = config.tasks.get(&task_name)
+                        && matches!(task_config.action, Some(TaskAction::Ensure { .. }))
+                    {
+                        rerun_dependents.insert(task_name.clone());
+                    }
+                    let _ = tx.send(UiEvent::RestartTask { panel: panel_idx }).await;
+                    redraw = true;
+                }
+            }
+
+            UiEvent::TimedOut { panel, generation } => {
+                // Skip if the process already exited or was replaced
+                let task_name = panels[*panel].task_name.clone();
+                if let Some(proc) = &procs[*panel]
+                    && proc.generation == generation
+                    && !proc.has_exited()
+                    && let Some(timeout) = config.tasks.get(&task_name).and_then(|c| c.timeout)
+                {
+                    let p = &mut panels[*panel];
+                    let timestamp = format_timestamp(p.timestamps);
+                    p.messages.push(
+                        MessageKind::Status,
+                        &format!("[timed out after {}s]", timeout.as_secs_f64()),
+                        timestamp.as_deref(),
+                    );
+                    if p.follow {
+                        p.scroll = p.visible_len().saturating_sub(1);
+                    }
+
+                    // The task's exit is handled as usual once it's gone
+                    timed_out.insert(generation);
+                    let stop = proc.stop_handle();
+                    tokio::spawn(async move { stop.terminate().await });
+                    redraw = true;
+                }
+            }
+
+            UiEvent::ReloadConfig => {
+                let Some(source) = &mut source else {
+                    continue;
+                };
+
+                match reload_config(source, &tasks_to_run) {
+                    // Keep running the old config until the new one loads
+                    Err(e) => status_panel.config_error = Some(e),
+                    Ok((loaded, new_panels, new_tasks_list)) => {
+                        status_panel.config_error = None;
+
+                        // Stop the tasks that were removed and retire their panels
+                        let removed: Vec<(String, PanelIndex)> = task_to_panel
+                            .iter()
+                            .filter(|(name, _)| !new_panels.iter().any(|p| &p.task_name == *name))
+                            .map(|(name, panel_idx)| (name.clone(), *panel_idx))
+                            .collect();
+                        for (task_name, panel_idx) in removed {
+                            if let Some(hc_task) = healthcheck_tasks.remove(&task_name) {
+                                hc_task.abort();
+                            }
+                            watchers.remove(&task_name);
+                            rerun_dependents.remove(&task_name);
+                            // A panel with a process stays until it's gone
+                            if let Some(proc) = procs[*panel_idx].take() {
+                                let stop = spawn_stop(panel_idx, proc, &tx);
+                                stopping.insert(panel_idx, (AfterStop::Retire, stop));
+                            } else if let Some((after, _)) = stopping.get_mut(&panel_idx) {
+                                *after = AfterStop::Retire;
+                            } else {
+                                panels[*panel_idx].retired = true;
+                            }
+                            task_to_panel.remove(&task_name);
+                            status_panel.remove_entry(&task_name);
+                        }
+
+                        // Update the remaining tasks and add the new ones
+                        for new_panel in new_panels {
+                            let task_name = new_panel.task_name.clone();
+                            let task_config = &loaded.config.tasks[&task_name];
+                            let panel_idx = match task_to_panel.get(&task_name).copied() {
+                                Some(panel_idx) => {
+                                    let panel = &mut panels[*panel_idx];
+                                    let changed = config.tasks.get(&task_name) != Some(task_config)
+                                        || panel.cmd != new_panel.cmd
+                                        || panel.cwd != new_panel.cwd
+                                        || panel.env != new_panel.env;
+                                    if changed {
+                                        panel.cmd = new_panel.cmd;
+                                        panel.cwd = new_panel.cwd;
+                                        panel.env = new_panel.env;
+                                        panel.stop_policy = new_panel.stop_policy;
+                                        panel.stdin = new_panel.stdin;
+                                        panel.tty = new_panel.tty;
+                                        panel.show_stdout = new_panel.show_stdout;
+                                        panel.show_stderr = new_panel.show_stderr;
+                                        panel.timestamps = new_panel.timestamps;
+                                        let status = status_panel
+                                            .get_entry(&task_name)
+                                            .map_or(ProcessStatus::NotStarted, |e| e.status);
+                                        update_status_entry(
+                                            &mut status_panel,
+                                            &task_name,
+                                            task_config,
+                                            status,
+                                        );
+                                        watchers.remove(&task_name);
+
+                                        // Tasks that have run (or were skipped) pick
+                                        // up the change by restarting
+                                        let skipped = status_panel
+                                            .get_entry(&task_name)
+                                            .is_some_and(|entry| entry.skipped);
+                                        if procs[*panel_idx].is_some() || skipped {
+                                            let timestamp = format_timestamp(panel.timestamps);
+                                            panel.messages.push(
+                                                MessageKind::Status,
+                                                "[config changed]",
+                                                timestamp.as_deref(),
+                                            );
+                                            let _ = tx
+                                                .send(UiEvent::RestartTask { panel: panel_idx })
+                                                .await;
+                                        }
+                                    }
+                                    panel_idx
+                                }
+                                None => {
+                                    let panel_idx = PanelIndex::new(panels.len());
+                                    panels.push(new_panel);
+                                    procs.push(None);
+                                    task_to_panel.insert(task_name.clone(), panel_idx);
+                                    update_status_entry(
+                                        &mut status_panel,
+                                        &task_name,
+                                        task_config,
+                                        ProcessStatus::NotStarted,
+                                    );
+                                    panel_idx
+                                }
+                            };
+
+                            let wants_watcher = new_tasks_list.contains(&task_name)
+                                && !task_config.watch.is_empty();
+                            if !wants_watcher {
+                                watchers.remove(&task_name);
+                            } else if !watchers.contains_key(&task_name)
+                                && let Some(watcher) =
+                                    start_watcher(&mut panels[*panel_idx], task_config, tx.clone())
+                            {
+                                watchers.insert(task_name, watcher);
+                            }
+                        }
+
+                        // Start the tasks the new config selects that weren't
+                        // selected before
+                        task_manager.reload(new_tasks_list.clone(), task_to_panel.clone());
+                        for panel in panels.iter().filter(|panel| !panel.retired) {
+                            let Some(entry) = status_panel.get_entry(&panel.task_name) else {
+                                continue;
+                            };
+                            let selected = new_tasks_list.contains(&panel.task_name);
+                            match entry.status {
+                                ProcessStatus::NotStarted if selected => status_panel
+                                    .update_entry(panel.task_name.clone(), ProcessStatus::Pending),
+                                ProcessStatus::Pending | ProcessStatus::Blocked if !selected => {
+                                    status_panel.set_blocked(&panel.task_name, None);
+                                    status_panel.update_entry(
+                                        panel.task_name.clone(),
+                                        ProcessStatus::NotStarted,
                                     );
-                                    healthcheck_tasks.insert(task_name.clone(), hc_task);
                                 }
+                                _ => {}
                             }
-                            Err(e) => {
-                                let timestamp = format_timestamp(panels[*panel].timestamps);
-                                panels[*panel].messages.push(
-                                    MessageKind::Status,
-                                    &format!("[auto-restart failed: {e}]"),
-                                    timestamp.as_deref(),
-                                );
-                            }
+                        }
+                        let _ = tx.send(UiEvent::StartNextTask).await;
+
+                        if source.files != loaded.files {
+                            config_watcher = spawn_config_watcher(&loaded.files, tx.clone())
+                                .map_err(|e| status_panel.config_error = Some(format!("{e:#}")))
+                                .ok();
+                            source.files = loaded.files;
+                        }
+                        config = loaded.config;
+                        tasks_list = new_tasks_list;
+
+                        if panels[*active].retired {
+                            showing_status = true;
                         }
                     }
                 }
@@ -534,10 +1472,13 @@ pub async fn run_with_input(
                 redraw = true;
             }
 
-            UiEvent::SwitchPanel(i) if *i < panels.len() => {
-                active = i;
-                showing_status = false;
-                redraw = true;
+            UiEvent::SwitchPanel(i) => {
+                // Number keys count the panels still in the config
+                if let Some(&panel_idx) = live_panels(&panels).get(*i) {
+                    active = panel_idx;
+                    showing_status = false;
+                    redraw = true;
+                }
             }
 
             UiEvent::SwitchToStatus => {
@@ -546,35 +1487,39 @@ pub async fn run_with_input(
             }
 
             UiEvent::PrevPanel => {
-                if showing_status {
+                let live = live_panels(&panels);
+                let position = live.iter().position(|&i| i == active);
+                match position {
                     // Wrap from status to last panel
-                    if !panels.is_empty() {
-                        active = PanelIndex::new(panels.len() - 1);
-                        showing_status = false;
+                    _ if showing_status => {
+                        if let Some(&last) = live.last() {
+                            active = last;
+                            showing_status = false;
+                        }
                     }
-                } else if *active == 0 {
-                    // Go from first panel to status
-                    showing_status = true;
-                } else {
                     // Go to previous panel
-                    active = PanelIndex::new(*active - 1);
+                    Some(position) if position > 0 => active = live[position - 1],
+                    // Go from first panel to status
+                    _ => showing_status = true,
                 }
                 redraw = true;
             }
 
             UiEvent::NextPanel => {
-                if showing_status {
+                let live = live_panels(&panels);
+                let position = live.iter().position(|&i| i == active);
+                match position {
                     // Go from status to first panel
-                    if !panels.is_empty() {
-                        active = PanelIndex::new(0);
-                        showing_status = false;
+                    _ if showing_status => {
+                        if let Some(&first) = live.first() {
+                            active = first;
+                            showing_status = false;
+                        }
                     }
-                } else if *active >= panels.len() - 1 {
-                    // Go from last panel to status
-                    showing_status = true;
-                } else {
                     // Go to next panel
-                    active = PanelIndex::new(*active + 1);
+                    Some(position) if position + 1 < live.len() => active = live[position + 1],
+                    // Go from last panel to status
+                    _ => showing_status = true,
                 }
                 redraw = true;
             }
@@ -587,7 +1532,7 @@ pub async fn run_with_input(
                         .iter()
                         .map(|p| {
                             if tasks_list.contains(&p.task_name) {
-                                ProcessStatus::Running
+                                ProcessStatus::Pending
                             } else {
                                 ProcessStatus::NotStarted
                             }
@@ -595,19 +1540,30 @@ pub async fn run_with_input(
                         .collect();
                 }
 
+                // Panels added by a reload haven't been started
+                prev_statuses.resize(procs.len(), ProcessStatus::NotStarted);
+
                 let mut any_change = false;
 
                 for (i, proc) in procs.iter_mut().enumerate() {
+                    if panels[i].retired {
+                        continue;
+                    }
+
                     let current_status = if let Some(p) = proc {
-                        if p.pid.is_none() || is_process_exited_by_pid(p.pid) {
+                        if p.pid.is_none() || p.has_exited() {
                             ProcessStatus::Exited
                         } else {
                             ProcessStatus::Running
                         }
                     } else {
-                        // Preserve NotStarted status for tasks that were never started
+                        // Preserve the status of tasks that were never started
                         match prev_statuses.get(i) {
-                            Some(ProcessStatus::NotStarted) => ProcessStatus::NotStarted,
+                            Some(
+                                status @ (ProcessStatus::NotStarted
+                                | ProcessStatus::Pending
+                                | ProcessStatus::Blocked),
+                            ) => *status,
                             _ => ProcessStatus::Exited,
                         }
                     };
@@ -617,11 +1573,7 @@ pub async fn run_with_input(
                         status_panel.update_entry(panels[i].task_name.clone(), current_status);
                     }
 
-                    if i >= prev_statuses.len() {
-                        prev_statuses.push(current_status);
-                    } else {
-                        prev_statuses[i] = current_status;
-                    }
+                    prev_statuses[i] = current_status;
                 }
 
                 if any_change {
@@ -631,225 +1583,283 @@ pub async fn run_with_input(
                 prev_statuses_storage = Some(prev_statuses);
             }
 
-            UiEvent::Restart => {
-                // Check if task was NotStarted before we potentially terminate it
+            UiEvent::Restart | UiEvent::RestartTask { .. } => {
+                // `r` restarts the task that is being shown
+                let target = match ev {
+                    UiEvent::RestartTask { panel } => panel,
+                    _ => active,
+                };
+                if panels[*target].retired {
+                    continue;
+                }
+
+                // A task that is being stopped starts again once it's gone,
+                // unless it was removed from the config
+                if let Some((after, _)) = stopping.get_mut(&target) {
+                    if *after != AfterStop::Retire {
+                        *after = AfterStop::Restart;
+                    }
+                    continue;
+                }
+
+                // Stop the running process first, and start the task again
+                // once it's gone
+                if let Some(proc) = procs[*target].take() {
+                    if let Some(hc_task) = healthcheck_tasks.remove(&panels[*target].task_name) {
+                        hc_task.abort();
+                    }
+                    let stop = spawn_stop(target, proc, &tx);
+                    stopping.insert(target, (AfterStop::Restart, stop));
+                    continue;
+                }
+
+                // Check if task hadn't started before
                 let was_not_started = status_panel
                     .entries
                     .iter()
-                    .find(|e| e.task_name == panels[*active].task_name)
-                    .map(|e| e.status == ProcessStatus::NotStarted)
+                    .find(|e| e.task_name == panels[*target].task_name)
+                    .map(|e| {
+                        matches!(
+                            e.status,
+                            ProcessStatus::NotStarted
+                                | ProcessStatus::Pending
+                                | ProcessStatus::Blocked
+                        )
+                    })
                     .unwrap_or(false);
 
-                if let Some(proc) = procs[*active].take() {
-                    // Get exit status Arc before awaiting (which partially moves proc)
-                    let exit_status_arc = proc.exit_status_arc();
-
-                    proc.terminate().await;
-                    // Wait for the process to fully exit and all I/O to drain
-                    let _ = proc.wait_task.await;
-                    let _ = proc.stdout_task.await;
-                    let _ = proc.stderr_task.await;
-
-                    // Add exit message before restart message
-                    let exit_status = exit_status_arc.lock().unwrap();
-                    if let Some(Ok(status)) = exit_status.as_ref() {
-                        use std::os::unix::process::ExitStatusExt;
-                        let exit_code = status.code().or_else(|| status.signal().map(|s| 128 + s));
-                        let msg = format!(
-                            "[exited: {}]",
-                            exit_code
-                                .map(|c| c.to_string())
-                                .unwrap_or_else(|| "unknown".into())
-                        );
-                        let timestamp = format_timestamp(panels[*active].timestamps);
-                        panels[*active].messages.push(
-                            MessageKind::Status,
-                            &msg,
-                            timestamp.as_deref(),
-                        );
-                    }
-                }
-
-                let was_following = panels[*active].follow;
-                let timestamp = format_timestamp(panels[*active].timestamps);
+                let was_following = panels[*target].follow;
+                let timestamp = format_timestamp(panels[*target].timestamps);
                 let status_msg = if was_not_started {
                     "[starting]"
                 } else {
                     "[restarting]"
                 };
-                panels[*active].messages.push(
+                panels[*target].messages.push(
                     MessageKind::Status,
                     status_msg,
                     timestamp.as_deref(),
                 );
-                let max_len = panels[*active].visible_len();
+                let max_len = panels[*target].visible_len();
                 if max_len > 0 && was_following {
-                    panels[*active].scroll = max_len - 1;
+                    panels[*target].scroll = max_len - 1;
                 }
-                panels[*active].follow = was_following;
+                panels[*target].follow = was_following;
 
-                let task_name = panels[*active].task_name.clone();
+                let task_name = panels[*target].task_name.clone();
+                status_panel.update_restarts(&task_name, 0);
+                status_panel.clear_blocked_by(&task_name);
+                ensure_attempts.remove(&task_name);
+                status_panel.set_skipped(&task_name, false);
 
                 // Cancel any existing healthcheck for this task
                 if let Some(hc_task) = healthcheck_tasks.remove(&task_name) {
                     hc_task.abort();
                 }
 
-                let cwd = panels[*active].cwd.as_deref();
-                match TaskInstance::spawn(
-                    active,
-                    &panels[*active].cmd,
-                    cwd,
-                    tx.clone(),
-                    shutdown_tx.subscribe(),
+                let Some(task_config) = config.tasks.get(&task_name) else {
+                    continue;
+                };
+                let ctx = SpawnContext {
+                    tx: &tx,
+                    shutdown_tx: &shutdown_tx,
+                    pid_path: &pid_path,
+                    terminal_size,
+                };
+                if let Err(e) = start_task(
+                    target,
+                    &panels[*target],
+                    task_config,
+                    &mut procs,
+                    &mut status_panel,
+                    &mut healthcheck_tasks,
+                    &ctx,
                 ) {
-                    Ok(proc) => {
-                        procs[*active] = Some(proc);
-
-                        // Spawn healthcheck task if configured
-                        if let Some(task_config) = config.tasks.get(&task_name)
-                            && let Some(healthcheck) = &task_config.healthcheck
-                        {
-                            // Reset healthcheck status to pending
-                            status_panel.set_has_healthcheck(&task_name);
-
-                            let hc_task = spawn_healthcheck(
-                                task_name.clone(),
-                                healthcheck.clone(),
-                                tx.clone(),
-                                shutdown_tx.subscribe(),
-                            );
-                            healthcheck_tasks.insert(task_name, hc_task);
-                        }
-                    }
-                    Err(e) => {
-                        let timestamp = format_timestamp(panels[*active].timestamps);
-                        panels[*active].messages.push(
-                            MessageKind::Status,
-                            &format!("[spawn failed: {e}]"),
-                            timestamp.as_deref(),
-                        );
-                    }
+                    push_status_message(&mut panels[*target], &format!("[spawn failed: {e}]"));
                 }
                 redraw = true;
             }
 
             UiEvent::Stop => {
-                if let Some(proc) = procs[*active].take() {
-                    // Get exit status Arc before awaiting (which partially moves proc)
-                    let exit_status_arc = proc.exit_status_arc();
-
-                    proc.terminate().await;
-                    // Wait for the process to fully exit and all I/O to drain
-                    let _ = proc.wait_task.await;
-                    let _ = proc.stdout_task.await;
-                    let _ = proc.stderr_task.await;
-
-                    // Add exit message
-                    let exit_status = exit_status_arc.lock().unwrap();
-                    if let Some(Ok(status)) = exit_status.as_ref() {
-                        use std::os::unix::process::ExitStatusExt;
-                        let exit_code = status.code().or_else(|| status.signal().map(|s| 128 + s));
-                        let msg = format!(
-                            "[stopped: {}]",
-                            exit_code
-                                .map(|c| c.to_string())
-                                .unwrap_or_else(|| "unknown".into())
+                // Don't let a continuous check restart a task that was stopped on purpose
+                if let Some(hc_task) = healthcheck_tasks.remove(&panels[*active].task_name) {
+                    hc_task.abort();
+                }
+
+                // A task that is being stopped to start it again stays stopped
+                if let Some((after, _)) = stopping.get_mut(&active) {
+                    if *after != AfterStop::Retire {
+                        *after = AfterStop::Stay;
+                    }
+                } else if let Some(proc) = procs[*active].take() {
+                    let stop = spawn_stop(active, proc, &tx);
+                    stopping.insert(active, (AfterStop::Stay, stop));
+                }
+            }
+
+            UiEvent::Stopped { panel, exit_code } => {
+                let Some((after, _)) = stopping.remove(&panel) else {
+                    continue;
+                };
+                let task_name = panels[*panel].task_name.clone();
+                let exit_code = exit_code
+                    .map(|c| c.to_string())
+                    .unwrap_or_else(|| "unknown".into());
+
+                match after {
+                    AfterStop::Stay => {
+                        let p = &mut panels[*panel];
+                        let timestamp = format_timestamp(p.timestamps);
+                        p.messages.push(
+                            MessageKind::Status,
+                            &format!("[stopped: {exit_code}]"),
+                            timestamp.as_deref(),
                         );
-                        let timestamp = format_timestamp(panels[*active].timestamps);
-                        panels[*active].messages.push(
+                        if p.follow {
+                            p.scroll = p.visible_len().saturating_sub(1);
+                        }
+                    }
+                    AfterStop::Restart => {
+                        // Add exit message before restart message
+                        let p = &mut panels[*panel];
+                        let timestamp = format_timestamp(p.timestamps);
+                        p.messages.push(
                             MessageKind::Status,
-                            &msg,
+                            &format!("[exited: {exit_code}]"),
                             timestamp.as_deref(),
                         );
+                        let _ = tx.send(UiEvent::RestartTask { panel }).await;
                     }
+                    AfterStop::AutoRestart => {
+                        if let Some(task_config) = config.tasks.get(&task_name) {
+                            // Cancel any existing healthcheck for this task
+                            if let Some(hc_task) = healthcheck_tasks.remove(&task_name) {
+                                hc_task.abort();
+                            }
 
-                    // Update scroll if following
-                    let was_following = panels[*active].follow;
-                    let max_len = panels[*active].visible_len();
-                    if max_len > 0 && was_following {
-                        panels[*active].scroll = max_len - 1;
-                    }
-                    panels[*active].follow = was_following;
+                            // Ensure tasks are being retried rather than restarted
+                            let msg = match ensure_attempts.get(&task_name) {
+                                Some(attempt)
+                                    if matches!(
+                                        task_config.action,
+                                        Some(TaskAction::Ensure { .. })
+                                    ) =>
+                                {
+                                    format!("[attempt {attempt}/{}]", task_config.retries + 1)
+                                }
+                                _ => "[auto-restarting]".to_string(),
+                            };
+                            let p = &mut panels[*panel];
+                            let was_following = p.follow;
+                            let timestamp = format_timestamp(p.timestamps);
+                            p.messages
+                                .push(MessageKind::Status, &msg, timestamp.as_deref());
+                            let max_len = p.visible_len();
+                            if max_len > 0 && was_following {
+                                p.scroll = max_len - 1;
+                            }
+                            p.follow = was_following;
 
-                    redraw = true;
+                            let ctx = SpawnContext {
+                                tx: &tx,
+                                shutdown_tx: &shutdown_tx,
+                                pid_path: &pid_path,
+                                terminal_size,
+                            };
+                            if let Err(e) = start_task(
+                                panel,
+                                &panels[*panel],
+                                task_config,
+                                &mut procs,
+                                &mut status_panel,
+                                &mut healthcheck_tasks,
+                                &ctx,
+                            ) {
+                                push_status_message(
+                                    &mut panels[*panel],
+                                    &format!("[auto-restart failed: {e}]"),
+                                );
+                            }
+                        }
+                    }
+                    AfterStop::Retire => {
+                        panels[*panel].retired = true;
+                        if panel == active {
+                            showing_status = true;
+                        }
+                    }
                 }
+
+                redraw = true;
             }
 
             UiEvent::Exit => {
                 // Signal keyboard task to stop and abort status check task
                 keyboard_shutdown.store(true, std::sync::atomic::Ordering::Relaxed);
                 status_check_task.abort();
-
-                // Exit alternate screen immediately so user sees shutdown progress
-                if enable_terminal {
-                    let _ = execute!(io::stdout(), LeaveAlternateScreen);
-                    let _ = disable_raw_mode();
-                }
+                signal_task.abort();
+                // Stop watching, so changes made while shutting down don't
+                // restart anything
+                drop(config_watcher.take());
+                watchers.clear();
 
                 // Ignore send errors - if all receivers are gone, shutdown proceeds anyway
                 let _ = shutdown_tx.send(());
 
-                // Collect running tasks
-                let mut running_tasks: Vec<String> = procs
-                    .iter()
-                    .enumerate()
-                    .filter_map(|(i, p)| {
-                        if p.is_some() {
-                            Some(panels[i].task_name.clone())
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-
-                // Helper to print current status (uses ANSI escape to clear to end of line)
-                let print_status = |tasks: &[String]| {
-                    if tasks.is_empty() {
-                        print!("\rWaiting for tasks to shut down: (none)\x1b[K");
-                    } else {
-                        print!(
-                            "\rWaiting for tasks to shut down: [{}]\x1b[K",
-                            tasks.join(", ")
-                        );
-                    }
-                    let _ = io::stdout().flush();
-                };
-
-                if enable_terminal && !running_tasks.is_empty() {
-                    print_status(&running_tasks);
-                }
-
-                // Terminate and wait for each process synchronously
+                // Stop all tasks concurrently, so one slow task's stop timeout
+                // doesn't delay the others, and show each task's progress
+                let (stage_tx, mut stage_rx) =
+                    tokio::sync::mpsc::unbounded_channel::<(String, StopStage)>();
+                let mut stop_stages: HashMap<String, StopStage> = HashMap::new();
                 for (i, proc) in procs.iter_mut().enumerate() {
                     if let Some(p) = proc.take() {
-                        p.terminate().await;
-                        let _ = p.wait_task.await;
-                        let _ = p.stdout_task.await;
-                        let _ = p.stderr_task.await;
-
-                        // Remove from running list and update display
-                        if enable_terminal {
-                            running_tasks.retain(|s| s != &panels[i].task_name);
-                            print_status(&running_tasks);
-                        }
+                        let task_name = panels[i].task_name.clone();
+                        let stage_tx = stage_tx.clone();
+                        tokio::spawn(async move {
+                            p.terminate_reporting(|stage| {
+                                let _ = stage_tx.send((task_name.clone(), stage));
+                            })
+                            .await;
+                            let _ = p.wait_task.await;
+                            // Output stays open if the task left background
+                            // processes behind, so don't wait for it forever
+                            for mut reader in std::iter::once(p.stdout_task).chain(p.stderr_task) {
+                                if tokio::time::timeout(OUTPUT_DRAIN_TIMEOUT, &mut reader)
+                                    .await
+                                    .is_err()
+                                {
+                                    reader.abort();
+                                }
+                            }
+                            let _ = stage_tx.send((task_name, StopStage::Exited));
+                        });
                     }
                 }
+                drop(stage_tx);
 
-                // Abort keyboard task if it's still running
-                if let Some(ref task) = keyboard_task {
-                    task.abort();
+                // The terminal may be gone, which mustn't stop the shutdown
+                if enable_terminal {
+                    let _ = render::draw_shutdown(&mut terminal, &status_panel, &stop_stages);
+                }
+                while let Some((task_name, stage)) = stage_rx.recv().await {
+                    stop_stages.insert(task_name, stage);
+                    if enable_terminal {
+                        let _ = render::draw_shutdown(&mut terminal, &status_panel, &stop_stages);
+                    }
+                }
+                // Tasks that were already being stopped are waited for too.
+                // Nothing is left to tell when they're gone.
+                internal_rx.close();
+                for (_, (_, stop)) in stopping.drain() {
+                    let _ = stop.await;
                 }
+                let _ = PidFile::forget_own(&pid_path);
 
                 // Abort any remaining healthcheck tasks
                 for (_, task) in healthcheck_tasks.drain() {
                     task.abort();
                 }
 
-                if enable_terminal {
-                    println!();
-                }
-
                 break;
             }
 
@@ -860,43 +1870,57 @@ pub async fn run_with_input(
 
                 for task_name in ready_tasks {
                     if let Some(panel_idx) = task_manager.get_panel_index(&task_name) {
-                        let panel = &panels[*panel_idx];
-                        let cwd = panel.cwd.as_deref();
-                        match TaskInstance::spawn(
+                        // Ensure tasks that may have nothing new to do start
+                        // once they're known not to be up to date
+                        if let Some(task_config) = config.tasks.get(&task_name)
+                            && matches!(task_config.action, Some(TaskAction::Ensure { .. }))
+                            && (task_config.tracks_freshness() || task_config.skip_if.is_some())
+                            && !out_of_date.remove(&task_name)
+                        {
+                            FreshnessCheck::new(
+                                &task_name,
+                                task_config,
+                                &panels[*panel_idx],
+                                &state,
+                            )
+                            .spawn(task_name.clone(), &tx);
+                            freshness_checks.insert(task_name);
+                            continue;
+                        }
+
+                        let Some(task_config) = config.tasks.get(&task_name) else {
+                            continue;
+                        };
+                        let ctx = SpawnContext {
+                            tx: &tx,
+                            shutdown_tx: &shutdown_tx,
+                            pid_path: &pid_path,
+                            terminal_size,
+                        };
+                        match start_task(
                             panel_idx,
-                            &panel.cmd,
-                            cwd,
-                            tx.clone(),
-                            shutdown_tx.subscribe(),
+                            &panels[*panel_idx],
+                            task_config,
+                            &mut procs,
+                            &mut status_panel,
+                            &mut healthcheck_tasks,
+                            &ctx,
                         ) {
-                            Ok(proc) => {
-                                procs[*panel_idx] = Some(proc);
-                                status_panel
-                                    .update_entry(task_name.clone(), ProcessStatus::Running);
-                                started_any = true;
-
-                                // Spawn healthcheck task if configured
-                                if let Some(task_config) = config.tasks.get(&task_name)
-                                    && let Some(healthcheck) = &task_config.healthcheck
-                                {
-                                    status_panel.set_has_healthcheck(&task_name);
-                                    let hc_task = spawn_healthcheck(
-                                        task_name.clone(),
-                                        healthcheck.clone(),
-                                        tx.clone(),
-                                        shutdown_tx.subscribe(),
-                                    );
-                                    healthcheck_tasks.insert(task_name.clone(), hc_task);
-                                }
-                            }
+                            Ok(()) => started_any = true,
                             Err(e) => {
-                                let timestamp = format_timestamp(panels[*panel_idx].timestamps);
-                                panels[*panel_idx].messages.push(
-                                    MessageKind::Status,
+                                push_status_message(
+                                    &mut panels[*panel_idx],
                                     &format!("[spawn failed: {e}]"),
-                                    timestamp.as_deref(),
                                 );
                                 status_panel.update_entry(task_name.clone(), ProcessStatus::Exited);
+                                if matches!(task_config.action, Some(TaskAction::Ensure { .. })) {
+                                    for dependent in
+                                        task_manager.pending_dependents(&task_name, &config)
+                                    {
+                                        status_panel
+                                            .set_blocked(&dependent, Some(task_name.clone()));
+                                    }
+                                }
                             }
                         }
                     }
@@ -907,10 +1931,74 @@ pub async fn run_with_input(
                 }
             }
 
+            UiEvent::FreshnessChecked {
+                task_name,
+                up_to_date,
+            } => {
+                // Ignore a check for a task that was removed from the config
+                // or started some other way in the meantime
+                let Some(panel_idx) = task_manager.get_panel_index(&task_name) else {
+                    continue;
+                };
+                if !freshness_checks.remove(&task_name) || procs[*panel_idx].is_some() {
+                    continue;
+                }
+
+                let p = &mut panels[*panel_idx];
+                let timestamp = format_timestamp(p.timestamps);
+                match up_to_date {
+                    // Ensure tasks with nothing new to do count as completed
+                    Ok(true) => {
+                        p.messages.push(
+                            MessageKind::Status,
+                            "[skipped: up to date]",
+                            timestamp.as_deref(),
+                        );
+                        status_panel.update_entry(task_name.clone(), ProcessStatus::Exited);
+                        status_panel.set_skipped(&task_name, true);
+                        task_manager.mark_completed(&task_name);
+                    }
+                    Ok(false) | Err(_) => {
+                        if let Err(e) = up_to_date {
+                            p.messages.push(
+                                MessageKind::Status,
+                                &format!("[{e}]"),
+                                timestamp.as_deref(),
+                            );
+                        }
+                        out_of_date.insert(task_name.clone());
+                        task_manager.requeue(&task_name);
+                    }
+                }
+                let _ = tx.send(UiEvent::StartNextTask).await;
+                redraw = true;
+            }
+
+            UiEvent::Fingerprinted {
+                panel,
+                generation,
+                fingerprint,
+            } => {
+                // A later run of the task makes this one's result stale
+                if procs[*panel]
+                    .as_ref()
+                    .is_some_and(|p| p.generation != generation)
+                {
+                    continue;
+                }
+                let task_name = panels[*panel].task_name.clone();
+                match fingerprint {
+                    Some(fingerprint) => state.fingerprints.insert(task_name, fingerprint),
+                    None => state.fingerprints.remove(&task_name),
+                };
+                save_state(&state, &state_path, &mut panels[*panel]);
+            }
+
             UiEvent::HealthcheckPassed { task_name } => {
                 // Mark the task as healthy
                 task_manager.mark_healthy(&task_name);
-                status_panel.update_healthcheck_passed(&task_name);
+                status_panel.update_healthcheck(&task_name, HealthcheckState::Passed);
+                status_panel.clear_blocked_by(&task_name);
 
                 // Log the healthcheck success
                 if let Some(panel_idx) = task_manager.get_panel_index(&task_name) {
@@ -925,9 +2013,6 @@ pub async fn run_with_input(
                 // Always redraw - the status sidebar is always visible
                 redraw = true;
 
-                // Remove the healthcheck task (it has completed)
-                healthcheck_tasks.remove(&task_name);
-
                 // Try to start tasks that were waiting for this healthcheck
                 let _ = tx.send(UiEvent::StartNextTask).await;
             }
@@ -951,17 +2036,22 @@ pub async fn run_with_input(
                 }
             }
 
-            UiEvent::HealthcheckFailed { task_name } => {
+            UiEvent::HealthcheckFailed {
+                task_name,
+                retries_left,
+            } => {
                 // Log the healthcheck failure
                 if let Some(panel_idx) = task_manager.get_panel_index(&task_name) {
                     let p = &mut panels[*panel_idx];
                     let at_bottom = p.follow;
                     let timestamp = format_timestamp(p.timestamps);
-                    p.messages.push(
-                        MessageKind::Healthcheck,
-                        "[healthcheck failed, retrying...]",
-                        timestamp.as_deref(),
-                    );
+                    let msg = match retries_left {
+                        Some(1) => "[healthcheck failed, 1 retry left]".to_string(),
+                        Some(n) => format!("[healthcheck failed, {n} retries left]"),
+                        None => "[healthcheck failed, retrying...]".to_string(),
+                    };
+                    p.messages
+                        .push(MessageKind::Healthcheck, &msg, timestamp.as_deref());
 
                     if at_bottom {
                         p.scroll = p.visible_len().saturating_sub(1);
@@ -973,21 +2063,148 @@ pub async fn run_with_input(
                 }
             }
 
+            UiEvent::HealthcheckUnhealthy { task_name } => {
+                task_manager.mark_unhealthy(&task_name);
+                status_panel.update_healthcheck(&task_name, HealthcheckState::Failed);
+
+                // Tasks waiting for this one would never start, so say so
+                for dependent in task_manager.pending_dependents(&task_name, &config) {
+                    status_panel.set_blocked(&dependent, Some(task_name.clone()));
+                }
+
+                if let Some(panel_idx) = task_manager.get_panel_index(&task_name) {
+                    let p = &mut panels[*panel_idx];
+                    let timestamp = format_timestamp(p.timestamps);
+                    p.messages.push(
+                        MessageKind::Healthcheck,
+                        "[healthcheck failed too many times, task is unhealthy]",
+                        timestamp.as_deref(),
+                    );
+                    if p.follow {
+                        p.scroll = p.visible_len().saturating_sub(1);
+                    }
+
+                    if config
+                        .tasks
+                        .get(&task_name)
+                        .and_then(|task_config| task_config.healthcheck.as_ref())
+                        .is_some_and(|healthcheck| healthcheck.restart_on_unhealthy)
+                    {
+                        let _ = tx.send(UiEvent::RestartTask { panel: panel_idx }).await;
+                    }
+                }
+
+                // Always redraw - the status sidebar is always visible
+                redraw = true;
+            }
+
+            UiEvent::EnterInputMode(mode) => {
+                let proc = procs[*active].as_ref().filter(|p| !p.has_exited());
+                let problem = if !panels[*active].accepts_input() {
+                    Some("[input needs `stdin: pipe` or `tty: true`]")
+                } else if proc.is_none() {
+                    Some("[not running]")
+                } else if !proc.is_some_and(|p| p.accepts_input()) {
+                    Some("[stdin is closed]")
+                } else {
+                    None
+                };
+
+                // The status view has no task to send input to
+                if showing_status || problem.is_some() {
+                    *input_mode.lock().unwrap() = None;
+                    if !showing_status && let Some(msg) = problem {
+                        push_status_message(&mut panels[*active], msg);
+                    }
+                } else {
+                    input = Some(Input::new(mode));
+                }
+                redraw = true;
+            }
+
+            UiEvent::Input(key) => {
+                let Some(current) = &mut input else {
+                    continue;
+                };
+                let mode = current.mode;
+                match current.handle_key(&key) {
+                    None => {}
+                    Some(InputAction::Send(bytes)) => {
+                        let line = String::from_utf8_lossy(&bytes).trim_end().to_string();
+                        let sent = procs[*active]
+                            .as_ref()
+                            .filter(|p| !p.has_exited())
+                            .is_some_and(|p| p.send_input(bytes));
+                        if !sent {
+                            push_status_message(&mut panels[*active], "[stdin is closed]");
+                            input = None;
+                            *input_mode.lock().unwrap() = None;
+                        } else if mode == InputMode::Line && !panels[*active].tty {
+                            // A task reading a pipe doesn't echo what it reads
+                            push_status_message(&mut panels[*active], &format!("[input: {line}]"));
+                        }
+                    }
+                    Some(InputAction::Close) => {
+                        if let Some(p) = procs[*active].as_mut() {
+                            p.close_input();
+                        }
+                        let msg = if panels[*active].tty {
+                            "[sent end of file]"
+                        } else {
+                            "[stdin closed]"
+                        };
+                        push_status_message(&mut panels[*active], msg);
+                        input = None;
+                        *input_mode.lock().unwrap() = None;
+                    }
+                }
+                redraw = true;
+            }
+
+            UiEvent::LeaveInputMode => {
+                input = None;
+                *input_mode.lock().unwrap() = None;
+                redraw = true;
+            }
+
+            UiEvent::Resize => redraw = true,
+
             _ => {}
         }
 
         if redraw {
-            if showing_status {
-                render::draw_status(&mut terminal, &panels, &status_panel)?;
+            // Pseudo-terminals follow the size of the output area, which
+            // shrinks while the input bar is shown
+            let size = render::output_size(terminal.size().unwrap_or_default(), input.is_some());
+            if size != terminal_size {
+                terminal_size = size;
+                for proc in procs.iter().flatten() {
+                    let _ = proc.resize(size);
+                }
+            }
+
+            let drawn = if showing_status {
+                render::draw_status(&mut terminal, &panels, &status_panel)
             } else {
-                render::draw(&mut terminal, &panels[*active], &status_panel)?;
+                render::draw(
+                    &mut terminal,
+                    &panels[*active],
+                    &status_panel,
+                    input.as_ref(),
+                )
+            };
+            // Without a terminal there is nobody left to stop the tasks
+            if drawn.is_err() {
+                let _ = tx.send(UiEvent::Exit).await;
             }
         }
     }
 
     for proc in procs.iter().flatten() {
         proc.stdout_task.abort();
-        proc.stderr_task.abort();
+        if let Some(stderr_task) = &proc.stderr_task {
+            stderr_task.abort();
+        }
         proc.wait_task.abort();
     }
 
@@ -996,6 +2213,11 @@ pub async fn run_with_input(
         let _ = execute!(io::stdout(), LeaveAlternateScreen);
         let _ = disable_raw_mode();
     }
+    // Dropping the terminal shows the cursor again and reports failing to on
+    // stderr, which panics once the terminal has been closed
+    if terminal.show_cursor().is_err() {
+        std::mem::forget(terminal);
+    }
     Ok(())
 }
 
@@ -1017,8 +2239,177 @@ mod tests {
     use indexmap::IndexMap;
 
     use crate::config::{CommandValue, TaskConfiguration};
+    use nix::sys::signal::Signal;
     use std::borrow::Cow;
 
+    #[test]
+    fn test_stop_policy() {
+        let yaml = r#"
+    tasks:
+      default:
+        run: ./server
+      custom-signal:
+        run: java -jar server.jar
+        stop_signal: TERM
+      custom-timeout:
+        run: ./server
+        stop_timeout: 2.5
+      both:
+        run: postgres
+        stop_signal: sigquit
+        stop_timeout: 30
+    "#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let policy = |name: &str| stop_policy(&config.tasks[name]);
+        assert_eq!(policy("default"), StopPolicy::default());
+        assert_eq!(
+            policy("custom-signal"),
+            StopPolicy {
+                signal: Signal::SIGTERM,
+                timeout: Duration::from_secs(10),
+            }
+        );
+        assert_eq!(
+            policy("custom-timeout"),
+            StopPolicy {
+                signal: Signal::SIGINT,
+                timeout: Duration::from_millis(2500),
+            }
+        );
+        assert_eq!(
+            policy("both"),
+            StopPolicy {
+                signal: Signal::SIGQUIT,
+                timeout: Duration::from_secs(30),
+            }
+        );
+    }
+
+    /// Run a healthcheck until it passes or gives up, returning a summary of
+    /// the events it sent.
+    async fn healthcheck_events(healthcheck: Healthcheck) -> Vec<String> {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(100);
+        let (_shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel(1);
+        let task = spawn_healthcheck("task".to_string(), healthcheck, vec![], tx, shutdown_rx);
+        tokio::time::timeout(Duration::from_secs(5), task)
+            .await
+            .expect("healthcheck should finish")
+            .unwrap();
+
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            events.push(match event {
+                UiEvent::HealthcheckLine { text, .. } => text,
+                UiEvent::HealthcheckFailed { retries_left, .. } => {
+                    format!("failed {retries_left:?}")
+                }
+                UiEvent::HealthcheckUnhealthy { .. } => "unhealthy".to_string(),
+                UiEvent::HealthcheckPassed { .. } => "passed".to_string(),
+                _ => "other".to_string(),
+            });
+        }
+        events
+    }
+
+    #[tokio::test]
+    async fn test_healthcheck_timeout_and_retries() {
+        let events = healthcheck_events(Healthcheck {
+            method: HealthcheckMethod::Cmd("sleep 5".to_string()),
+            interval: Duration::from_millis(50),
+            timeout: Duration::from_millis(100),
+            retries: Some(2),
+            start_period: Duration::ZERO,
+            mode: HealthcheckMode::Readiness,
+            restart_on_unhealthy: false,
+        })
+        .await;
+        assert_eq!(
+            events,
+            vec![
+                "[healthcheck timed out after 0.1s]",
+                "failed Some(1)",
+                "[healthcheck timed out after 0.1s]",
+                "unhealthy",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_healthcheck_start_period() {
+        let events = healthcheck_events(Healthcheck {
+            method: HealthcheckMethod::Cmd("false".to_string()),
+            interval: Duration::from_millis(50),
+            timeout: Duration::from_secs(1),
+            retries: Some(1),
+            start_period: Duration::from_millis(300),
+            mode: HealthcheckMode::Readiness,
+            restart_on_unhealthy: false,
+        })
+        .await;
+        // Failures during the start period are retried without counting
+        assert!(events.len() > 2, "{events:?}");
+        assert!(
+            events[..events.len() - 1]
+                .iter()
+                .all(|e| e == "failed Some(1)")
+        );
+        assert_eq!(events.last().unwrap(), "unhealthy");
+    }
+
+    #[tokio::test]
+    async fn test_healthcheck_continuous_reports_changes() {
+        let counter = std::env::temp_dir().join(format!("rote-liveness-{}", std::process::id()));
+        let _ = std::fs::remove_file(&counter);
+        // Passes on the first check, fails the next two, then recovers
+        let cmd = format!(
+            "n=$(cat '{0}' 2>/dev/null || echo 0); echo $((n + 1)) > '{0}'; [ $n -lt 1 ] || [ $n -ge 3 ]",
+            counter.display()
+        );
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(100);
+        let (shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel(1);
+        let task = spawn_healthcheck(
+            "task".to_string(),
+            Healthcheck {
+                method: HealthcheckMethod::Cmd(cmd),
+                interval: Duration::from_millis(50),
+                timeout: Duration::from_secs(1),
+                retries: Some(2),
+                start_period: Duration::ZERO,
+                mode: HealthcheckMode::Continuous,
+                restart_on_unhealthy: false,
+            },
+            vec![],
+            tx,
+            shutdown_rx,
+        );
+
+        let mut events = Vec::new();
+        while events.len() < 4 {
+            let event = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+                .await
+                .expect("healthcheck should keep running")
+                .unwrap();
+            events.push(match event {
+                UiEvent::HealthcheckFailed { retries_left, .. } => {
+                    format!("failed {retries_left:?}")
+                }
+                UiEvent::HealthcheckUnhealthy { .. } => "unhealthy".to_string(),
+                UiEvent::HealthcheckPassed { .. } => "passed".to_string(),
+                _ => "other".to_string(),
+            });
+        }
+        let _ = shutdown_tx.send(());
+        let _ = task.await;
+        let _ = std::fs::remove_file(&counter);
+
+        // Later passes aren't reported again until after a failure
+        assert_eq!(
+            events,
+            vec!["passed", "failed Some(1)", "unhealthy", "passed"]
+        );
+    }
+
     /// Helper to extract panel names from a config in the order they would be created.
     /// This mirrors the logic in run_with_input for creating panels.
     fn get_panel_order(config: &Config) -> Vec<String> {
@@ -1047,9 +2438,9 @@ mod tests {
                 cwd: None,
                 display: None,
                 require: vec![],
-                autorestart: false,
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
         tasks.insert(
@@ -1061,9 +2452,9 @@ mod tests {
                 cwd: None,
                 display: None,
                 require: vec![],
-                autorestart: false,
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
         tasks.insert(
@@ -1075,9 +2466,9 @@ mod tests {
                 cwd: None,
                 display: None,
                 require: vec![],
-                autorestart: false,
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
         // Task without action should be excluded from panels
@@ -1087,16 +2478,17 @@ mod tests {
                 action: None,
                 cwd: None,
                 display: None,
-                require: vec!["first".to_string()],
-                autorestart: false,
+                require: vec!["first".into()],
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
 
         let config = Config {
             default: None,
             tasks,
+            ..Default::default()
         };
 
         let panel_order = get_panel_order(&config);
@@ -1105,6 +2497,23 @@ mod tests {
         assert_eq!(panel_order, vec!["third", "first", "second"]);
     }
 
+    #[test]
+    fn test_tasks_run_next_to_explicit_config() {
+        // A config given with `-c` is loaded the same way as a discovered
+        // one, so tasks without `cwd` run in its directory too
+        let loaded = load_config(&[PathBuf::from("tests/data/example.yaml")]).unwrap();
+        let panels = build_panels(&loaded.config, &loaded.config_dir).unwrap();
+        assert!(!panels.is_empty());
+        for panel in &panels {
+            assert_eq!(
+                panel.cwd.as_deref(),
+                Some("tests/data"),
+                "{}",
+                panel.task_name
+            );
+        }
+    }
+
     #[test]
     fn test_visible_len_empty_panel() {
         let panel = Panel::new(
@@ -1170,6 +2579,7 @@ mod tests {
         let config = Config {
             default: None,
             tasks: IndexMap::new(),
+            ..Default::default()
         };
         let result = resolve_dependencies(&config, &[]).unwrap();
         assert!(result.is_empty());
@@ -1185,15 +2595,16 @@ mod tests {
                 cwd: None,
                 display: None,
                 require: vec![],
-                autorestart: false,
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
 
         let config = Config {
             default: None,
             tasks,
+            ..Default::default()
         };
 
         let result = resolve_dependencies(&config, &["task1".to_string()]).unwrap();
@@ -1209,10 +2620,10 @@ mod tests {
                 action: None,
                 cwd: None,
                 display: None,
-                require: vec!["dep1".to_string()],
-                autorestart: false,
+                require: vec!["dep1".into()],
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
         tasks.insert(
@@ -1222,15 +2633,16 @@ mod tests {
                 cwd: None,
                 display: None,
                 require: vec![],
-                autorestart: false,
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
 
         let config = Config {
             default: None,
             tasks,
+            ..Default::default()
         };
 
         let result = resolve_dependencies(&config, &["task1".to_string()]).unwrap();
@@ -1246,10 +2658,10 @@ mod tests {
                 action: None,
                 cwd: None,
                 display: None,
-                require: vec!["dep1".to_string(), "dep2".to_string()],
-                autorestart: false,
+                require: vec!["dep1".into(), "dep2".into()],
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
         tasks.insert(
@@ -1259,9 +2671,9 @@ mod tests {
                 cwd: None,
                 display: None,
                 require: vec![],
-                autorestart: false,
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
         tasks.insert(
@@ -1271,15 +2683,16 @@ mod tests {
                 cwd: None,
                 display: None,
                 require: vec![],
-                autorestart: false,
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
 
         let config = Config {
             default: None,
             tasks,
+            ..Default::default()
         };
 
         let result = resolve_dependencies(&config, &["task1".to_string()]).unwrap();
@@ -1298,10 +2711,10 @@ mod tests {
                 action: None,
                 cwd: None,
                 display: None,
-                require: vec!["dep1".to_string()],
-                autorestart: false,
+                require: vec!["dep1".into()],
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
         tasks.insert(
@@ -1310,10 +2723,10 @@ mod tests {
                 action: None,
                 cwd: None,
                 display: None,
-                require: vec!["dep2".to_string()],
-                autorestart: false,
+                require: vec!["dep2".into()],
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
         tasks.insert(
@@ -1323,15 +2736,16 @@ mod tests {
                 cwd: None,
                 display: None,
                 require: vec![],
-                autorestart: false,
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
 
         let config = Config {
             default: None,
             tasks,
+            ..Default::default()
         };
 
         let result = resolve_dependencies(&config, &["task1".to_string()]).unwrap();
@@ -1347,10 +2761,10 @@ mod tests {
                 action: None,
                 cwd: None,
                 display: None,
-                require: vec!["task2".to_string()],
-                autorestart: false,
+                require: vec!["task2".into()],
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
         tasks.insert(
@@ -1359,16 +2773,17 @@ mod tests {
                 action: None,
                 cwd: None,
                 display: None,
-                require: vec!["task1".to_string()],
-                autorestart: false,
+                require: vec!["task1".into()],
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
 
         let config = Config {
             default: None,
             tasks,
+            ..Default::default()
         };
 
         let result = resolve_dependencies(&config, &["task1".to_string()]);
@@ -1386,6 +2801,7 @@ mod tests {
         let config = Config {
             default: None,
             tasks: IndexMap::new(),
+            ..Default::default()
         };
 
         let result = resolve_dependencies(&config, &["nonexistent".to_string()]);
@@ -1407,16 +2823,17 @@ mod tests {
                 action: None,
                 cwd: None,
                 display: None,
-                require: vec!["nonexistent".to_string()],
-                autorestart: false,
+                require: vec!["nonexistent".into()],
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
 
         let config = Config {
             default: None,
             tasks,
+            ..Default::default()
         };
 
         let result = resolve_dependencies(&config, &["task1".to_string()]);
@@ -1432,10 +2849,10 @@ mod tests {
                 action: None,
                 cwd: None,
                 display: None,
-                require: vec!["dep1".to_string()],
-                autorestart: false,
+                require: vec!["dep1".into()],
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
         tasks.insert(
@@ -1444,10 +2861,10 @@ mod tests {
                 action: None,
                 cwd: None,
                 display: None,
-                require: vec!["dep1".to_string()],
-                autorestart: false,
+                require: vec!["dep1".into()],
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
         tasks.insert(
@@ -1457,15 +2874,16 @@ mod tests {
                 cwd: None,
                 display: None,
                 require: vec![],
-                autorestart: false,
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
 
         let config = Config {
             default: None,
             tasks,
+            ..Default::default()
         };
 
         let result =
@@ -1485,10 +2903,10 @@ mod tests {
                 action: None,
                 cwd: None,
                 display: None,
-                require: vec!["dep1".to_string(), "dep2".to_string()],
-                autorestart: false,
+                require: vec!["dep1".into(), "dep2".into()],
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
         tasks.insert(
@@ -1497,10 +2915,10 @@ mod tests {
                 action: None,
                 cwd: None,
                 display: None,
-                require: vec!["base".to_string()],
-                autorestart: false,
+                require: vec!["base".into()],
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
         tasks.insert(
@@ -1509,10 +2927,10 @@ mod tests {
                 action: None,
                 cwd: None,
                 display: None,
-                require: vec!["base".to_string()],
-                autorestart: false,
+                require: vec!["base".into()],
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
         tasks.insert(
@@ -1522,15 +2940,16 @@ mod tests {
                 cwd: None,
                 display: None,
                 require: vec![],
-                autorestart: false,
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
 
         let config = Config {
             default: None,
             tasks,
+            ..Default::default()
         };
 
         let result = resolve_dependencies(&config, &["task1".to_string()]).unwrap();
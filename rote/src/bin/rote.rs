@@ -1,10 +1,11 @@
-use anyhow::Context as _;
 use clap::{Parser, Subcommand};
-use std::fs;
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
-use rote_mux::Config;
+use rote_mux::check::check_config;
+use rote_mux::leftovers::{PID_FILE_NAME, find_leftovers, stop_leftovers};
+use rote_mux::loader::{discover_config, load_config};
 
 const EXAMPLE_YAML: &str = include_str!("../../tests/data/example.yaml");
 
@@ -14,10 +15,12 @@ struct Args {
     #[command(subcommand)]
     command: Option<Command>,
 
-    /// The path to the configuration file. If omitted will look for `rote.yaml`
-    /// in the current directory.
+    /// The path to the configuration file. If omitted, uses `ROTE_CONFIG` or
+    /// looks for `rote.yaml`, `rote.yml` or `.rote.yaml` in the current
+    /// directory and its parents. May be given multiple times; later files
+    /// are merged on top of earlier ones.
     #[arg(short, long, value_name = "FILE")]
-    config: Option<String>,
+    config: Vec<String>,
 
     /// The services to run. If omitted, the default service from the config
     /// file will be run. If the default service is not specified in the config,
@@ -34,16 +37,22 @@ struct Args {
 enum Command {
     /// Run rote with a configuration file
     Run(RunArgs),
+    /// Validate a configuration file without running anything
+    Check(CheckArgs),
+    /// Print a JSON Schema for the configuration file format
+    Schema,
     /// Run utility tools
     Tool(ToolArgs),
 }
 
 #[derive(Parser, Debug)]
 struct RunArgs {
-    /// The path to the configuration file. If omitted will look for `rote.yaml`
-    /// in the current directory.
+    /// The path to the configuration file. If omitted, uses `ROTE_CONFIG` or
+    /// looks for `rote.yaml`, `rote.yml` or `.rote.yaml` in the current
+    /// directory and its parents. May be given multiple times; later files
+    /// are merged on top of earlier ones.
     #[arg(short, long, value_name = "FILE")]
-    config: Option<String>,
+    config: Vec<String>,
     /// The services to run. If omitted, the default service from the config
     /// file will be run. If the default service is not specified in the config,
     /// no services will be run.
@@ -54,6 +63,16 @@ struct RunArgs {
     generate_example: bool,
 }
 
+#[derive(Parser, Debug)]
+struct CheckArgs {
+    /// The path to the configuration file. If omitted, uses `ROTE_CONFIG` or
+    /// looks for `rote.yaml`, `rote.yml` or `.rote.yaml` in the current
+    /// directory and its parents. May be given multiple times; later files
+    /// are merged on top of earlier ones.
+    #[arg(short, long, value_name = "FILE")]
+    config: Vec<String>,
+}
+
 #[derive(Parser, Debug)]
 struct ToolArgs {
     /// Wait and retry until the tool succeeds (exits with code 0)
@@ -121,6 +140,12 @@ async fn run() -> anyhow::Result<()> {
     match args.command {
         Some(Command::Tool(tool_args)) => run_tool(tool_args).await,
         Some(Command::Run(run_args)) => run_main(run_args).await,
+        Some(Command::Check(check_args)) => run_check(check_args),
+        Some(Command::Schema) => {
+            let schema = rote_mux::schema::config_schema();
+            println!("{}", serde_json::to_string_pretty(&schema)?);
+            Ok(())
+        }
         None => {
             // Default behavior: use top-level args (backwards compatible)
             run_main(RunArgs {
@@ -139,28 +164,70 @@ async fn run_main(args: RunArgs) -> anyhow::Result<()> {
         return Ok(());
     }
 
-    let config_path = if let Some(config) = args.config {
-        PathBuf::from(config)
-    } else {
-        PathBuf::from("rote.yaml")
-    };
+    let paths = config_paths(&args.config)?;
+    let loaded = load_config(&paths)?;
 
-    let yaml_dir = config_path
-        .parent()
-        .ok_or_else(|| anyhow::anyhow!("failed to determine config file directory"))?
-        .to_path_buf();
+    offer_to_stop_leftovers(&loaded.config_dir)?;
+    rote_mux::run_reloadable(loaded, paths, args.services, None).await?;
 
-    let yaml_str = fs::read_to_string(&config_path)
-        .with_context(|| format!("failed to read config file '{}'", config_path.display()))?;
+    Ok(())
+}
 
-    let config: Config =
-        serde_yaml::from_str(&yaml_str).context("failed to parse config file as YAML")?;
+/// Offer to stop the tasks an earlier run left running because it died
+/// before it could stop them.
+fn offer_to_stop_leftovers(config_dir: &Path) -> anyhow::Result<()> {
+    let pid_path = config_dir.join(PID_FILE_NAME);
+    let leftovers = find_leftovers(&pid_path);
+    if leftovers.is_empty() {
+        return Ok(());
+    }
 
-    rote_mux::run(config, args.services, yaml_dir).await?;
+    eprintln!("An earlier run of rote left these tasks running:");
+    for leftover in &leftovers {
+        eprintln!("  {leftover}");
+    }
+    if !std::io::stdin().is_terminal() {
+        return Ok(());
+    }
+    eprint!("Stop them? [Y/n] ");
 
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    if matches!(answer.trim().to_lowercase().as_str(), "" | "y" | "yes") {
+        stop_leftovers(&pid_path, &leftovers);
+    }
     Ok(())
 }
 
+fn run_check(args: CheckArgs) -> anyhow::Result<()> {
+    let diagnostics = check_config(&config_paths(&args.config)?);
+    for diagnostic in &diagnostics {
+        println!("{diagnostic}");
+    }
+
+    match diagnostics.len() {
+        0 => {
+            println!("No problems found.");
+            Ok(())
+        }
+        1 => Err(anyhow::anyhow!("found 1 problem")),
+        n => Err(anyhow::anyhow!("found {n} problems")),
+    }
+}
+
+/// The config files given with `--config`, or the ones found by
+/// `discover_config` if there were none.
+fn config_paths(config: &[String]) -> anyhow::Result<Vec<PathBuf>> {
+    if !config.is_empty() {
+        return Ok(config.iter().map(PathBuf::from).collect());
+    }
+    let cwd = std::env::current_dir()?;
+    Ok(discover_config(
+        &cwd,
+        std::env::var_os("ROTE_CONFIG").as_deref(),
+    )?)
+}
+
 async fn run_tool(args: ToolArgs) -> anyhow::Result<()> {
     use rote_mux::tools;
 
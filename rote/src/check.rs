@@ -0,0 +1,792 @@
+use serde_yaml::Value;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::Marker;
+
+use crate::config::{Config, DependencyCondition, TaskAction, parse_signal, parse_tool};
+use crate::loader::load_config;
+use crate::schema::config_schema;
+use crate::watch::validate_glob;
+
+/// Task fields accepted under another name, which the schema leaves out.
+const TASK_FIELD_ALIASES: &[&str] = &["autorestart"];
+
+/// The fields allowed at each level of a config file, taken from the JSON
+/// Schema of the config types so that new fields are known right away.
+struct KnownFields {
+    top_level: HashSet<String>,
+    task: HashSet<String>,
+    healthcheck: HashSet<String>,
+}
+
+impl KnownFields {
+    fn from_schema(schema: &serde_json::Value) -> Self {
+        let definition = |name: &str| &schema["definitions"][name];
+        let mut task = property_names(definition("TaskConfiguration"));
+        task.extend(TASK_FIELD_ALIASES.iter().map(|alias| alias.to_string()));
+        Self {
+            top_level: property_names(schema),
+            task,
+            healthcheck: property_names(definition("Healthcheck")),
+        }
+    }
+}
+
+/// The properties of an object schema, including the ones only allowed in
+/// some of its alternatives, like a task's `run` and `ensure`.
+fn property_names(schema: &serde_json::Value) -> HashSet<String> {
+    let mut names: HashSet<String> = schema["properties"]
+        .as_object()
+        .into_iter()
+        .flat_map(|properties| properties.keys().cloned())
+        .collect();
+    for alternatives in ["anyOf", "oneOf"] {
+        for alternative in schema[alternatives].as_array().into_iter().flatten() {
+            names.extend(property_names(alternative));
+        }
+    }
+    names
+}
+
+/// Stream names accepted by `display`.
+const DISPLAY_STREAMS: &[&str] = &["stdout", "stderr"];
+
+/// A problem found in a config file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub file: PathBuf,
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column number.
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.file.display(),
+            self.line,
+            self.column,
+            self.message
+        )
+    }
+}
+
+/// Positions of mapping keys and sequence items in a YAML file, by path.
+/// A path is the list of mapping keys (and sequence indices) from the root.
+#[derive(Default)]
+struct SourceMap {
+    locations: HashMap<Vec<String>, (usize, usize)>,
+}
+
+impl SourceMap {
+    fn parse(source: &str) -> Self {
+        let mut builder = SourceMapBuilder::default();
+        // Syntax errors are reported through serde_yaml, which has better messages
+        let _ = Parser::new_from_str(source).load(&mut builder, false);
+        builder.map
+    }
+
+    fn get(&self, path: &[&str]) -> Option<(usize, usize)> {
+        let path: Vec<String> = path.iter().map(|s| s.to_string()).collect();
+        self.locations.get(&path).copied()
+    }
+}
+
+enum Frame {
+    Mapping {
+        path: Vec<String>,
+        key: Option<String>,
+    },
+    Sequence {
+        path: Vec<String>,
+        index: usize,
+    },
+}
+
+#[derive(Default)]
+struct SourceMapBuilder {
+    stack: Vec<Frame>,
+    map: SourceMap,
+}
+
+impl SourceMapBuilder {
+    /// The path of a value that starts at `mark`, recording sequence items.
+    fn value_path(&mut self, mark: Marker) -> Vec<String> {
+        match self.stack.last() {
+            Some(Frame::Mapping {
+                path,
+                key: Some(key),
+            }) => {
+                let mut path = path.clone();
+                path.push(key.clone());
+                path
+            }
+            Some(Frame::Sequence { path, index }) => {
+                let mut path = path.clone();
+                path.push(index.to_string());
+                self.map
+                    .locations
+                    .insert(path.clone(), (mark.line(), mark.col() + 1));
+                path
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Advance the innermost container past a complete value.
+    fn finish_value(&mut self) {
+        match self.stack.last_mut() {
+            Some(Frame::Mapping { key, .. }) => *key = None,
+            Some(Frame::Sequence { index, .. }) => *index += 1,
+            None => {}
+        }
+    }
+}
+
+impl MarkedEventReceiver for SourceMapBuilder {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        match event {
+            Event::MappingStart(..) => {
+                let path = self.value_path(mark);
+                self.stack.push(Frame::Mapping { path, key: None });
+            }
+            Event::SequenceStart(..) => {
+                let path = self.value_path(mark);
+                self.stack.push(Frame::Sequence { path, index: 0 });
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.stack.pop();
+                self.finish_value();
+            }
+            Event::Scalar(value, ..) => {
+                if let Some(Frame::Mapping {
+                    path,
+                    key: key @ None,
+                }) = self.stack.last_mut()
+                {
+                    let mut key_path = path.clone();
+                    key_path.push(value.clone());
+                    self.map
+                        .locations
+                        .insert(key_path, (mark.line(), mark.col() + 1));
+                    *key = Some(value);
+                } else {
+                    self.value_path(mark);
+                    self.finish_value();
+                }
+            }
+            Event::Alias(_) => {
+                self.value_path(mark);
+                self.finish_value();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A config file that takes part in the check.
+struct SourceFile {
+    path: PathBuf,
+    value: Option<Value>,
+    map: SourceMap,
+}
+
+/// Validate a config (and every file it includes) without starting anything.
+///
+/// Checks each file for syntax errors, unknown fields, unknown `display`
+/// streams and invalid healthcheck tools, then loads the merged config and
+/// checks `default`, `require` targets, dependency cycles, healthchecks on
+/// `ensure` tasks and commands that can't be split into arguments.
+pub fn check_config(paths: &[PathBuf]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut files = Vec::new();
+    let mut seen = HashSet::new();
+    for path in paths {
+        collect_files(path, None, &mut files, &mut seen, &mut diagnostics);
+    }
+
+    let known = KnownFields::from_schema(&config_schema());
+    let referenced = referenced_task_fields(&files);
+    for file in &files {
+        if let Some(value) = &file.value {
+            check_file(file, value, &known, &referenced, &mut diagnostics);
+        }
+    }
+
+    // Problems in single files usually make loading fail with a less precise
+    // message, so only report load errors for otherwise clean files.
+    if diagnostics.is_empty() {
+        match load_config(paths) {
+            Ok(loaded) => check_semantics(&loaded.config, &files, &mut diagnostics),
+            Err(e) => diagnostics.push(Diagnostic {
+                file: paths[0].clone(),
+                line: 1,
+                column: 1,
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    diagnostics.sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+    diagnostics
+}
+
+/// Read a file and, recursively, the files it includes.
+fn collect_files(
+    path: &Path,
+    included_from: Option<(&SourceFile, usize)>,
+    files: &mut Vec<SourceFile>,
+    seen: &mut HashSet<PathBuf>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    // The same file can be included under different spellings
+    let key = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if !seen.insert(key) {
+        return;
+    }
+
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            let message = format!("failed to read config file '{}': {e}", path.display());
+            diagnostics.push(match included_from {
+                Some((parent, index)) => {
+                    let (line, column) = parent
+                        .map
+                        .get(&["include", &index.to_string()])
+                        .unwrap_or((1, 1));
+                    Diagnostic {
+                        file: parent.path.clone(),
+                        line,
+                        column,
+                        message,
+                    }
+                }
+                None => Diagnostic {
+                    file: path.to_path_buf(),
+                    line: 1,
+                    column: 1,
+                    message,
+                },
+            });
+            return;
+        }
+    };
+
+    let value = match serde_yaml::from_str::<Value>(&source) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            let (line, column) = e
+                .location()
+                .map(|l| (l.line(), l.column()))
+                .unwrap_or((1, 1));
+            diagnostics.push(Diagnostic {
+                file: path.to_path_buf(),
+                line,
+                column,
+                message: format!("invalid YAML: {e}"),
+            });
+            None
+        }
+    };
+
+    let file = SourceFile {
+        path: path.to_path_buf(),
+        value,
+        map: SourceMap::parse(&source),
+    };
+
+    let includes: Vec<String> = file
+        .value
+        .as_ref()
+        .and_then(|v| v.get("include"))
+        .and_then(|v| serde_yaml::from_value(v.clone()).ok())
+        .unwrap_or_default();
+    let base_dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    for (index, include) in includes.iter().enumerate() {
+        collect_files(
+            &base_dir.join(include),
+            Some((&file, index)),
+            files,
+            seen,
+            diagnostics,
+        );
+    }
+
+    files.push(file);
+}
+
+/// Names used in `${task.<field>}` references anywhere in the config. These
+/// are custom fields, so they are not reported as unknown.
+fn referenced_task_fields(files: &[SourceFile]) -> HashSet<String> {
+    fn visit(value: &Value, names: &mut HashSet<String>) {
+        match value {
+            Value::String(s) => {
+                for part in s.split("${task.").skip(1) {
+                    let end = part.find(['}', ':']).unwrap_or(part.len());
+                    names.insert(part[..end].to_string());
+                }
+            }
+            Value::Sequence(items) => items.iter().for_each(|v| visit(v, names)),
+            Value::Mapping(map) => map.values().for_each(|v| visit(v, names)),
+            _ => {}
+        }
+    }
+
+    let mut names = HashSet::new();
+    for value in files.iter().filter_map(|f| f.value.as_ref()) {
+        visit(value, &mut names);
+    }
+    names
+}
+
+fn check_file(
+    file: &SourceFile,
+    value: &Value,
+    known: &KnownFields,
+    referenced: &HashSet<String>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let mut report = |path: &[&str], message: String| {
+        let (line, column) = file.map.get(path).unwrap_or((1, 1));
+        diagnostics.push(Diagnostic {
+            file: file.path.clone(),
+            line,
+            column,
+            message,
+        });
+    };
+
+    let Some(root) = value.as_mapping() else {
+        if !value.is_null() {
+            report(&[], "config file must be a mapping".to_string());
+        }
+        return;
+    };
+
+    for key in root.keys().filter_map(Value::as_str) {
+        if !known.top_level.contains(key) {
+            report(&[key], format!("unknown top-level field '{key}'"));
+        }
+    }
+
+    for section in ["tasks", "templates"] {
+        let Some(tasks) = root.get(section).and_then(Value::as_mapping) else {
+            continue;
+        };
+        let kind = if section == "tasks" {
+            "task"
+        } else {
+            "template"
+        };
+
+        for (name, task) in tasks {
+            let Some(name) = name.as_str() else {
+                continue;
+            };
+            let Some(task) = task.as_mapping() else {
+                continue;
+            };
+
+            for key in task.keys().filter_map(Value::as_str) {
+                if !known.task.contains(key) && !referenced.contains(key) {
+                    report(
+                        &[section, name, key],
+                        format!("unknown field '{key}' in {kind} '{name}'"),
+                    );
+                }
+            }
+
+            if let Some(Value::Sequence(streams)) = task.get("display") {
+                for (i, stream) in streams.iter().enumerate() {
+                    let stream = stream.as_str().unwrap_or_default();
+                    if !DISPLAY_STREAMS.contains(&stream) {
+                        report(
+                            &[section, name, "display", &i.to_string()],
+                            format!(
+                                "unknown display stream '{stream}' in {kind} '{name}' \
+                                 (expected 'stdout' or 'stderr')"
+                            ),
+                        );
+                    }
+                }
+            }
+
+            if let Some(Value::String(signal)) = task.get("stop_signal")
+                && let Err(e) = parse_signal(signal)
+            {
+                report(
+                    &[section, name, "stop_signal"],
+                    format!("invalid stop_signal in {kind} '{name}': {e}"),
+                );
+            }
+
+            if let Some(healthcheck) = task.get("healthcheck").and_then(Value::as_mapping) {
+                for key in healthcheck.keys().filter_map(Value::as_str) {
+                    if !known.healthcheck.contains(key) {
+                        report(
+                            &[section, name, "healthcheck", key],
+                            format!("unknown healthcheck field '{key}' in {kind} '{name}'"),
+                        );
+                    }
+                }
+                if let Some(Value::String(tool)) = healthcheck.get("tool")
+                    && !tool.contains("${")
+                    && let Err(e) = parse_tool(tool)
+                {
+                    report(
+                        &[section, name, "healthcheck", "tool"],
+                        format!("invalid healthcheck tool in {kind} '{name}': {e}"),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Find where a task field is defined, preferring later files (which
+/// override earlier ones) and falling back to the task itself.
+fn locate(files: &[SourceFile], task_name: &str, field: &[&str]) -> (PathBuf, usize, usize) {
+    let mut path = vec!["tasks", task_name];
+    path.extend_from_slice(field);
+
+    for candidate in [&path[..], &path[..2]] {
+        for file in files.iter().rev() {
+            if let Some((line, column)) = file.map.get(candidate) {
+                return (file.path.clone(), line, column);
+            }
+        }
+    }
+
+    let last = files.last().map(|f| f.path.clone()).unwrap_or_default();
+    (last, 1, 1)
+}
+
+fn check_semantics(config: &Config, files: &[SourceFile], diagnostics: &mut Vec<Diagnostic>) {
+    if let Some(default) = &config.default
+        && !config.tasks.contains_key(default)
+    {
+        let (file, line, column) = files
+            .iter()
+            .rev()
+            .find_map(|f| f.map.get(&["default"]).map(|(l, c)| (f.path.clone(), l, c)))
+            .unwrap_or_else(|| (files[0].path.clone(), 1, 1));
+        diagnostics.push(Diagnostic {
+            file,
+            line,
+            column,
+            message: format!("default task '{default}' is not defined"),
+        });
+    }
+
+    let mut report = |task_name: &str, field: &[&str], message: String| {
+        let (file, line, column) = locate(files, task_name, field);
+        diagnostics.push(Diagnostic {
+            file,
+            line,
+            column,
+            message,
+        });
+    };
+
+    for (name, task) in &config.tasks {
+        let dependency_lists = [
+            ("require", &task.require, "requires", "requires"),
+            ("after", &task.after, "runs after", "waits for"),
+        ];
+        for (list, deps, verb, waits) in dependency_lists {
+            for (i, dep) in deps.iter().enumerate() {
+                let field = [list, &i.to_string()];
+                match config.tasks.get(&dep.task) {
+                    None => report(
+                        name,
+                        &field,
+                        format!("task '{name}' {verb} unknown task '{}'", dep.task),
+                    ),
+                    Some(dep_config)
+                        if dep.condition == Some(DependencyCondition::Healthy)
+                            && dep_config.healthcheck.is_none() =>
+                    {
+                        report(
+                            name,
+                            &[field[0], field[1], "condition"],
+                            format!(
+                                "task '{name}' {waits} '{}' to be healthy, but it has no \
+                                 healthcheck",
+                                dep.task
+                            ),
+                        )
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+
+        if let Some(TaskAction::Ensure { .. }) = &task.action
+            && task.healthcheck.is_some()
+        {
+            report(
+                name,
+                &["healthcheck"],
+                format!(
+                    "task '{name}' is an 'ensure' task, so its healthcheck is ignored \
+                     (dependents wait for it to complete instead)"
+                ),
+            );
+        }
+
+        if let Some(TaskAction::Run { command } | TaskAction::Ensure { command }) = &task.action {
+            let field = if matches!(task.action, Some(TaskAction::Run { .. })) {
+                "run"
+            } else {
+                "ensure"
+            };
+            if let Err(e) = command.to_argv(config.task_shell(task).as_deref()) {
+                report(name, &[field], format!("task '{name}': {e}"));
+            }
+        }
+
+        if !matches!(task.action, Some(TaskAction::Ensure { .. })) {
+            for (field, set) in [
+                ("sources", !task.sources.is_empty()),
+                ("outputs", !task.outputs.is_empty()),
+                ("skip_if", task.skip_if.is_some()),
+                ("timeout", task.timeout.is_some()),
+                ("retries", task.retries > 0),
+                ("retry_delay", task.retry_delay.is_some()),
+            ] {
+                if set {
+                    report(
+                        name,
+                        &[field],
+                        format!(
+                            "task '{name}' is not an 'ensure' task, so its '{field}' setting is ignored"
+                        ),
+                    );
+                }
+            }
+        }
+
+        let glob_lists = [
+            ("watch", &task.watch),
+            ("watch_ignore", &task.watch_ignore),
+            ("sources", &task.sources),
+            ("outputs", &task.outputs),
+        ];
+        for (list, patterns) in glob_lists {
+            for (i, pattern) in patterns.iter().enumerate() {
+                if let Err(e) = validate_glob(pattern) {
+                    report(
+                        name,
+                        &[list, &i.to_string()],
+                        format!("task '{name}': invalid {list} pattern '{pattern}': {e}"),
+                    );
+                }
+            }
+        }
+    }
+
+    for cycle in find_cycles(config) {
+        report(
+            &cycle[0],
+            &["require"],
+            format!("circular dependency: {}", cycle.join(" -> ")),
+        );
+    }
+}
+
+/// Find dependency cycles through `require` and `after`, each reported once as the list
+/// of tasks in the cycle (the first task is repeated at the end).
+fn find_cycles(config: &Config) -> Vec<Vec<String>> {
+    fn visit(
+        task: &str,
+        config: &Config,
+        stack: &mut Vec<String>,
+        done: &mut HashSet<String>,
+        cycles: &mut Vec<Vec<String>>,
+    ) {
+        if done.contains(task) {
+            return;
+        }
+        if let Some(pos) = stack.iter().position(|t| t == task) {
+            let mut cycle = stack[pos..].to_vec();
+            cycle.push(task.to_string());
+            cycles.push(cycle);
+            return;
+        }
+        let Some(task_config) = config.tasks.get(task) else {
+            return;
+        };
+
+        stack.push(task.to_string());
+        for dep in task_config.require.iter().chain(&task_config.after) {
+            visit(&dep.task, config, stack, done, cycles);
+        }
+        stack.pop();
+        done.insert(task.to_string());
+    }
+
+    let mut cycles = Vec::new();
+    let mut done = HashSet::new();
+    for name in config.tasks.keys() {
+        visit(name, config, &mut Vec::new(), &mut done, &mut cycles);
+    }
+    cycles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(name: &str) -> PathBuf {
+        Path::new("tests/data/check").join(name)
+    }
+
+    fn messages(diagnostics: &[Diagnostic]) -> Vec<String> {
+        diagnostics.iter().map(|d| d.to_string()).collect()
+    }
+
+    #[test]
+    fn test_known_fields_from_schema() {
+        let known = KnownFields::from_schema(&config_schema());
+        fn names(fields: &HashSet<String>) -> Vec<&str> {
+            let mut names: Vec<&str> = fields.iter().map(String::as_str).collect();
+            names.sort_unstable();
+            names
+        }
+        assert_eq!(
+            names(&known.top_level),
+            ["default", "env", "include", "shell", "tasks", "templates"]
+        );
+        for field in ["run", "ensure", "autorestart", "cwd", "healthcheck", "tty"] {
+            assert!(known.task.contains(field), "{field}");
+        }
+        assert_eq!(
+            names(&known.healthcheck),
+            [
+                "cmd",
+                "interval",
+                "mode",
+                "restart_on_unhealthy",
+                "retries",
+                "start_period",
+                "timeout",
+                "tool"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_source_map_locations() {
+        let source = "tasks:\n  api:\n    run: ./api\n    require: [db, cache]\n";
+        let map = SourceMap::parse(source);
+        assert_eq!(map.get(&["tasks"]), Some((1, 1)));
+        assert_eq!(map.get(&["tasks", "api"]), Some((2, 3)));
+        assert_eq!(map.get(&["tasks", "api", "run"]), Some((3, 5)));
+        assert_eq!(map.get(&["tasks", "api", "require", "0"]), Some((4, 15)));
+        assert_eq!(map.get(&["tasks", "api", "require", "1"]), Some((4, 19)));
+        assert_eq!(map.get(&["tasks", "db"]), None);
+    }
+
+    #[test]
+    fn test_check_valid_config() {
+        let diagnostics = check_config(&[PathBuf::from("tests/data/example.yaml")]);
+        assert_eq!(messages(&diagnostics), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_check_reports_problems_with_locations() {
+        let path = data("problems.yaml");
+        let file = path.display().to_string();
+        let diagnostics = check_config(&[path]);
+        assert_eq!(
+            messages(&diagnostics),
+            vec![
+                format!("{file}:4:5: unknown field 'helthcheck' in task 'api'"),
+                format!(
+                    "{file}:7:23: unknown display stream 'stdeer' in task 'api' \
+                     (expected 'stdout' or 'stderr')"
+                ),
+                format!("{file}:13:7: unknown healthcheck field 'timout' in task 'db'"),
+                format!("{file}:14:5: invalid stop_signal in task 'db': unknown signal: SIGSMART"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_reports_semantic_problems() {
+        let path = data("semantics.yaml");
+        let file = path.display().to_string();
+        let diagnostics = check_config(&[path]);
+        assert_eq!(
+            messages(&diagnostics),
+            vec![
+                format!("{file}:1:1: default task 'missing' is not defined"),
+                format!("{file}:7:19: task 'api' requires unknown task 'cache'"),
+                format!(
+                    "{file}:10:5: task 'migrate' is an 'ensure' task, so its healthcheck is \
+                     ignored (dependents wait for it to complete instead)"
+                ),
+                format!(
+                    "{file}:14:5: task 'broken': failed to parse command: missing closing quote"
+                ),
+                format!("{file}:17:5: circular dependency: a -> b -> a"),
+                format!(
+                    "{file}:25:9: task 'web' requires 'db' to be healthy, but it has no \
+                     healthcheck"
+                ),
+                format!("{file}:26:13: task 'web' runs after unknown task 'ghost'"),
+                format!(
+                    "{file}:27:13: task 'web': invalid watch pattern 'src/[': error parsing glob \
+                     'src/[': unclosed character class; missing ']'"
+                ),
+                format!(
+                    "{file}:28:5: task 'web' is not an 'ensure' task, so its 'sources' setting \
+                     is ignored"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_custom_fields_referenced_by_interpolation() {
+        let diagnostics = check_config(&[data("custom-fields.yaml")]);
+        assert_eq!(messages(&diagnostics), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_check_missing_include() {
+        let path = data("missing-include.yaml");
+        let file = path.display().to_string();
+        let diagnostics = check_config(&[path]);
+        assert_eq!(diagnostics.len(), 1);
+        let diagnostic = diagnostics[0].to_string();
+        assert!(
+            diagnostic.starts_with(&format!("{file}:1:24: ")),
+            "{diagnostic}"
+        );
+        assert!(diagnostics[0].message.contains("nowhere.yaml"));
+    }
+
+    #[test]
+    fn test_check_include_cycle_through_other_spelling() {
+        let diagnostics = check_config(&[PathBuf::from("tests/data/overlay/self-include.yaml")]);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains("include cycle"));
+    }
+
+    #[test]
+    fn test_check_invalid_yaml() {
+        let diagnostics = check_config(&[data("invalid.yaml")]);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 4, "{}", diagnostics[0]);
+        assert!(diagnostics[0].message.starts_with("invalid YAML"));
+    }
+}
@@ -1,8 +1,14 @@
 use indexmap::IndexMap;
+use nix::sys::signal::Signal;
+use schemars::{JsonSchema, Schema, SchemaGenerator};
 use serde::Deserialize;
 use std::borrow::Cow;
+use std::collections::HashSet;
+use std::path::Path;
 use std::time::Duration;
 
+use crate::error::RoteError;
+
 /// Represents a healthcheck method - either a shell command or a built-in tool.
 #[derive(Debug, Clone, PartialEq)]
 pub enum HealthcheckMethod {
@@ -34,6 +40,112 @@ pub struct Healthcheck {
     pub method: HealthcheckMethod,
     /// How often to run the healthcheck (in seconds).
     pub interval: Duration,
+    /// How long a single check may take before it counts as failed.
+    pub timeout: Duration,
+    /// How many failed checks in a row mark the task as unhealthy. `None`
+    /// keeps checking until the healthcheck passes.
+    pub retries: Option<u32>,
+    /// How long after the task starts failed checks don't count towards
+    /// `retries`.
+    pub start_period: Duration,
+    /// Whether to stop after the first success or keep checking.
+    pub mode: HealthcheckMode,
+    /// Whether to restart the task when a continuous check finds it unhealthy.
+    pub restart_on_unhealthy: bool,
+}
+
+impl Healthcheck {
+    /// How many failed checks in a row mark the task as unhealthy, given
+    /// whether it has been healthy before.
+    pub fn retry_limit(&self, was_healthy: bool) -> Option<u32> {
+        match (self.retries, was_healthy) {
+            (Some(retries), _) => Some(retries),
+            // A task that was healthy isn't waited on forever
+            (None, true) => Some(DEFAULT_LIVENESS_RETRIES),
+            (None, false) => None,
+        }
+    }
+}
+
+/// When a task's healthcheck runs.
+#[derive(Debug, Default, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum HealthcheckMode {
+    /// Check until the task is healthy once, then stop.
+    #[default]
+    Readiness,
+    /// Keep checking after the task is healthy, so it is marked unhealthy if
+    /// it stops responding.
+    Continuous,
+}
+
+/// What a task's process reads as its standard input.
+#[derive(Debug, Default, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum StdinMode {
+    /// Nothing: reading gets end of file straight away.
+    #[default]
+    Null,
+    /// A pipe, which what is typed in input mode is written to.
+    Pipe,
+}
+
+/// The default for a healthcheck's `timeout`.
+pub const DEFAULT_HEALTHCHECK_TIMEOUT: Duration = Duration::from_secs(30);
+/// How many failed checks in a row mark a task that was healthy as
+/// unhealthy when `retries` isn't set.
+pub const DEFAULT_LIVENESS_RETRIES: u32 = 3;
+
+/// Healthcheck configuration as written in YAML. Exactly one of `cmd` and
+/// `tool` must be set.
+#[derive(Deserialize, JsonSchema)]
+#[schemars(
+    rename = "Healthcheck",
+    extend("oneOf" = [{ "required": ["cmd"] }, { "required": ["tool"] }])
+)]
+struct RawHealthcheck {
+    /// A shell command to run (via sh -c). Healthy when it exits with code 0.
+    cmd: Option<String>,
+    /// A built-in tool to call directly: `is-port-open <port>`,
+    /// `http-get <port|url>` or `http-get-ok <port|url>`.
+    tool: Option<String>,
+    /// How often to run the healthcheck (in seconds).
+    #[serde(deserialize_with = "deserialize_duration_secs")]
+    #[schemars(with = "f64", range(min = 0))]
+    interval: Duration,
+    /// How long a single check may take before it counts as failed (in
+    /// seconds, default 30).
+    #[serde(default, deserialize_with = "deserialize_optional_duration_secs")]
+    #[schemars(with = "Option<f64>", range(min = 0))]
+    timeout: Option<Duration>,
+    /// How many failed checks in a row mark the task as unhealthy. If
+    /// omitted, the check is retried until it passes.
+    #[serde(default)]
+    #[schemars(range(min = 1))]
+    retries: Option<u32>,
+    /// How long after the task starts (in seconds) failed checks don't count
+    /// towards `retries`.
+    #[serde(default, deserialize_with = "deserialize_optional_duration_secs")]
+    #[schemars(with = "Option<f64>", range(min = 0))]
+    start_period: Option<Duration>,
+    /// `readiness` (the default) stops checking once the task is healthy;
+    /// `continuous` keeps checking, and marks the task as unhealthy after
+    /// `retries` failures in a row (3 if not set).
+    #[serde(default)]
+    mode: HealthcheckMode,
+    /// Restart the task when a `continuous` check finds it unhealthy.
+    #[serde(default)]
+    restart_on_unhealthy: bool,
+}
+
+impl JsonSchema for Healthcheck {
+    fn schema_name() -> Cow<'static, str> {
+        RawHealthcheck::schema_name()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        RawHealthcheck::json_schema(generator)
+    }
 }
 
 impl<'de> serde::Deserialize<'de> for Healthcheck {
@@ -41,14 +153,6 @@ impl<'de> serde::Deserialize<'de> for Healthcheck {
     where
         D: serde::Deserializer<'de>,
     {
-        #[derive(Deserialize)]
-        struct RawHealthcheck {
-            cmd: Option<String>,
-            tool: Option<String>,
-            #[serde(deserialize_with = "deserialize_duration_secs")]
-            interval: Duration,
-        }
-
         let raw = RawHealthcheck::deserialize(deserializer)?;
 
         let method = match (raw.cmd, raw.tool) {
@@ -72,12 +176,17 @@ impl<'de> serde::Deserialize<'de> for Healthcheck {
         Ok(Healthcheck {
             method,
             interval: raw.interval,
+            timeout: raw.timeout.unwrap_or(DEFAULT_HEALTHCHECK_TIMEOUT),
+            retries: raw.retries,
+            start_period: raw.start_period.unwrap_or_default(),
+            mode: raw.mode,
+            restart_on_unhealthy: raw.restart_on_unhealthy,
         })
     }
 }
 
 /// Parse a tool string like "is-port-open 5432" into a HealthcheckTool.
-fn parse_tool(s: &str) -> Result<HealthcheckTool, String> {
+pub(crate) fn parse_tool(s: &str) -> Result<HealthcheckTool, String> {
     let parts: Vec<&str> = s.split_whitespace().collect();
     if parts.is_empty() {
         return Err("empty tool specification".to_string());
@@ -129,19 +238,166 @@ where
     Ok(Duration::from_secs_f64(secs))
 }
 
-#[derive(Debug, Deserialize)]
+fn deserialize_optional_duration_secs<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let secs: Option<f64> = Deserialize::deserialize(deserializer)?;
+    secs.map(|secs| Duration::try_from_secs_f64(secs).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// Parse a signal name like `SIGTERM`, `TERM` or `term`.
+pub(crate) fn parse_signal(s: &str) -> Result<Signal, String> {
+    let name = s.trim().to_ascii_uppercase();
+    let name = if name.starts_with("SIG") {
+        name
+    } else {
+        format!("SIG{name}")
+    };
+    name.parse().map_err(|_| format!("unknown signal: {s}"))
+}
+
+fn deserialize_signal<'de, D>(deserializer: D) -> Result<Option<Signal>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let name: Option<String> = Deserialize::deserialize(deserializer)?;
+    name.map(|name| parse_signal(&name).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// An environment variable value as written in YAML.
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum EnvValue {
+    String(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+/// Deserialize a map of environment variables. Values may be any YAML scalar
+/// (so `PORT: 8080` works without quoting) and are converted to strings.
+pub(crate) fn deserialize_env<'de, D>(deserializer: D) -> Result<IndexMap<String, String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let raw: IndexMap<String, EnvValue> = Deserialize::deserialize(deserializer)?;
+    Ok(raw
+        .into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                EnvValue::String(s) => s,
+                EnvValue::Int(i) => i.to_string(),
+                EnvValue::Float(f) => f.to_string(),
+                EnvValue::Bool(b) => b.to_string(),
+            };
+            (key, value)
+        })
+        .collect())
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
 pub struct Config {
     /// The default task to run when none is specified.
     pub default: Option<String>,
+    /// Other config files merged underneath this one, relative to this file.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Environment variables passed to every task and its healthcheck.
+    #[serde(default, deserialize_with = "deserialize_env")]
+    #[schemars(with = "IndexMap<String, EnvValue>")]
+    pub env: IndexMap<String, String>,
+    /// Run string commands through a shell: `true` for `sh`, or the shell
+    /// program to use. Tasks can override it with their own `shell`.
+    #[serde(default)]
+    pub shell: Option<ShellSetting>,
     /// A mapping of task names to their configurations (preserves YAML order).
     pub tasks: IndexMap<String, TaskConfiguration>,
 }
 
-#[derive(Debug, Deserialize)]
+impl Config {
+    /// The shell a task's command should run through, if any. The task's own
+    /// `shell` takes precedence over the top-level one; `shell: true` uses
+    /// the top-level shell program if there is one, otherwise `sh`.
+    pub fn task_shell(&self, task_config: &TaskConfiguration) -> Option<String> {
+        let default_program = match &self.shell {
+            Some(ShellSetting::Program(program)) => program.as_str(),
+            _ => DEFAULT_SHELL,
+        };
+        match task_config.shell.as_ref().or(self.shell.as_ref())? {
+            ShellSetting::Enabled(false) => None,
+            ShellSetting::Enabled(true) => Some(default_program.to_string()),
+            ShellSetting::Program(program) => Some(program.clone()),
+        }
+    }
+
+    /// Build the environment for a task. Later sources override earlier ones:
+    /// the top-level `env`, then each of the task's `env_file`s in order, then
+    /// the task's own `env`. Env files are resolved relative to `config_dir`.
+    pub fn task_env(
+        &self,
+        task_name: &str,
+        config_dir: &Path,
+    ) -> crate::error::Result<Vec<(String, String)>> {
+        let env = match self.tasks.get(task_name) {
+            Some(task_config) => merge_env(
+                task_name,
+                &self.env,
+                &task_config.env_file,
+                &task_config.env,
+                config_dir,
+            )?,
+            None => self.env.clone(),
+        };
+        Ok(env.into_iter().collect())
+    }
+}
+
+/// Layer a task's `env_file`s and `env` on top of the top-level `env`.
+pub(crate) fn merge_env(
+    task_name: &str,
+    global_env: &IndexMap<String, String>,
+    env_files: &[String],
+    task_env: &IndexMap<String, String>,
+    config_dir: &Path,
+) -> crate::error::Result<IndexMap<String, String>> {
+    let mut env = global_env.clone();
+
+    for env_file in env_files {
+        let path = config_dir.join(env_file);
+        let iter = dotenvy::from_path_iter(&path).map_err(|e| {
+            RoteError::Config(format!(
+                "task '{task_name}': failed to read env file '{}': {e}",
+                path.display()
+            ))
+        })?;
+        for item in iter {
+            let (key, value) = item.map_err(|e| {
+                RoteError::Config(format!(
+                    "task '{task_name}': failed to parse env file '{}': {e}",
+                    path.display()
+                ))
+            })?;
+            env.insert(key, value);
+        }
+    }
+    env.extend(task_env.clone());
+
+    Ok(env)
+}
+
+/// Configuration for a single task (or a template tasks can extend).
+#[derive(Debug, Default, Deserialize, JsonSchema, PartialEq)]
 pub struct TaskConfiguration {
     /// The action to be performed for the task (either `run` or `start`).
     #[serde(default, flatten)]
     pub action: Option<TaskAction>,
+    /// The task or template this task inherits its settings from. Resolved
+    /// when the config is loaded.
+    #[serde(default)]
+    pub extends: Option<String>,
     /// The working directory for the task command, relative to the
     /// directory containing the YAML file.
     #[serde(default)]
@@ -149,20 +405,233 @@ pub struct TaskConfiguration {
     /// Specifies which output streams to display. If omitted, all streams
     /// are displayed. An empty list means no output is displayed.
     #[serde(default)]
+    #[schemars(extend("items" = { "enum": ["stdout", "stderr"] }))]
     pub display: Option<Vec<String>>,
-    /// A list of other tasks that must be started before this task.
+    /// Other tasks this task waits for before starting, either as names or
+    /// as `{task, condition}`.
+    #[serde(default)]
+    pub require: Vec<Dependency>,
+    /// Tasks this task starts after when they are started as well. Unlike
+    /// `require`, these tasks aren't started just because this one is.
+    #[serde(default)]
+    pub after: Vec<Dependency>,
+    /// Run the command through a shell (`true` or a shell program such as
+    /// `bash`) so pipes, `&&` and globs work. Defaults to the top-level
+    /// `shell`.
+    #[serde(default)]
+    pub shell: Option<ShellSetting>,
+    /// When to restart the task after it exits: `always`, `on-failure` or
+    /// `never` (the default). Also accepted as `autorestart`, where `true`
+    /// means `always`.
+    #[serde(
+        default,
+        alias = "autorestart",
+        deserialize_with = "deserialize_restart_policy"
+    )]
+    #[schemars(with = "RawRestartPolicy")]
+    pub restart: RestartPolicy,
+    /// How many times in a row the task may be restarted after failing
+    /// quickly before it is considered to be crash looping (default 10).
+    #[serde(default)]
+    pub max_restarts: Option<u32>,
+    /// How long to wait (in seconds) before restarting the task (default 1).
+    /// Doubles with every consecutive failure, up to 60 seconds.
+    #[serde(default, deserialize_with = "deserialize_optional_duration_secs")]
+    #[schemars(with = "Option<f64>", range(min = 0))]
+    pub restart_delay: Option<Duration>,
+    /// Globs, relative to the task's directory, of the files an `ensure`
+    /// task reads. It is skipped if they haven't changed since it last
+    /// succeeded (and its `outputs` exist).
     #[serde(default)]
-    pub require: Vec<String>,
-    /// Whether to automatically restart the task when it exits.
+    pub sources: Vec<String>,
+    /// Globs of the files an `ensure` task creates. It is run again if any
+    /// of them matches nothing.
     #[serde(default)]
-    pub autorestart: bool,
+    pub outputs: Vec<String>,
+    /// How long (in seconds) an `ensure` task may run before it is stopped
+    /// and counted as failed. No limit by default.
+    #[serde(default, deserialize_with = "deserialize_optional_duration_secs")]
+    #[schemars(with = "Option<f64>", range(min = 0))]
+    pub timeout: Option<Duration>,
+    /// How many more times to run an `ensure` task after it fails before
+    /// giving up (default 0).
+    #[serde(default)]
+    pub retries: u32,
+    /// How long to wait (in seconds) before retrying a failed `ensure` task
+    /// (default 1).
+    #[serde(default, deserialize_with = "deserialize_optional_duration_secs")]
+    #[schemars(with = "Option<f64>", range(min = 0))]
+    pub retry_delay: Option<Duration>,
+    /// A shell command deciding whether an `ensure` task can be skipped: it
+    /// is skipped if the command exits with status 0.
+    #[serde(default)]
+    pub skip_if: Option<String>,
+    /// Globs, relative to the task's directory, of files whose changes
+    /// restart the task (or re-run it, for `ensure` tasks).
+    #[serde(default)]
+    pub watch: Vec<String>,
+    /// Globs of files to leave out of `watch`.
+    #[serde(default)]
+    pub watch_ignore: Vec<String>,
     /// Whether to show timestamps for log messages.
     #[serde(default)]
     pub timestamps: bool,
+    /// The signal sent first when stopping the task (default `SIGINT`).
+    #[serde(default, deserialize_with = "deserialize_signal")]
+    #[schemars(with = "Option<String>")]
+    pub stop_signal: Option<Signal>,
+    /// How long to wait (in seconds) after `stop_signal` before escalating
+    /// to `SIGTERM` and then `SIGKILL`. Defaults to 10 seconds when
+    /// `stop_signal` is set and 0.3 seconds otherwise.
+    #[serde(default, deserialize_with = "deserialize_optional_duration_secs")]
+    #[schemars(with = "Option<f64>", range(min = 0))]
+    pub stop_timeout: Option<Duration>,
+    /// What the task reads as its standard input: `null` (the default) or
+    /// `pipe`, which lets you type to it in input mode.
+    #[serde(default)]
+    pub stdin: StdinMode,
+    /// Run the task under a pseudo-terminal instead of with pipes, so tools
+    /// that check for a terminal keep their progress output and don't buffer
+    /// it. Its stdout and stderr are merged, and it can always be typed to.
+    /// Color escape sequences are kept, like in piped output, and the ones
+    /// that move the cursor are removed.
+    #[serde(default)]
+    pub tty: bool,
     /// Optional healthcheck configuration. When specified, dependents will
     /// wait for this task's healthcheck to pass before starting.
     #[serde(default)]
     pub healthcheck: Option<Healthcheck>,
+    /// Environment variables for the task. These override values from the
+    /// top-level `env` and from `env_file`.
+    #[serde(default, deserialize_with = "deserialize_env")]
+    #[schemars(with = "IndexMap<String, EnvValue>")]
+    pub env: IndexMap<String, String>,
+    /// Dotenv files to load, relative to the directory containing the YAML
+    /// file. Later files override earlier ones.
+    #[serde(default)]
+    pub env_file: Vec<String>,
+}
+
+impl TaskConfiguration {
+    /// The tasks this task waits for when `selected` are the tasks being
+    /// started: everything in `require`, and whatever in `after` is selected.
+    pub fn dependencies<'a>(
+        &'a self,
+        selected: &'a HashSet<String>,
+    ) -> impl Iterator<Item = &'a Dependency> {
+        self.require
+            .iter()
+            .chain(self.after.iter().filter(|dep| selected.contains(&dep.task)))
+    }
+
+    /// Whether the task is skipped based on its `sources` and `outputs`.
+    pub fn tracks_freshness(&self) -> bool {
+        !self.sources.is_empty() || !self.outputs.is_empty()
+    }
+
+    /// The most consecutive failed restarts allowed by `max_restarts`.
+    pub fn max_restarts(&self) -> u32 {
+        self.max_restarts.unwrap_or(DEFAULT_MAX_RESTARTS)
+    }
+
+    /// How long to wait before the next restart, after `failures`
+    /// consecutive failures.
+    pub fn restart_delay(&self, failures: u32) -> Duration {
+        let base = self.restart_delay.unwrap_or(DEFAULT_RESTART_DELAY);
+        let factor = 2u32.saturating_pow(failures.saturating_sub(1));
+        base.saturating_mul(factor).min(MAX_RESTART_DELAY.max(base))
+    }
+
+    /// How long to wait before retrying a failed `ensure` task.
+    pub fn retry_delay(&self) -> Duration {
+        self.retry_delay.unwrap_or(DEFAULT_RETRY_DELAY)
+    }
+}
+
+/// A task listed in `require`, with what it has to reach before the task
+/// requiring it is started.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub task: String,
+    /// If omitted, `ensure` tasks must complete successfully, `run` tasks
+    /// with a healthcheck must be healthy and other tasks must be started.
+    pub condition: Option<DependencyCondition>,
+}
+
+impl Dependency {
+    /// The condition to wait for, given the required task's configuration.
+    pub fn condition_for(&self, dep_config: &TaskConfiguration) -> DependencyCondition {
+        match (self.condition, &dep_config.action) {
+            (Some(condition), _) => condition,
+            (None, Some(TaskAction::Ensure { .. })) => DependencyCondition::CompletedSuccessfully,
+            (None, Some(TaskAction::Run { .. })) if dep_config.healthcheck.is_some() => {
+                DependencyCondition::Healthy
+            }
+            (None, _) => DependencyCondition::Started,
+        }
+    }
+}
+
+impl From<&str> for Dependency {
+    fn from(task: &str) -> Self {
+        Dependency {
+            task: task.to_string(),
+            condition: None,
+        }
+    }
+}
+
+/// What a required task has to reach before its dependents start.
+#[derive(Debug, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyCondition {
+    /// The task has been started.
+    Started,
+    /// The task's healthcheck has passed.
+    Healthy,
+    /// The task has exited with status 0.
+    CompletedSuccessfully,
+}
+
+/// A `require` entry as written in YAML: a task name, or a task with a
+/// condition.
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+#[schemars(rename = "Dependency")]
+enum RawDependency {
+    Task(String),
+    WithCondition {
+        /// The required task.
+        task: String,
+        /// `started`, `healthy` or `completed_successfully`.
+        #[serde(default)]
+        condition: Option<DependencyCondition>,
+    },
+}
+
+impl JsonSchema for Dependency {
+    fn schema_name() -> Cow<'static, str> {
+        RawDependency::schema_name()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        RawDependency::json_schema(generator)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Dependency {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(match RawDependency::deserialize(deserializer)? {
+            RawDependency::Task(task) => Dependency {
+                task,
+                condition: None,
+            },
+            RawDependency::WithCondition { task, condition } => Dependency { task, condition },
+        })
+    }
 }
 
 /// Represents the action to be performed for a task.
@@ -171,7 +640,7 @@ pub struct TaskConfiguration {
 /// a command to be executed. `ensure` is used for something that should run
 /// to completion before the task is considered ready, while `run` is used
 /// for long-running tasks. These are mutually exclusive.
-#[derive(Debug, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Deserialize, JsonSchema, PartialEq, Clone)]
 #[serde(untagged)]
 pub enum TaskAction {
     Ensure {
@@ -184,11 +653,13 @@ pub enum TaskAction {
     },
 }
 
-/// Represents a command value that can be either a string or a boolean.
-#[derive(Debug, Deserialize, Clone, PartialEq)]
+/// Represents a command value that can be a string, a list of arguments or
+/// a boolean.
+#[derive(Debug, Deserialize, JsonSchema, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum CommandValue {
     String(Cow<'static, str>),
+    List(Vec<String>),
     Bool(bool),
 }
 
@@ -196,12 +667,111 @@ impl CommandValue {
     pub fn as_command(&self) -> Cow<'static, str> {
         match self {
             CommandValue::String(s) => s.clone(),
+            CommandValue::List(args) => Cow::Owned(shell_words::join(args)),
             CommandValue::Bool(true) => Cow::Borrowed("true"),
             CommandValue::Bool(false) => Cow::Borrowed("false"),
         }
     }
+
+    /// The program and arguments to execute.
+    ///
+    /// Without a shell, strings are split into arguments with shell quoting
+    /// rules (but no pipes, globs or variable expansion) and lists are used
+    /// as-is. With a shell, the command is passed to `<shell> -c`.
+    pub fn to_argv(&self, shell: Option<&str>) -> Result<Vec<String>, String> {
+        let command = self.as_command();
+        if command.trim().is_empty() {
+            return Err("command is empty".to_string());
+        }
+
+        match (self, shell) {
+            (_, Some(shell)) => Ok(vec![
+                shell.to_string(),
+                "-c".to_string(),
+                command.into_owned(),
+            ]),
+            (CommandValue::List(args), None) => Ok(args.clone()),
+            (_, None) => {
+                shell_words::split(&command).map_err(|e| format!("failed to parse command: {e}"))
+            }
+        }
+    }
+}
+
+/// Whether to run commands through a shell: `true` for the default shell, or
+/// the shell program to use (e.g. `bash`).
+#[derive(Debug, Deserialize, JsonSchema, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ShellSetting {
+    Enabled(bool),
+    Program(String),
+}
+
+/// The shell used for `shell: true` when no program is configured.
+pub const DEFAULT_SHELL: &str = "sh";
+
+/// When a `run` task is restarted after its process exits.
+#[derive(Debug, Default, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    /// Restart whenever the task exits.
+    Always,
+    /// Restart only when the task exits with a non-zero status.
+    OnFailure,
+    /// Never restart the task.
+    #[default]
+    Never,
+}
+
+impl RestartPolicy {
+    /// Whether a task that exited with `exit_code` should be restarted.
+    pub fn should_restart(self, exit_code: Option<i32>) -> bool {
+        match self {
+            RestartPolicy::Always => true,
+            RestartPolicy::OnFailure => exit_code != Some(0),
+            RestartPolicy::Never => false,
+        }
+    }
 }
 
+/// A restart policy as written in the config, where `true` and `false` are
+/// kept from the old boolean `autorestart`.
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum RawRestartPolicy {
+    Enabled(bool),
+    Policy(RestartPolicy),
+}
+
+fn deserialize_restart_policy<'de, D>(deserializer: D) -> Result<RestartPolicy, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match RawRestartPolicy::deserialize(deserializer)? {
+        RawRestartPolicy::Enabled(true) => RestartPolicy::Always,
+        RawRestartPolicy::Enabled(false) => RestartPolicy::Never,
+        RawRestartPolicy::Policy(policy) => policy,
+    })
+}
+
+/// The default for `max_restarts`.
+pub const DEFAULT_MAX_RESTARTS: u32 = 10;
+/// The default for `restart_delay`.
+pub const DEFAULT_RESTART_DELAY: Duration = Duration::from_secs(1);
+/// The longest the restart backoff grows to.
+pub const MAX_RESTART_DELAY: Duration = Duration::from_secs(60);
+/// The default for `retry_delay`.
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// The default for `stop_signal`.
+pub const DEFAULT_STOP_SIGNAL: Signal = Signal::SIGINT;
+/// The default for `stop_timeout`, short since most tasks exit right away on
+/// `SIGINT`.
+pub const DEFAULT_STOP_TIMEOUT: Duration = Duration::from_millis(300);
+/// The default for `stop_timeout` when a task sets `stop_signal`. A task that
+/// picks its own signal usually does so to shut down gracefully, which takes
+/// longer than [`DEFAULT_STOP_TIMEOUT`] allows.
+pub const STOP_SIGNAL_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -267,10 +837,10 @@ mod tests {
         assert_eq!(
             map["ping-demo"].require,
             vec![
-                "google-ping".to_string(),
-                "cloudflare-ping".to_string(),
-                "short-lived".to_string(),
-                "auto-restarting".to_string()
+                Dependency::from("google-ping"),
+                Dependency::from("cloudflare-ping"),
+                Dependency::from("short-lived"),
+                Dependency::from("auto-restarting")
             ]
         );
         assert!(map["ping-demo"].action.is_none());
@@ -340,7 +910,7 @@ tasks:
         );
         assert_eq!(task.cwd, None);
         assert_eq!(task.display, None);
-        assert_eq!(task.require, Vec::<String>::new());
+        assert!(task.require.is_empty());
     }
 
     #[test]
@@ -397,7 +967,7 @@ tasks:
             })
         );
         assert_eq!(task.display, Some(vec![]));
-        assert_eq!(task.require, Vec::<String>::new());
+        assert!(task.require.is_empty());
     }
 
     #[test]
@@ -442,6 +1012,296 @@ tasks:
         }
     }
 
+    #[test]
+    fn test_command_list() {
+        let yaml = r#"
+    tasks:
+      task:
+        run: [python, -c, "print('a b')"]
+    "#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let Some(TaskAction::Run { command }) = &config.tasks["task"].action else {
+            panic!("expected run action");
+        };
+        assert_eq!(
+            command,
+            &CommandValue::List(vec![
+                "python".to_string(),
+                "-c".to_string(),
+                "print('a b')".to_string()
+            ])
+        );
+        assert_eq!(
+            command.to_argv(None).unwrap(),
+            vec!["python", "-c", "print('a b')"]
+        );
+        assert_eq!(command.as_command(), "python -c 'print('\\''a b'\\'')'");
+    }
+
+    #[test]
+    fn test_command_to_argv() {
+        let command = CommandValue::String(Cow::Borrowed("cat *.log | grep 'an error'"));
+        assert_eq!(
+            command.to_argv(None).unwrap(),
+            vec!["cat", "*.log", "|", "grep", "an error"]
+        );
+        assert_eq!(
+            command.to_argv(Some("bash")).unwrap(),
+            vec!["bash", "-c", "cat *.log | grep 'an error'"]
+        );
+        assert_eq!(
+            CommandValue::Bool(true).to_argv(Some("sh")).unwrap(),
+            vec!["sh", "-c", "true"]
+        );
+
+        let err = CommandValue::String(Cow::Borrowed("echo 'oops"))
+            .to_argv(None)
+            .unwrap_err();
+        assert!(err.contains("failed to parse command"));
+        let err = CommandValue::String(Cow::Borrowed("  "))
+            .to_argv(Some("sh"))
+            .unwrap_err();
+        assert!(err.contains("empty"));
+        assert!(CommandValue::List(vec![]).to_argv(None).is_err());
+    }
+
+    #[test]
+    fn test_stop_signal_invalid() {
+        let yaml = r#"
+    tasks:
+      task:
+        run: ./server
+        stop_signal: SIGWHATEVER
+    "#;
+        let err = serde_yaml::from_str::<Config>(yaml).unwrap_err();
+        assert!(err.to_string().contains("unknown signal: SIGWHATEVER"));
+
+        let yaml = r#"
+    tasks:
+      task:
+        run: ./server
+        stop_timeout: -1
+    "#;
+        assert!(serde_yaml::from_str::<Config>(yaml).is_err());
+    }
+
+    #[test]
+    fn test_require_conditions() {
+        let yaml = r#"
+    tasks:
+      app:
+        run: ./app
+        require:
+          - logger
+          - task: db
+            condition: healthy
+          - task: seed
+            condition: completed_successfully
+          - task: cache
+    "#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            config.tasks["app"].require,
+            vec![
+                Dependency::from("logger"),
+                Dependency {
+                    task: "db".to_string(),
+                    condition: Some(DependencyCondition::Healthy),
+                },
+                Dependency {
+                    task: "seed".to_string(),
+                    condition: Some(DependencyCondition::CompletedSuccessfully),
+                },
+                Dependency::from("cache"),
+            ]
+        );
+
+        let yaml = r#"
+    tasks:
+      app:
+        run: ./app
+        require:
+          - task: db
+            condition: ready
+    "#;
+        assert!(serde_yaml::from_str::<Config>(yaml).is_err());
+    }
+
+    #[test]
+    fn test_dependency_default_condition() {
+        let ensure = TaskConfiguration {
+            action: Some(TaskAction::Ensure {
+                command: CommandValue::Bool(true),
+            }),
+            ..Default::default()
+        };
+        let run = TaskConfiguration {
+            action: Some(TaskAction::Run {
+                command: CommandValue::Bool(true),
+            }),
+            ..Default::default()
+        };
+        let checked = TaskConfiguration {
+            action: Some(TaskAction::Run {
+                command: CommandValue::Bool(true),
+            }),
+            healthcheck: Some(Healthcheck {
+                method: HealthcheckMethod::Cmd("true".to_string()),
+                interval: Duration::from_secs(1),
+                timeout: DEFAULT_HEALTHCHECK_TIMEOUT,
+                retries: None,
+                start_period: Duration::ZERO,
+                mode: HealthcheckMode::Readiness,
+                restart_on_unhealthy: false,
+            }),
+            ..Default::default()
+        };
+
+        let dep = Dependency::from("dep");
+        assert_eq!(
+            dep.condition_for(&ensure),
+            DependencyCondition::CompletedSuccessfully
+        );
+        assert_eq!(dep.condition_for(&run), DependencyCondition::Started);
+        assert_eq!(dep.condition_for(&checked), DependencyCondition::Healthy);
+
+        let dep = Dependency {
+            task: "dep".to_string(),
+            condition: Some(DependencyCondition::Started),
+        };
+        assert_eq!(dep.condition_for(&checked), DependencyCondition::Started);
+    }
+
+    #[test]
+    fn test_restart_policy() {
+        let yaml = r#"
+    tasks:
+      default:
+        run: ./server
+      legacy-on:
+        run: ./server
+        autorestart: true
+      legacy-off:
+        run: ./server
+        autorestart: false
+      always:
+        run: ./server
+        restart: always
+      on-failure:
+        run: ./server
+        restart: on-failure
+    "#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let restart = |name: &str| config.tasks[name].restart;
+        assert_eq!(restart("default"), RestartPolicy::Never);
+        assert_eq!(restart("legacy-on"), RestartPolicy::Always);
+        assert_eq!(restart("legacy-off"), RestartPolicy::Never);
+        assert_eq!(restart("always"), RestartPolicy::Always);
+        assert_eq!(restart("on-failure"), RestartPolicy::OnFailure);
+
+        assert!(RestartPolicy::Always.should_restart(Some(0)));
+        assert!(!RestartPolicy::OnFailure.should_restart(Some(0)));
+        assert!(RestartPolicy::OnFailure.should_restart(Some(1)));
+        assert!(RestartPolicy::OnFailure.should_restart(None));
+        assert!(!RestartPolicy::Never.should_restart(Some(1)));
+
+        let yaml = r#"
+    tasks:
+      task:
+        run: ./server
+        restart: sometimes
+    "#;
+        assert!(serde_yaml::from_str::<Config>(yaml).is_err());
+    }
+
+    #[test]
+    fn test_restart_delay_backoff() {
+        let task = TaskConfiguration::default();
+        assert_eq!(task.max_restarts(), DEFAULT_MAX_RESTARTS);
+        assert_eq!(task.restart_delay(0), Duration::from_secs(1));
+        assert_eq!(task.restart_delay(1), Duration::from_secs(1));
+        assert_eq!(task.restart_delay(2), Duration::from_secs(2));
+        assert_eq!(task.restart_delay(4), Duration::from_secs(8));
+        assert_eq!(task.restart_delay(10), MAX_RESTART_DELAY);
+        assert_eq!(task.restart_delay(u32::MAX), MAX_RESTART_DELAY);
+
+        let task = TaskConfiguration {
+            max_restarts: Some(3),
+            restart_delay: Some(Duration::from_millis(250)),
+            ..Default::default()
+        };
+        assert_eq!(task.max_restarts(), 3);
+        assert_eq!(task.restart_delay(3), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_ensure_timeout_and_skipping() {
+        let yaml = r#"
+    tasks:
+      install:
+        ensure: npm install
+        timeout: 90
+        sources: [package.json]
+        outputs: [node_modules]
+      migrate:
+        ensure: ./migrate
+        timeout: 0.5
+        skip_if: ./migrate status
+    "#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let install = &config.tasks["install"];
+        assert_eq!(install.timeout, Some(Duration::from_secs(90)));
+        assert!(install.tracks_freshness());
+        let migrate = &config.tasks["migrate"];
+        assert_eq!(migrate.timeout, Some(Duration::from_millis(500)));
+        assert_eq!(migrate.skip_if.as_deref(), Some("./migrate status"));
+        assert!(!migrate.tracks_freshness());
+        assert_eq!(migrate.retries, 0);
+        assert_eq!(migrate.retry_delay(), DEFAULT_RETRY_DELAY);
+
+        let yaml = r#"
+    tasks:
+      migrate:
+        ensure: ./migrate
+        retries: 2
+        retry_delay: 0.25
+    "#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let migrate = &config.tasks["migrate"];
+        assert_eq!(migrate.retries, 2);
+        assert_eq!(migrate.retry_delay(), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_task_shell() {
+        let yaml = r#"
+    tasks:
+      direct:
+        run: ./server
+      default-shell:
+        run: ./server | tee log
+        shell: true
+      bash:
+        run: ./server |& tee log
+        shell: bash
+    "#;
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        let shell = |config: &Config, name: &str| config.task_shell(&config.tasks[name]);
+        assert_eq!(shell(&config, "direct"), None);
+        assert_eq!(shell(&config, "default-shell").as_deref(), Some("sh"));
+        assert_eq!(shell(&config, "bash").as_deref(), Some("bash"));
+
+        // The top-level shell applies to every task and picks the program for
+        // `shell: true`
+        config.shell = Some(ShellSetting::Program("zsh".to_string()));
+        assert_eq!(shell(&config, "direct").as_deref(), Some("zsh"));
+        assert_eq!(shell(&config, "default-shell").as_deref(), Some("zsh"));
+        assert_eq!(shell(&config, "bash").as_deref(), Some("bash"));
+
+        config.tasks["direct"].shell = Some(ShellSetting::Enabled(false));
+        assert_eq!(shell(&config, "direct"), None);
+    }
+
     #[test]
     fn test_healthcheck_parsing_cmd() {
         let yaml = r#"
@@ -503,6 +1363,76 @@ tasks:
         assert_eq!(hc.interval, std::time::Duration::from_millis(500));
     }
 
+    #[test]
+    fn test_healthcheck_timeout_retries_start_period() {
+        let yaml = r#"
+tasks:
+  defaults:
+    run: ./server
+    healthcheck:
+      cmd: "true"
+      interval: 1
+  custom:
+    run: ./server
+    healthcheck:
+      tool: is-port-open 8080
+      interval: 1
+      timeout: 2.5
+      retries: 3
+      start_period: 60
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let hc = config.tasks["defaults"].healthcheck.as_ref().unwrap();
+        assert_eq!(hc.timeout, DEFAULT_HEALTHCHECK_TIMEOUT);
+        assert_eq!(hc.retries, None);
+        assert_eq!(hc.start_period, Duration::ZERO);
+
+        let hc = config.tasks["custom"].healthcheck.as_ref().unwrap();
+        assert_eq!(hc.timeout, Duration::from_millis(2500));
+        assert_eq!(hc.retries, Some(3));
+        assert_eq!(hc.start_period, Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_healthcheck_mode() {
+        let yaml = r#"
+tasks:
+  readiness:
+    run: ./server
+    healthcheck:
+      cmd: "true"
+      interval: 1
+  liveness:
+    run: ./server
+    healthcheck:
+      cmd: "true"
+      interval: 1
+      mode: continuous
+      restart_on_unhealthy: true
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let hc = config.tasks["readiness"].healthcheck.as_ref().unwrap();
+        assert_eq!(hc.mode, HealthcheckMode::Readiness);
+        assert!(!hc.restart_on_unhealthy);
+        assert_eq!(hc.retry_limit(false), None);
+        assert_eq!(hc.retry_limit(true), Some(DEFAULT_LIVENESS_RETRIES));
+
+        let hc = config.tasks["liveness"].healthcheck.as_ref().unwrap();
+        assert_eq!(hc.mode, HealthcheckMode::Continuous);
+        assert!(hc.restart_on_unhealthy);
+
+        let yaml = r#"
+tasks:
+  task:
+    run: ./server
+    healthcheck:
+      cmd: "true"
+      interval: 1
+      mode: sometimes
+"#;
+        assert!(serde_yaml::from_str::<Config>(yaml).is_err());
+    }
+
     #[test]
     fn test_healthcheck_optional() {
         let yaml = r#"
@@ -705,4 +1635,82 @@ tasks:
         let err = result.unwrap_err().to_string();
         assert!(err.contains("requires exactly one argument"));
     }
+
+    #[test]
+    fn test_env_parsing() {
+        let yaml = r#"
+env:
+  RUST_LOG: debug
+  PORT: 8080
+tasks:
+  task:
+    run: ./server
+    env:
+      DEBUG: true
+      RATIO: 0.5
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.env["RUST_LOG"], "debug");
+        assert_eq!(config.env["PORT"], "8080");
+        let task = &config.tasks["task"];
+        assert_eq!(task.env["DEBUG"], "true");
+        assert_eq!(task.env["RATIO"], "0.5");
+        assert!(task.env_file.is_empty());
+    }
+
+    #[test]
+    fn test_task_env_precedence() {
+        let yaml = r#"
+env:
+  PORT: 1000
+  RUST_LOG: info
+tasks:
+  task:
+    run: ./server
+    env_file: [example.env]
+    env:
+      RUST_LOG: debug
+  other:
+    run: ./other
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let env = config.task_env("task", Path::new("tests/data")).unwrap();
+        assert_eq!(
+            env,
+            vec![
+                ("PORT".to_string(), "3000".to_string()),
+                ("RUST_LOG".to_string(), "debug".to_string()),
+                (
+                    "DATABASE_URL".to_string(),
+                    "postgres://localhost/dev".to_string()
+                ),
+            ]
+        );
+
+        let env = config.task_env("other", Path::new("tests/data")).unwrap();
+        assert_eq!(
+            env,
+            vec![
+                ("PORT".to_string(), "1000".to_string()),
+                ("RUST_LOG".to_string(), "info".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_task_env_missing_env_file() {
+        let yaml = r#"
+tasks:
+  task:
+    run: ./server
+    env_file: [does-not-exist.env]
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let err = config
+            .task_env("task", Path::new("tests/data"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("task 'task'"));
+        assert!(err.contains("does-not-exist.env"));
+    }
 }
@@ -0,0 +1,256 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::watch::{WatchFilter, literal_prefix};
+
+/// The file, next to the config, that remembers what `ensure` tasks last ran
+/// successfully against.
+pub const STATE_FILE_NAME: &str = ".rote-state.json";
+
+/// What rote remembers between runs.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct StateFile {
+    /// The fingerprint of each `ensure` task's `sources` (and command, working
+    /// directory and environment) at the end of its last successful run.
+    #[serde(default)]
+    pub fingerprints: BTreeMap<String, String>,
+}
+
+impl StateFile {
+    /// Read the state file. A missing or unreadable file is treated as
+    /// empty, which just means every task runs once more.
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let contents = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        std::fs::write(path, contents + "\n")
+    }
+}
+
+/// The files below `root` matching any of `patterns`, relative to `root`
+/// and sorted. Patterns work the same way as `watch` patterns.
+pub fn matching_files(root: &Path, patterns: &[String]) -> Result<Vec<PathBuf>> {
+    let filter = WatchFilter::new(patterns, &[])?;
+    let mut files = BTreeSet::new();
+    for pattern in patterns {
+        let (prefix, _) = literal_prefix(pattern);
+        collect_files(root, &root.join(prefix), &filter, &mut files);
+    }
+    Ok(files.into_iter().collect())
+}
+
+fn collect_files(root: &Path, path: &Path, filter: &WatchFilter, files: &mut BTreeSet<PathBuf>) {
+    if path.is_file() {
+        if let Ok(relative) = path.strip_prefix(root)
+            && filter.matches(relative)
+        {
+            files.insert(relative.to_path_buf());
+        }
+        return;
+    }
+
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        // Don't follow symlinked directories, which may loop
+        let is_dir_link = entry.file_type().is_ok_and(|t| t.is_symlink()) && entry.path().is_dir();
+        if !is_dir_link {
+            collect_files(root, &entry.path(), filter, files);
+        }
+    }
+}
+
+/// A 64-bit FNV-1a hash. Fingerprints are saved between runs, so unlike the
+/// hashers in the standard library, its output must never change.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    /// Hash `bytes` after their length, so neighbouring fields can't be
+    /// confused with each other.
+    fn write_field(&mut self, bytes: &[u8]) {
+        for byte in (bytes.len() as u64).to_le_bytes().iter().chain(bytes) {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+/// A fingerprint of the task's command, working directory `root` and
+/// environment, and of the name, size and modification time of each file
+/// below `root` matching `sources`.
+pub fn fingerprint(
+    root: &Path,
+    sources: &[String],
+    command: &[String],
+    env: &[(String, String)],
+) -> Result<String> {
+    let mut hasher = Fnv1a::new();
+    hasher.write_field(root.as_os_str().as_encoded_bytes());
+    hasher.write_field(&(command.len() as u64).to_le_bytes());
+    for arg in command {
+        hasher.write_field(arg.as_bytes());
+    }
+    hasher.write_field(&(env.len() as u64).to_le_bytes());
+    for (name, value) in env {
+        hasher.write_field(name.as_bytes());
+        hasher.write_field(value.as_bytes());
+    }
+    for file in matching_files(root, sources)? {
+        let metadata = std::fs::metadata(root.join(&file))?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        hasher.write_field(file.as_os_str().as_encoded_bytes());
+        hasher.write_field(&metadata.len().to_le_bytes());
+        hasher.write_field(&modified.as_nanos().to_le_bytes());
+    }
+    Ok(format!("{:016x}", hasher.0))
+}
+
+/// Whether every pattern in `outputs` matches at least one file.
+pub fn outputs_exist(root: &Path, outputs: &[String]) -> bool {
+    outputs.iter().all(|pattern| {
+        matching_files(root, std::slice::from_ref(pattern)).is_ok_and(|files| !files.is_empty())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rote-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("src/nested")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_matching_files() {
+        let dir = temp_dir("matching-files");
+        std::fs::write(dir.join("package.json"), "{}").unwrap();
+        std::fs::write(dir.join("src/a.js"), "").unwrap();
+        std::fs::write(dir.join("src/nested/b.js"), "").unwrap();
+        std::fs::write(dir.join("src/nested/c.css"), "").unwrap();
+
+        let patterns = ["package.json".to_string(), "src/**/*.js".to_string()];
+        assert_eq!(
+            matching_files(&dir, &patterns).unwrap(),
+            vec![
+                PathBuf::from("package.json"),
+                PathBuf::from("src/a.js"),
+                PathBuf::from("src/nested/b.js"),
+            ]
+        );
+        assert_eq!(
+            matching_files(&dir, &["src/nested".to_string()]).unwrap(),
+            vec![
+                PathBuf::from("src/nested/b.js"),
+                PathBuf::from("src/nested/c.css"),
+            ]
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_fingerprint_changes_with_sources_and_command() {
+        let dir = temp_dir("fingerprint");
+        std::fs::write(dir.join("src/a.js"), "a").unwrap();
+        let sources = ["src".to_string()];
+        let command = ["npm".to_string(), "install".to_string()];
+        let env = [("NODE_ENV".to_string(), "development".to_string())];
+
+        let first = fingerprint(&dir, &sources, &command, &env).unwrap();
+        assert_eq!(first, fingerprint(&dir, &sources, &command, &env).unwrap());
+
+        let other_command = ["npm".to_string(), "ci".to_string()];
+        assert_ne!(
+            first,
+            fingerprint(&dir, &sources, &other_command, &env).unwrap()
+        );
+
+        let other_env = [("NODE_ENV".to_string(), "production".to_string())];
+        assert_ne!(
+            first,
+            fingerprint(&dir, &sources, &command, &other_env).unwrap()
+        );
+        assert_ne!(first, fingerprint(&dir, &sources, &command, &[]).unwrap());
+
+        // Sources are relative to the working directory, so moving it to
+        // another directory with the same files still counts as a change
+        assert_ne!(
+            first,
+            fingerprint(&dir.join("src/.."), &sources, &command, &env).unwrap()
+        );
+
+        std::fs::write(dir.join("src/b.js"), "b").unwrap();
+        assert_ne!(first, fingerprint(&dir, &sources, &command, &env).unwrap());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_fingerprint_is_stable() {
+        // Saved fingerprints must keep matching across rote and Rust versions
+        let command = ["make".to_string()];
+        let env = [("A".to_string(), "1".to_string())];
+        assert_eq!(
+            fingerprint(Path::new("/nonexistent"), &[], &command, &env).unwrap(),
+            "51a64efed5f2b95f"
+        );
+    }
+
+    #[test]
+    fn test_outputs_exist() {
+        let dir = temp_dir("outputs");
+        assert!(outputs_exist(&dir, &[]));
+        assert!(!outputs_exist(&dir, &["src".to_string()]));
+
+        std::fs::write(dir.join("src/a.js"), "").unwrap();
+        assert!(outputs_exist(&dir, &["src".to_string()]));
+        assert!(!outputs_exist(
+            &dir,
+            &["src".to_string(), "dist/*.js".to_string()]
+        ));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_state_file_roundtrip() {
+        let dir = temp_dir("state-file");
+        let path = dir.join(STATE_FILE_NAME);
+        assert_eq!(StateFile::load(&path), StateFile::default());
+
+        let mut state = StateFile::default();
+        state
+            .fingerprints
+            .insert("install".to_string(), "abc".to_string());
+        state.save(&path).unwrap();
+        assert_eq!(StateFile::load(&path), state);
+
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(StateFile::load(&path), StateFile::default());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,173 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// How keys typed in input mode reach the focused task.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InputMode {
+    /// Keys are collected into a line, which is sent when Enter is pressed.
+    Line,
+    /// Every key is sent as soon as it is pressed.
+    Raw,
+}
+
+/// What to do with the focused task's stdin after a key in input mode.
+#[derive(Debug, PartialEq, Eq)]
+pub enum InputAction {
+    /// Write these bytes to it.
+    Send(Vec<u8>),
+    /// Close it, so the task reads end of file.
+    Close,
+}
+
+/// The state of input mode: its kind and the line typed so far.
+#[derive(Debug)]
+pub struct Input {
+    pub mode: InputMode,
+    pub line: String,
+}
+
+impl Input {
+    pub fn new(mode: InputMode) -> Self {
+        Self {
+            mode,
+            line: String::new(),
+        }
+    }
+
+    /// Handle a key typed in input mode, returning what to send to the task.
+    pub fn handle_key(&mut self, key: &KeyEvent) -> Option<InputAction> {
+        match self.mode {
+            InputMode::Raw => key_bytes(key).map(InputAction::Send),
+            InputMode::Line => {
+                let control = key.modifiers.contains(KeyModifiers::CONTROL);
+                match key.code {
+                    KeyCode::Enter => {
+                        let mut line = std::mem::take(&mut self.line);
+                        line.push('\n');
+                        Some(InputAction::Send(line.into_bytes()))
+                    }
+                    KeyCode::Char('d') if control && self.line.is_empty() => {
+                        Some(InputAction::Close)
+                    }
+                    KeyCode::Char('u') if control => {
+                        self.line.clear();
+                        None
+                    }
+                    KeyCode::Char(c) if !control => {
+                        self.line.push(c);
+                        None
+                    }
+                    KeyCode::Backspace => {
+                        self.line.pop();
+                        None
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+/// Whether `key` leaves input `mode`: Esc for line input, and Ctrl-] for raw
+/// input, where Esc is sent to the task like any other key.
+pub fn is_leave_key(mode: InputMode, key: &KeyEvent) -> bool {
+    match mode {
+        InputMode::Line => key.code == KeyCode::Esc,
+        // Terminals send Ctrl-] as 0x1d, which crossterm reports as Ctrl-5
+        InputMode::Raw => {
+            key.modifiers.contains(KeyModifiers::CONTROL)
+                && matches!(key.code, KeyCode::Char(']' | '5'))
+        }
+    }
+}
+
+/// The bytes a terminal would send for `key`, if it has any.
+pub fn key_bytes(key: &KeyEvent) -> Option<Vec<u8>> {
+    let bytes: &[u8] = match key.code {
+        KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            return c
+                .is_ascii_alphabetic()
+                .then(|| vec![c.to_ascii_lowercase() as u8 & 0x1f]);
+        }
+        KeyCode::Char(c) => return Some(c.to_string().into_bytes()),
+        KeyCode::Enter => b"\n",
+        KeyCode::Tab => b"\t",
+        KeyCode::Backspace => b"\x7f",
+        KeyCode::Esc => b"\x1b",
+        KeyCode::Up => b"\x1b[A",
+        KeyCode::Down => b"\x1b[B",
+        KeyCode::Right => b"\x1b[C",
+        KeyCode::Left => b"\x1b[D",
+        KeyCode::Home => b"\x1b[H",
+        KeyCode::End => b"\x1b[F",
+        KeyCode::Delete => b"\x1b[3~",
+        KeyCode::PageUp => b"\x1b[5~",
+        KeyCode::PageDown => b"\x1b[6~",
+        _ => return None,
+    };
+    Some(bytes.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn ctrl(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    #[test]
+    fn test_line_input() {
+        let mut input = Input::new(InputMode::Line);
+        for c in "helo".chars() {
+            assert_eq!(input.handle_key(&key(KeyCode::Char(c))), None);
+        }
+        input.handle_key(&key(KeyCode::Backspace));
+        input.handle_key(&key(KeyCode::Char('l')));
+        input.handle_key(&key(KeyCode::Char('o')));
+        assert_eq!(input.line, "hello");
+
+        assert_eq!(
+            input.handle_key(&key(KeyCode::Enter)),
+            Some(InputAction::Send(b"hello\n".to_vec()))
+        );
+        assert_eq!(input.line, "");
+
+        // Ctrl-D only closes stdin on an empty line, like in a terminal
+        input.handle_key(&key(KeyCode::Char('x')));
+        assert_eq!(input.handle_key(&ctrl('d')), None);
+        input.handle_key(&ctrl('u'));
+        assert_eq!(input.handle_key(&ctrl('d')), Some(InputAction::Close));
+    }
+
+    #[test]
+    fn test_raw_input() {
+        let mut input = Input::new(InputMode::Raw);
+        assert_eq!(
+            input.handle_key(&key(KeyCode::Char('é'))),
+            Some(InputAction::Send("é".as_bytes().to_vec()))
+        );
+        assert_eq!(
+            input.handle_key(&ctrl('c')),
+            Some(InputAction::Send(vec![0x03]))
+        );
+        assert_eq!(
+            input.handle_key(&key(KeyCode::Up)),
+            Some(InputAction::Send(b"\x1b[A".to_vec()))
+        );
+        assert_eq!(input.handle_key(&key(KeyCode::F(1))), None);
+        assert_eq!(input.line, "");
+    }
+
+    #[test]
+    fn test_leave_keys() {
+        assert!(is_leave_key(InputMode::Line, &key(KeyCode::Esc)));
+        assert!(!is_leave_key(InputMode::Raw, &key(KeyCode::Esc)));
+        assert!(is_leave_key(InputMode::Raw, &ctrl(']')));
+        assert!(is_leave_key(InputMode::Raw, &ctrl('5')));
+        assert!(!is_leave_key(InputMode::Line, &ctrl(']')));
+    }
+}
@@ -0,0 +1,372 @@
+use indexmap::IndexMap;
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::path::Path;
+
+use crate::config::{deserialize_env, merge_env};
+use crate::error::{Result, RoteError};
+
+/// Maximum depth when a `${task.field}` reference points at another templated field.
+const MAX_EXPANSION_DEPTH: usize = 8;
+
+/// Task fields whose string values are interpolated.
+const INTERPOLATED_FIELDS: &[&str] = &["run", "ensure", "cwd"];
+
+/// Healthcheck fields whose string values are interpolated.
+const INTERPOLATED_HEALTHCHECK_FIELDS: &[&str] = &["cmd", "tool"];
+
+/// Expand `${...}` references in a string.
+///
+/// Supported forms are `${NAME}`, which fails if `lookup` returns `None`, and
+/// `${NAME:-default}`, which uses `default` when the variable is unset or
+/// empty. `$${` produces a literal `${`. A `$` not followed by `{` is left
+/// untouched so shell syntax like `$HOME` or `$$` passes through as-is.
+pub fn expand<F>(input: &str, mut lookup: F) -> std::result::Result<String, String>
+where
+    F: FnMut(&str) -> std::result::Result<Option<String>, String>,
+{
+    let mut result = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(pos) = rest.find('$') {
+        result.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+
+        if let Some(after) = rest.strip_prefix("$${") {
+            result.push_str("${");
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("${") {
+            let end = after
+                .find('}')
+                .ok_or_else(|| format!("unterminated '${{' in '{input}'"))?;
+            let expr = &after[..end];
+            let (name, default) = match expr.split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (expr, None),
+            };
+            if !is_valid_name(name) {
+                return Err(format!("invalid variable name '{name}'"));
+            }
+            let value = lookup(name)?.filter(|v| !(default.is_some() && v.is_empty()));
+            match (value, default) {
+                (Some(value), _) => result.push_str(&value),
+                (None, Some(default)) => result.push_str(default),
+                (None, None) => return Err(format!("undefined variable '{name}'")),
+            }
+            rest = &after[end + 1..];
+        } else {
+            result.push('$');
+            rest = &rest[1..];
+        }
+    }
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+/// A variable name is an identifier, optionally prefixed with `task.`.
+fn is_valid_name(name: &str) -> bool {
+    let ident = name.strip_prefix("task.").unwrap_or(name);
+    let mut chars = ident.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// The parts of the config needed to build each task's environment.
+#[derive(Deserialize)]
+struct EnvSection {
+    #[serde(default, deserialize_with = "deserialize_env")]
+    env: IndexMap<String, String>,
+    #[serde(default)]
+    tasks: IndexMap<String, TaskEnvSection>,
+}
+
+#[derive(Deserialize)]
+struct TaskEnvSection {
+    #[serde(default, deserialize_with = "deserialize_env")]
+    env: IndexMap<String, String>,
+    #[serde(default)]
+    env_file: Vec<String>,
+}
+
+/// Expand variables in task commands (strings or each item of an argument
+/// list), `cwd` and healthcheck `cmd`/`tool` strings of a parsed (but not yet
+/// deserialized) config document.
+///
+/// `${VAR}` is looked up in the task's environment (top-level `env`, then
+/// `env_file`, then `env`) and falls back to rote's own environment.
+/// `${task.name}` is the task name and `${task.<field>}` is any other scalar
+/// field of the same task, so custom fields like `port: 8080` can be shared
+/// between `run` and `healthcheck`.
+pub fn interpolate_config(value: &mut Value, config_dir: &Path) -> Result<()> {
+    let env_section: EnvSection = serde_yaml::from_value(value.clone())?;
+
+    let Some(tasks) = value.get_mut("tasks").and_then(Value::as_mapping_mut) else {
+        return Ok(());
+    };
+
+    for (name, task) in tasks.iter_mut() {
+        let Some(task_name) = name.as_str() else {
+            continue;
+        };
+        let Some(task) = task.as_mapping_mut() else {
+            continue;
+        };
+
+        let env = match env_section.tasks.get(task_name) {
+            Some(task_env) => merge_env(
+                task_name,
+                &env_section.env,
+                &task_env.env_file,
+                &task_env.env,
+                config_dir,
+            )?,
+            None => env_section.env.clone(),
+        };
+
+        let snapshot = task.clone();
+        let expand_field = |field: &str, s: &str| {
+            expand_with_task(s, task_name, &snapshot, &env, 0)
+                .map_err(|e| RoteError::Config(format!("task '{task_name}', field '{field}': {e}")))
+        };
+
+        for field in INTERPOLATED_FIELDS {
+            match task.get_mut(*field) {
+                Some(Value::String(s)) => *s = expand_field(field, s)?,
+                Some(Value::Sequence(items)) => {
+                    for item in items {
+                        if let Value::String(s) = item {
+                            *s = expand_field(field, s)?;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(healthcheck) = task.get_mut("healthcheck").and_then(Value::as_mapping_mut) {
+            for field in INTERPOLATED_HEALTHCHECK_FIELDS {
+                if let Some(Value::String(s)) = healthcheck.get_mut(*field) {
+                    *s = expand_field(&format!("healthcheck.{field}"), s)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn expand_with_task(
+    input: &str,
+    task_name: &str,
+    task: &serde_yaml::Mapping,
+    env: &IndexMap<String, String>,
+    depth: usize,
+) -> std::result::Result<String, String> {
+    if depth > MAX_EXPANSION_DEPTH {
+        return Err("variable references are nested too deeply (cycle?)".to_string());
+    }
+
+    expand(input, |name| match name.strip_prefix("task.") {
+        Some("name") => Ok(Some(task_name.to_string())),
+        Some(field) => match task.get(field) {
+            Some(Value::String(s)) => {
+                expand_with_task(s, task_name, task, env, depth + 1).map(Some)
+            }
+            Some(Value::Number(n)) => Ok(Some(n.to_string())),
+            Some(Value::Bool(b)) => Ok(Some(b.to_string())),
+            Some(Value::Null) | None => Ok(None),
+            Some(_) => Err(format!("'task.{field}' is not a scalar value")),
+        },
+        None => Ok(env.get(name).cloned().or_else(|| std::env::var(name).ok())),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CommandValue, Config, HealthcheckMethod, HealthcheckTool, TaskAction};
+    use std::borrow::Cow;
+
+    fn lookup(name: &str) -> std::result::Result<Option<String>, String> {
+        Ok(match name {
+            "PORT" => Some("8080".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        })
+    }
+
+    fn load(yaml: &str) -> Result<Config> {
+        let mut value: Value = serde_yaml::from_str(yaml)?;
+        interpolate_config(&mut value, Path::new("tests/data"))?;
+        Ok(serde_yaml::from_value(value)?)
+    }
+
+    #[test]
+    fn test_expand_plain_text() {
+        assert_eq!(expand("echo hello", lookup).unwrap(), "echo hello");
+    }
+
+    #[test]
+    fn test_expand_variable() {
+        assert_eq!(
+            expand("./server --port ${PORT}", lookup).unwrap(),
+            "./server --port 8080"
+        );
+    }
+
+    #[test]
+    fn test_expand_default() {
+        assert_eq!(expand("${HOST:-localhost}", lookup).unwrap(), "localhost");
+        assert_eq!(expand("${PORT:-3000}", lookup).unwrap(), "8080");
+        assert_eq!(expand("${EMPTY:-fallback}", lookup).unwrap(), "fallback");
+        assert_eq!(expand("${EMPTY}", lookup).unwrap(), "");
+    }
+
+    #[test]
+    fn test_expand_undefined() {
+        let err = expand("${MISSING}", lookup).unwrap_err();
+        assert!(err.contains("undefined variable 'MISSING'"));
+    }
+
+    #[test]
+    fn test_expand_leaves_shell_syntax_alone() {
+        assert_eq!(
+            expand("echo $HOME $$ $1 $", lookup).unwrap(),
+            "echo $HOME $$ $1 $"
+        );
+    }
+
+    #[test]
+    fn test_expand_escape() {
+        assert_eq!(
+            expand("bash -c 'echo $${HOME}'", lookup).unwrap(),
+            "bash -c 'echo ${HOME}'"
+        );
+    }
+
+    #[test]
+    fn test_expand_unterminated() {
+        let err = expand("echo ${PORT", lookup).unwrap_err();
+        assert!(err.contains("unterminated"));
+    }
+
+    #[test]
+    fn test_expand_invalid_name() {
+        let err = expand("echo ${1abc}", lookup).unwrap_err();
+        assert!(err.contains("invalid variable name"));
+    }
+
+    #[test]
+    fn test_interpolate_config_env_and_task_fields() {
+        let yaml = r#"
+env:
+  HOST: 127.0.0.1
+tasks:
+  api:
+    port: 9000
+    dir: services/api
+    cwd: ${task.dir}
+    run: ./server --host ${HOST} --port ${task.port} --name ${task.name}
+    env:
+      ROTE_TEST_LOG: debug
+    healthcheck:
+      tool: is-port-open ${task.port}
+      interval: 1
+  migrate:
+    ensure: ./migrate --log ${ROTE_TEST_LOG:-info}
+"#;
+        let config = load(yaml).unwrap();
+        let api = &config.tasks["api"];
+        assert_eq!(
+            api.action,
+            Some(TaskAction::Run {
+                command: CommandValue::String(Cow::Borrowed(
+                    "./server --host 127.0.0.1 --port 9000 --name api"
+                )),
+            })
+        );
+        assert_eq!(api.cwd.as_deref(), Some("services/api"));
+        assert_eq!(
+            api.healthcheck.as_ref().unwrap().method,
+            HealthcheckMethod::Tool(HealthcheckTool::IsPortOpen { port: 9000 })
+        );
+
+        // Task env is not visible to other tasks
+        assert_eq!(
+            config.tasks["migrate"].action,
+            Some(TaskAction::Ensure {
+                command: CommandValue::String(Cow::Borrowed("./migrate --log info")),
+            })
+        );
+    }
+
+    #[test]
+    fn test_interpolate_config_command_list() {
+        let yaml = r#"
+tasks:
+  api:
+    port: 9000
+    run: [./server, --port, "${task.port}", --name, "${ROTE_TEST_NAME:-my api}"]
+"#;
+        let config = load(yaml).unwrap();
+        assert_eq!(
+            config.tasks["api"].action,
+            Some(TaskAction::Run {
+                command: CommandValue::List(vec![
+                    "./server".to_string(),
+                    "--port".to_string(),
+                    "9000".to_string(),
+                    "--name".to_string(),
+                    "my api".to_string(),
+                ]),
+            })
+        );
+    }
+
+    #[test]
+    fn test_interpolate_config_env_file() {
+        let yaml = r#"
+tasks:
+  api:
+    env_file: [example.env]
+    run: ./server --port ${PORT}
+"#;
+        let config = load(yaml).unwrap();
+        assert_eq!(
+            config.tasks["api"].action,
+            Some(TaskAction::Run {
+                command: CommandValue::String(Cow::Borrowed("./server --port 3000")),
+            })
+        );
+    }
+
+    #[test]
+    fn test_interpolate_config_undefined_names_task_and_field() {
+        let yaml = r#"
+tasks:
+  api:
+    run: ./server
+    healthcheck:
+      cmd: curl localhost:${ROTE_TEST_SURELY_UNDEFINED}
+      interval: 1
+"#;
+        let err = load(yaml).unwrap_err();
+        assert!(matches!(err, RoteError::Config(_)));
+        let err = err.to_string();
+        assert!(err.contains("task 'api'"));
+        assert!(err.contains("healthcheck.cmd"));
+        assert!(err.contains("ROTE_TEST_SURELY_UNDEFINED"));
+    }
+
+    #[test]
+    fn test_interpolate_config_self_reference() {
+        let yaml = r#"
+tasks:
+  api:
+    run: ./server ${task.run}
+"#;
+        let err = load(yaml).unwrap_err().to_string();
+        assert!(err.contains("nested too deeply"));
+    }
+}
@@ -0,0 +1,301 @@
+use std::{fmt, io, path::Path, time::Duration};
+
+use nix::unistd::Pid;
+use serde::{Deserialize, Serialize};
+
+use crate::signals::{is_process_exited, stop_process_groups};
+
+/// The file, next to the config, that lists the processes rote has started,
+/// so that a run can find what an earlier one left behind.
+pub const PID_FILE_NAME: &str = ".rote-pids.json";
+
+/// How long leftovers get to exit after `SIGTERM` before they are killed.
+const LEFTOVER_STOP_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A task process started by rote.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RecordedProcess {
+    /// The rote process that started it.
+    pub owner: u32,
+    pub task: String,
+    /// The task's process, which leads a process group with the same id.
+    pub pid: u32,
+    /// When the process started, in clock ticks since boot, to tell it apart
+    /// from a later process that was given the same pid. Not known outside
+    /// Linux or for entries written by older versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<u64>,
+    pub command: Vec<String>,
+}
+
+impl RecordedProcess {
+    /// Whether the process, or anything left in its process group, is still
+    /// running.
+    fn is_running(&self) -> bool {
+        if is_process_exited(Pid::from_raw(self.pid as i32)) {
+            return false;
+        }
+        match (self.start_time, process_start_time(self.pid)) {
+            (Some(recorded), Some(current)) => recorded == current,
+            // Only the group is left, and the system doesn't give its id to
+            // another process while it exists
+            _ => true,
+        }
+    }
+}
+
+/// When the process `pid` started, in clock ticks since boot, from field 22
+/// of `/proc/<pid>/stat`.
+#[cfg(target_os = "linux")]
+fn process_start_time(pid: u32) -> Option<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // The command name in field 2 is in parentheses and may contain spaces,
+    // so count the fields after it, starting from field 3
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(19)?.parse().ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_start_time(_pid: u32) -> Option<u64> {
+    None
+}
+
+impl fmt::Display for RecordedProcess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (pid {}): {}",
+            self.task,
+            self.pid,
+            self.command.join(" ")
+        )
+    }
+}
+
+/// The processes started by every rote run in a directory.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct PidFile {
+    #[serde(default)]
+    pub processes: Vec<RecordedProcess>,
+}
+
+impl PidFile {
+    /// Read the pid file. A missing or unreadable file is treated as empty.
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Write the pid file, or remove it once nothing is left to remember.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if self.processes.is_empty() {
+            return match std::fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            };
+        }
+        let contents = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        std::fs::write(path, contents + "\n")
+    }
+
+    /// Record that this rote process started `task` as `pid`, replacing the
+    /// task's previous process. Other rote processes may share the file, so
+    /// it is read again first.
+    pub fn record(path: &Path, task: &str, pid: u32, command: &[String]) -> io::Result<()> {
+        let owner = std::process::id();
+        let mut pid_file = Self::load(path);
+        pid_file
+            .processes
+            .retain(|process| process.owner != owner || process.task != task);
+        pid_file.processes.push(RecordedProcess {
+            owner,
+            task: task.to_string(),
+            pid,
+            start_time: process_start_time(pid),
+            command: command.to_vec(),
+        });
+        pid_file.save(path)
+    }
+
+    /// Forget the processes this rote process started, once they have all
+    /// been stopped.
+    pub fn forget_own(path: &Path) -> io::Result<()> {
+        let owner = std::process::id();
+        let mut pid_file = Self::load(path);
+        pid_file.processes.retain(|process| process.owner != owner);
+        pid_file.save(path)
+    }
+}
+
+/// Processes in the pid file at `path` that are still running although the
+/// rote process that started them is gone. Entries for processes that have
+/// exited, or whose pid now belongs to another process, are dropped from the
+/// file.
+pub fn find_leftovers(path: &Path) -> Vec<RecordedProcess> {
+    let mut pid_file = PidFile::load(path);
+    let before = pid_file.processes.len();
+
+    // Our own pid may belong to a run that has since died
+    let owner_alive =
+        |owner: u32| owner != std::process::id() && !is_process_exited(Pid::from_raw(owner as i32));
+    pid_file
+        .processes
+        .retain(|process| owner_alive(process.owner) || process.is_running());
+    if pid_file.processes.len() != before {
+        let _ = pid_file.save(path);
+    }
+
+    pid_file
+        .processes
+        .into_iter()
+        .filter(|process| !owner_alive(process.owner))
+        .collect()
+}
+
+/// Stop `leftovers` along with everything in their process groups, and drop
+/// the ones that are gone from the pid file at `path`. Leftovers whose pid
+/// has since been given to another process are left alone.
+pub fn stop_leftovers(path: &Path, leftovers: &[RecordedProcess]) {
+    let pids: Vec<Pid> = leftovers
+        .iter()
+        .filter(|process| process.is_running())
+        .map(|process| Pid::from_raw(process.pid as i32))
+        .collect();
+    stop_process_groups(&pids, LEFTOVER_STOP_TIMEOUT);
+
+    let mut pid_file = PidFile::load(path);
+    pid_file
+        .processes
+        .retain(|process| !leftovers.contains(process) || process.is_running());
+    let _ = pid_file.save(path);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::CommandExt;
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rote-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// The pid of a process that has exited.
+    fn dead_pid() -> u32 {
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        pid
+    }
+
+    #[test]
+    fn test_record_and_forget() {
+        let dir = temp_dir("pid-file");
+        let path = dir.join(PID_FILE_NAME);
+        let other = RecordedProcess {
+            owner: dead_pid(),
+            task: "db".to_string(),
+            pid: 42,
+            start_time: None,
+            command: vec!["postgres".to_string()],
+        };
+        PidFile {
+            processes: vec![other.clone()],
+        }
+        .save(&path)
+        .unwrap();
+
+        PidFile::record(&path, "web", 100, &["npm".to_string()]).unwrap();
+        PidFile::record(&path, "web", 101, &["npm".to_string()]).unwrap();
+        let processes = PidFile::load(&path).processes;
+        assert_eq!(processes.len(), 2);
+        assert_eq!(processes[0], other);
+        assert_eq!(processes[1].pid, 101);
+        assert_eq!(processes[1].owner, std::process::id());
+        assert_eq!(processes[1].start_time, process_start_time(101));
+
+        // Only our own processes are forgotten, and an empty file is removed
+        PidFile::forget_own(&path).unwrap();
+        assert_eq!(PidFile::load(&path).processes, vec![other]);
+        PidFile::default().save(&path).unwrap();
+        assert!(!path.exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_find_and_stop_leftovers() {
+        let dir = temp_dir("leftovers");
+        let path = dir.join(PID_FILE_NAME);
+
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let leftover = RecordedProcess {
+            owner: dead_pid(),
+            task: "db".to_string(),
+            pid: child.id(),
+            start_time: process_start_time(child.id()),
+            command: vec!["sleep".to_string(), "30".to_string()],
+        };
+        let exited = RecordedProcess {
+            task: "web".to_string(),
+            pid: dead_pid(),
+            ..leftover.clone()
+        };
+        let alive_owner = RecordedProcess {
+            owner: child.id(),
+            task: "api".to_string(),
+            ..leftover.clone()
+        };
+        // The pid of a process that exited, since given to `child`
+        let reused = RecordedProcess {
+            task: "worker".to_string(),
+            start_time: leftover.start_time.map(|start_time| start_time + 1),
+            ..leftover.clone()
+        };
+        PidFile {
+            processes: vec![
+                leftover.clone(),
+                exited,
+                alive_owner.clone(),
+                reused.clone(),
+            ],
+        }
+        .save(&path)
+        .unwrap();
+
+        // Processes that exited or whose pid was reused are forgotten, and ones
+        // whose rote is still running aren't leftovers
+        assert_eq!(find_leftovers(&path), vec![leftover.clone()]);
+        assert_eq!(
+            PidFile::load(&path).processes,
+            vec![leftover.clone(), alive_owner.clone()]
+        );
+        assert_eq!(
+            leftover.to_string(),
+            format!("db (pid {}): sleep 30", child.id())
+        );
+
+        // A leftover whose pid is reused by the time it is stopped is left
+        // alone
+        stop_leftovers(&path, &[reused]);
+        assert!(child.try_wait().unwrap().is_none());
+
+        let reaper = std::thread::spawn(move || child.wait().unwrap());
+        stop_leftovers(&path, &[leftover]);
+        assert!(!reaper.join().unwrap().success());
+
+        // Stopped leftovers are forgotten right away
+        assert_eq!(PidFile::load(&path).processes, vec![alive_owner]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,15 +1,24 @@
 pub mod app;
+pub mod check;
 pub mod config;
 pub mod error;
+pub mod freshness;
+pub mod input;
+pub mod interpolate;
+pub mod leftovers;
+pub mod loader;
 pub mod panel;
 pub mod process;
+pub mod pty;
 pub mod render;
+pub mod schema;
 pub mod signals;
 pub mod task_manager;
 pub mod tools;
 pub mod ui;
+pub mod watch;
 
-pub use app::{run, run_with_input};
+pub use app::{run, run_reloadable, run_with_input};
 pub use config::{Config, TaskAction, TaskConfiguration};
 pub use error::{Result, RoteError};
 pub use ui::UiEvent;
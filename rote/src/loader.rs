@@ -0,0 +1,661 @@
+use serde_yaml::{Mapping, Value};
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::error::{Result, RoteError};
+use crate::interpolate::interpolate_config;
+
+/// Config file names looked for in each directory, in order of preference.
+pub const CONFIG_FILE_NAMES: &[&str] = &["rote.yaml", "rote.yml", ".rote.yaml"];
+
+/// Entries marking the root of a repository, where discovery stops.
+const VCS_MARKERS: &[&str] = &[".git", ".hg", ".jj", ".svn"];
+
+/// Find the config files to use when none were given on the command line.
+///
+/// `rote_config` is the value of the `ROTE_CONFIG` environment variable: if
+/// set, it lists the files to load, separated like `PATH`. Otherwise the
+/// first of [`CONFIG_FILE_NAMES`] found in `start` or one of its parents is
+/// used. The search stops at the root of a repository (a directory containing
+/// `.git`, `.hg`, `.jj` or `.svn`) or at the filesystem root. Files found in
+/// a parent directory are returned as `../`-relative paths.
+pub fn discover_config(start: &Path, rote_config: Option<&OsStr>) -> Result<Vec<PathBuf>> {
+    if let Some(value) = rote_config.filter(|v| !v.is_empty()) {
+        return Ok(std::env::split_paths(value).collect());
+    }
+
+    let mut relative = PathBuf::new();
+    for dir in start.ancestors() {
+        for name in CONFIG_FILE_NAMES {
+            if dir.join(name).is_file() {
+                return Ok(vec![relative.join(name)]);
+            }
+        }
+        if VCS_MARKERS.iter().any(|marker| dir.join(marker).exists()) {
+            break;
+        }
+        relative.push("..");
+    }
+
+    Err(RoteError::Config(format!(
+        "no config file found: looked for {} in '{}' and its parent directories \
+         (use --config or ROTE_CONFIG to choose one)",
+        CONFIG_FILE_NAMES.join(", "),
+        start.display()
+    )))
+}
+
+/// A configuration assembled from one or more YAML files.
+#[derive(Debug)]
+pub struct LoadedConfig {
+    pub config: Config,
+    /// The directory that relative paths (`cwd`, `env_file`) are resolved
+    /// against: the directory of the first config file.
+    pub config_dir: PathBuf,
+    /// Every file that contributed to the config, in the order it was merged.
+    pub files: Vec<PathBuf>,
+}
+
+/// Load and merge the given config files, in order.
+///
+/// Each file's `include` list is merged underneath the file itself (paths are
+/// relative to the including file), and every following file is merged on
+/// top of the previous ones. Mappings are merged key by key, so an override
+/// file can patch single fields of a task defined elsewhere; any other value
+/// (including lists) replaces the previous one, and `null` removes the key.
+/// Once the documents have been merged, `extends` is resolved and variables
+/// are interpolated.
+pub fn load_config(paths: &[PathBuf]) -> Result<LoadedConfig> {
+    let first = paths
+        .first()
+        .ok_or_else(|| RoteError::Config("no config file given".to_string()))?;
+    let config_dir = first.parent().map(Path::to_path_buf).unwrap_or_default();
+
+    let mut files = Vec::new();
+    let mut merged = Value::Mapping(Mapping::new());
+    for path in paths {
+        let document = load_document(path, &mut Vec::new(), &mut files)?;
+        merge_document(&mut merged, document, path)?;
+    }
+
+    resolve_extends(&mut merged)?;
+    interpolate_config(&mut merged, &config_dir)?;
+    let config: Config = serde_yaml::from_value(merged)?;
+
+    Ok(LoadedConfig {
+        config,
+        config_dir,
+        files,
+    })
+}
+
+/// Read a single file and merge its includes underneath it. `stack` holds
+/// the canonical paths of the files including it.
+fn load_document(path: &Path, stack: &mut Vec<PathBuf>, files: &mut Vec<PathBuf>) -> Result<Value> {
+    let read_error = |e: std::io::Error| {
+        RoteError::Config(format!(
+            "failed to read config file '{}': {e}",
+            path.display()
+        ))
+    };
+    let yaml_str = fs::read_to_string(path).map_err(read_error)?;
+
+    // The same file can be spelled differently, like `./a.yaml` or
+    // `../dir/a.yaml`
+    let canonical = fs::canonicalize(path).map_err(read_error)?;
+    if stack.contains(&canonical) {
+        let chain: Vec<String> = stack
+            .iter()
+            .chain(std::iter::once(&canonical))
+            .map(|p| p.display().to_string())
+            .collect();
+        return Err(RoteError::Config(format!(
+            "include cycle detected: {}",
+            chain.join(" -> ")
+        )));
+    }
+    let document: Value = serde_yaml::from_str(&yaml_str)
+        .map_err(|e| RoteError::Config(format!("failed to parse '{}': {e}", path.display())))?;
+
+    let includes: Vec<String> = match document.get("include") {
+        None | Some(Value::Null) => Vec::new(),
+        Some(value) => serde_yaml::from_value(value.clone()).map_err(|e| {
+            RoteError::Config(format!(
+                "'{}': 'include' must be a list of file names: {e}",
+                path.display()
+            ))
+        })?,
+    };
+
+    if includes.is_empty() {
+        files.push(path.to_path_buf());
+        return Ok(document);
+    }
+
+    stack.push(canonical);
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    let mut merged = Value::Mapping(Mapping::new());
+    for include in &includes {
+        let include_path = base_dir.join(include);
+        let included = load_document(&include_path, stack, files)?;
+        merge_document(&mut merged, included, &include_path)?;
+    }
+    stack.pop();
+
+    files.push(path.to_path_buf());
+    merge_document(&mut merged, document, path)?;
+    Ok(merged)
+}
+
+/// Merge a whole config document on top of `base`, checking task conflicts.
+fn merge_document(base: &mut Value, overlay: Value, path: &Path) -> Result<()> {
+    let Value::Mapping(overlay) = overlay else {
+        if overlay.is_null() {
+            // An empty file contributes nothing
+            return Ok(());
+        }
+        return Err(RoteError::Config(format!(
+            "'{}': config file must be a mapping",
+            path.display()
+        )));
+    };
+
+    if let (Some(Value::Mapping(base_tasks)), Some(Value::Mapping(overlay_tasks))) =
+        (base.get("tasks"), overlay.get("tasks"))
+    {
+        for (name, overlay_task) in overlay_tasks {
+            if let Some(base_task) = base_tasks.get(name) {
+                check_action_conflict(name, base_task, overlay_task, path)?;
+            }
+        }
+    }
+
+    merge_values(base, Value::Mapping(overlay));
+    Ok(())
+}
+
+/// A task's action kind can't silently change between files, since `run` and
+/// `ensure` together would be ambiguous. The override must clear the old one.
+fn check_action_conflict(name: &Value, base: &Value, overlay: &Value, path: &Path) -> Result<()> {
+    let is_set = |task: &Value, key: &str| task.get(key).is_some_and(|v| !v.is_null());
+    let clears = |task: &Value, key: &str| task.get(key).is_some_and(Value::is_null);
+
+    for (new, old) in [("run", "ensure"), ("ensure", "run")] {
+        if is_set(overlay, new) && is_set(base, old) && !clears(overlay, old) {
+            let name = name.as_str().unwrap_or("?");
+            return Err(RoteError::Config(format!(
+                "'{}': task '{name}' sets '{new}' but is already defined with '{old}' \
+                 (add '{old}: null' to replace it)",
+                path.display()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Recursively merge `overlay` into `base`. Mappings are merged key by key
+/// (preserving the order of existing keys and appending new ones), `null`
+/// removes a key, and anything else replaces the existing value.
+fn merge_values(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Mapping(base), Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                if value.is_null() {
+                    base.remove(&key);
+                } else if let Some(existing) = base.get_mut(&key) {
+                    merge_values(existing, value);
+                } else {
+                    base.insert(key, value);
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Replace every task that `extends` another task or template with the
+/// parent's fields merged underneath its own, and drop the `templates`
+/// section. Fields are merged the same way as override files, except that a
+/// task defining `run` or `ensure` replaces whichever action it inherited.
+fn resolve_extends(document: &mut Value) -> Result<()> {
+    let templates = match document
+        .as_mapping_mut()
+        .and_then(|m| m.remove("templates"))
+    {
+        None | Some(Value::Null) => Mapping::new(),
+        Some(Value::Mapping(templates)) => templates,
+        Some(_) => {
+            return Err(RoteError::Config(
+                "'templates' must be a mapping of template names to task settings".to_string(),
+            ));
+        }
+    };
+
+    let Some(Value::Mapping(tasks)) = document.get_mut("tasks") else {
+        return Ok(());
+    };
+
+    if let Some(name) = templates.keys().find(|name| tasks.contains_key(*name)) {
+        return Err(RoteError::Config(format!(
+            "'{}' is defined both as a task and as a template",
+            name.as_str().unwrap_or("?")
+        )));
+    }
+
+    fn visit(
+        name: &str,
+        definitions: &[&Mapping],
+        resolved: &mut HashMap<String, Value>,
+        temp_mark: &mut HashSet<String>,
+    ) -> Result<Value> {
+        if let Some(value) = resolved.get(name) {
+            return Ok(value.clone());
+        }
+
+        if temp_mark.contains(name) {
+            return Err(RoteError::Config(format!(
+                "Circular extends detected involving '{name}'"
+            )));
+        }
+
+        let definition = definitions
+            .iter()
+            .find_map(|d| d.get(name))
+            .cloned()
+            .unwrap_or(Value::Null);
+
+        let value = match definition.get("extends") {
+            None | Some(Value::Null) => definition,
+            Some(Value::String(parent)) => {
+                if !definitions.iter().any(|d| d.contains_key(parent.as_str())) {
+                    return Err(RoteError::Config(format!(
+                        "'{name}' extends '{parent}', which is not a task or template"
+                    )));
+                }
+
+                temp_mark.insert(name.to_string());
+                let mut value = visit(parent, definitions, resolved, temp_mark)?;
+                temp_mark.remove(name);
+
+                if let Some(inherited) = value.as_mapping_mut()
+                    && (definition.get("run").is_some() || definition.get("ensure").is_some())
+                {
+                    inherited.remove("run");
+                    inherited.remove("ensure");
+                }
+                merge_values(&mut value, definition);
+                value
+            }
+            Some(_) => {
+                return Err(RoteError::Config(format!(
+                    "'{name}': 'extends' must be the name of a task or template"
+                )));
+            }
+        };
+
+        resolved.insert(name.to_string(), value.clone());
+        Ok(value)
+    }
+
+    let definitions = tasks.clone();
+    let definitions = [&templates, &definitions];
+    let mut resolved = HashMap::new();
+    let mut temp_mark = HashSet::new();
+    for (name, task) in tasks.iter_mut() {
+        if let Some(name) = name.as_str() {
+            *task = visit(name, &definitions, &mut resolved, &mut temp_mark)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CommandValue, Dependency, TaskAction};
+    use std::borrow::Cow;
+
+    /// A scratch directory tree for discovery tests, removed on drop.
+    struct TempTree(PathBuf);
+
+    impl TempTree {
+        fn new(name: &str, files: &[&str]) -> Self {
+            let root =
+                std::env::temp_dir().join(format!("rote-loader-{}-{name}", std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            for file in files {
+                let path = root.join(file);
+                if file.ends_with('/') {
+                    fs::create_dir_all(&path).unwrap();
+                } else {
+                    fs::create_dir_all(path.parent().unwrap()).unwrap();
+                    fs::write(&path, "tasks: {}\n").unwrap();
+                }
+            }
+            TempTree(root)
+        }
+    }
+
+    impl Drop for TempTree {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn data(name: &str) -> PathBuf {
+        Path::new("tests/data/overlay").join(name)
+    }
+
+    #[test]
+    fn test_merge_values_patches_fields() {
+        let mut base: Value = serde_yaml::from_str(
+            r#"
+tasks:
+  api:
+    run: ./api
+    cwd: backend
+    healthcheck:
+      tool: is-port-open 8080
+      interval: 1
+"#,
+        )
+        .unwrap();
+        let overlay: Value = serde_yaml::from_str(
+            r#"
+tasks:
+  api:
+    cwd: null
+    healthcheck:
+      interval: 5
+"#,
+        )
+        .unwrap();
+        merge_values(&mut base, overlay);
+
+        let api = &base["tasks"]["api"];
+        assert_eq!(api["run"], Value::from("./api"));
+        assert!(api.get("cwd").is_none());
+        assert_eq!(api["healthcheck"]["tool"], Value::from("is-port-open 8080"));
+        assert_eq!(api["healthcheck"]["interval"], Value::from(5));
+    }
+
+    #[test]
+    fn test_load_single_file() {
+        let loaded = load_config(&[data("common.yaml")]).unwrap();
+        assert_eq!(loaded.config_dir, Path::new("tests/data/overlay"));
+        assert_eq!(loaded.files, vec![data("common.yaml")]);
+        let names: Vec<_> = loaded.config.tasks.keys().collect();
+        assert_eq!(names, vec!["db", "api"]);
+    }
+
+    #[test]
+    fn test_load_with_include_and_override() {
+        let loaded = load_config(&[data("base.yaml"), data("override.yaml")]).unwrap();
+        assert_eq!(
+            loaded.files,
+            vec![
+                data("common.yaml"),
+                data("base.yaml"),
+                data("override.yaml")
+            ]
+        );
+
+        let config = &loaded.config;
+        assert_eq!(config.default.as_deref(), Some("web"));
+        // Included tasks come first, then tasks from the including file, then new ones
+        let names: Vec<_> = config.tasks.keys().collect();
+        assert_eq!(names, vec!["db", "api", "web", "debug"]);
+
+        // base.yaml patched api's cwd, override.yaml patched its command
+        let api = &config.tasks["api"];
+        assert_eq!(api.cwd.as_deref(), Some("backend"));
+        assert_eq!(
+            api.action,
+            Some(TaskAction::Run {
+                command: CommandValue::String(Cow::Borrowed("cargo run --release")),
+            })
+        );
+        assert_eq!(api.require, vec![Dependency::from("db")]);
+        assert_eq!(api.env["RUST_LOG"], "debug");
+
+        // override.yaml replaced db's ensure with run
+        assert_eq!(
+            config.tasks["db"].action,
+            Some(TaskAction::Run {
+                command: CommandValue::String(Cow::Borrowed("postgres -D data")),
+            })
+        );
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let err = load_config(&[data("does-not-exist.yaml")])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("does-not-exist.yaml"));
+    }
+
+    #[test]
+    fn test_load_missing_include() {
+        let err = load_config(&[data("missing-include.yaml")])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("nowhere.yaml"));
+    }
+
+    #[test]
+    fn test_load_include_cycle() {
+        let err = load_config(&[data("cycle-a.yaml")])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("include cycle"));
+        assert!(err.contains("cycle-b.yaml"));
+    }
+
+    #[test]
+    fn test_load_include_cycle_through_other_spelling() {
+        let err = load_config(&[data("self-include.yaml")])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("include cycle"));
+        assert!(err.contains("self-include.yaml"));
+    }
+
+    #[test]
+    fn test_load_action_conflict() {
+        let err = load_config(&[data("common.yaml"), data("conflict.yaml")])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("conflict.yaml"));
+        assert!(err.contains("task 'db'"));
+        assert!(err.contains("ensure: null"));
+    }
+
+    fn resolve(yaml: &str) -> Result<Value> {
+        let mut value: Value = serde_yaml::from_str(yaml).unwrap();
+        resolve_extends(&mut value)?;
+        Ok(value)
+    }
+
+    #[test]
+    fn test_extends_template() {
+        let value = resolve(
+            r#"
+templates:
+  service:
+    run: ./service --port ${task.port}
+    timestamps: true
+    env:
+      RUST_LOG: info
+    healthcheck:
+      tool: is-port-open ${task.port}
+      interval: 1
+tasks:
+  users:
+    extends: service
+    port: 8001
+    cwd: services/users
+  orders:
+    extends: service
+    port: 8002
+    env:
+      RUST_LOG: debug
+    healthcheck:
+      interval: 5
+"#,
+        )
+        .unwrap();
+
+        assert!(value.get("templates").is_none());
+        let users = &value["tasks"]["users"];
+        assert_eq!(users["run"], Value::from("./service --port ${task.port}"));
+        assert_eq!(users["timestamps"], Value::from(true));
+        assert_eq!(users["cwd"], Value::from("services/users"));
+        assert_eq!(users["port"], Value::from(8001));
+
+        let orders = &value["tasks"]["orders"];
+        assert_eq!(orders["env"]["RUST_LOG"], Value::from("debug"));
+        assert_eq!(
+            orders["healthcheck"]["tool"],
+            Value::from("is-port-open ${task.port}")
+        );
+        assert_eq!(orders["healthcheck"]["interval"], Value::from(5));
+    }
+
+    #[test]
+    fn test_extends_task_chain_and_action_override() {
+        let value = resolve(
+            r#"
+templates:
+  base:
+    cwd: backend
+tasks:
+  setup:
+    extends: base
+    ensure: ./setup.sh
+  server:
+    extends: setup
+    run: ./server
+    autorestart: true
+"#,
+        )
+        .unwrap();
+
+        let server = &value["tasks"]["server"];
+        assert_eq!(server["cwd"], Value::from("backend"));
+        assert_eq!(server["run"], Value::from("./server"));
+        assert!(server.get("ensure").is_none());
+    }
+
+    #[test]
+    fn test_extends_unknown_target() {
+        let err = resolve(
+            r#"
+tasks:
+  api:
+    extends: nothing
+"#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("'api' extends 'nothing'"));
+    }
+
+    #[test]
+    fn test_extends_circular() {
+        let err = resolve(
+            r#"
+templates:
+  a:
+    extends: b
+  b:
+    extends: a
+tasks:
+  api:
+    extends: a
+"#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("Circular extends"));
+    }
+
+    #[test]
+    fn test_extends_name_collision() {
+        let err = resolve(
+            r#"
+templates:
+  api:
+    cwd: backend
+tasks:
+  api:
+    run: ./api
+"#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("both as a task and as a template"));
+    }
+
+    #[test]
+    fn test_load_extends_with_interpolation() {
+        let loaded = load_config(&[data("templates.yaml")]).unwrap();
+        let users = &loaded.config.tasks["users"];
+        assert_eq!(users.extends.as_deref(), Some("service"));
+        assert_eq!(
+            users.action,
+            Some(TaskAction::Run {
+                command: CommandValue::String(Cow::Borrowed("./service --port 8001")),
+            })
+        );
+        assert_eq!(users.cwd.as_deref(), Some("services/users"));
+        assert!(users.timestamps);
+    }
+
+    #[test]
+    fn test_discover_in_start_directory() {
+        let tree = TempTree::new("start", &["rote.yml", ".rote.yaml"]);
+        let paths = discover_config(&tree.0, None).unwrap();
+        assert_eq!(paths, vec![PathBuf::from("rote.yml")]);
+    }
+
+    #[test]
+    fn test_discover_in_parent_directory() {
+        let tree = TempTree::new("parent", &["rote.yaml", "services/api/src/"]);
+        let start = tree.0.join("services/api/src");
+        let paths = discover_config(&start, None).unwrap();
+        assert_eq!(paths, vec![PathBuf::from("../../../rote.yaml")]);
+        assert!(start.join(&paths[0]).is_file());
+    }
+
+    #[test]
+    fn test_discover_stops_at_repository_root() {
+        let tree = TempTree::new("vcs", &["rote.yaml", "repo/.git/", "repo/sub/"]);
+        let err = discover_config(&tree.0.join("repo/sub"), None).unwrap_err();
+        assert!(err.to_string().contains("no config file found"));
+
+        // A config at the repository root itself is still found
+        fs::write(tree.0.join("repo/.rote.yaml"), "tasks: {}\n").unwrap();
+        let paths = discover_config(&tree.0.join("repo/sub"), None).unwrap();
+        assert_eq!(paths, vec![PathBuf::from("../.rote.yaml")]);
+    }
+
+    #[test]
+    fn test_discover_rote_config_env() {
+        let tree = TempTree::new("env", &["rote.yaml"]);
+        let value = std::env::join_paths(["base.yaml", "override.yaml"]).unwrap();
+        let paths = discover_config(&tree.0, Some(&value)).unwrap();
+        assert_eq!(
+            paths,
+            vec![PathBuf::from("base.yaml"), PathBuf::from("override.yaml")]
+        );
+
+        // An empty value is ignored
+        let paths = discover_config(&tree.0, Some(OsStr::new(""))).unwrap();
+        assert_eq!(paths, vec![PathBuf::from("rote.yaml")]);
+    }
+}
@@ -39,6 +39,9 @@ impl From<PanelIndex> for usize {
 pub enum StreamKind {
     Stdout,
     Stderr,
+    /// The output of a task running under a pseudo-terminal, where stdout
+    /// and stderr are merged and can't be told apart.
+    Terminal,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -176,6 +179,14 @@ pub struct Panel {
     pub task_name: String,
     pub cmd: Vec<String>,
     pub cwd: Option<String>,
+    /// Extra environment variables passed to the process and its healthcheck.
+    pub env: Vec<(String, String)>,
+    /// How the process is stopped.
+    pub stop_policy: crate::process::StopPolicy,
+    /// What the process reads as its standard input.
+    pub stdin: crate::config::StdinMode,
+    /// Whether the process runs under a pseudo-terminal.
+    pub tty: bool,
     pub messages: MessageBuf,
     pub scroll: usize,
     pub follow: bool,
@@ -185,6 +196,9 @@ pub struct Panel {
     pub show_healthcheck: bool,
     pub timestamps: bool,
     pub process_status: Option<crate::ui::ProcessStatus>,
+    /// Set when a config reload removed the task. The panel keeps its index,
+    /// so that events still queued for it can't reach another panel.
+    pub retired: bool,
 }
 
 impl Panel {
@@ -201,6 +215,10 @@ impl Panel {
             task_name,
             cmd,
             cwd,
+            env: Vec::new(),
+            stop_policy: Default::default(),
+            stdin: Default::default(),
+            tty: false,
             messages: MessageBuf::new(),
             scroll: 0,
             follow: true,
@@ -210,6 +228,7 @@ impl Panel {
             show_healthcheck: true,
             timestamps,
             process_status: None,
+            retired: false,
         }
     }
 
@@ -218,6 +237,40 @@ impl Panel {
         self
     }
 
+    pub fn with_env(mut self, env: Vec<(String, String)>) -> Self {
+        self.env = env;
+        self
+    }
+
+    pub fn with_stop_policy(mut self, stop_policy: crate::process::StopPolicy) -> Self {
+        self.stop_policy = stop_policy;
+        self
+    }
+
+    pub fn with_stdin(mut self, stdin: crate::config::StdinMode) -> Self {
+        self.stdin = stdin;
+        self
+    }
+
+    pub fn with_tty(mut self, tty: bool) -> Self {
+        self.tty = tty;
+        self
+    }
+
+    /// How the process is connected to rote, with `terminal_size` as the
+    /// size of its pseudo-terminal if it has one.
+    pub fn process_io(&self, terminal_size: crate::pty::TerminalSize) -> crate::process::ProcessIo {
+        crate::process::ProcessIo {
+            stdin: self.stdin,
+            terminal: self.tty.then_some(terminal_size),
+        }
+    }
+
+    /// Whether what is typed in input mode can be sent to the process.
+    pub fn accepts_input(&self) -> bool {
+        self.tty || self.stdin == crate::config::StdinMode::Pipe
+    }
+
     pub fn visible_len(&self) -> usize {
         self.messages
             .lines_filtered(
@@ -249,6 +302,18 @@ pub struct StatusPanel {
     pub entries: Vec<StatusEntry>,
     pub scroll: usize,
     pub entry_indices: HashMap<String, usize>,
+    /// Why the last config reload failed, if it did.
+    pub config_error: Option<String>,
+}
+
+/// How far a task's healthcheck has got.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HealthcheckState {
+    /// Hasn't passed yet.
+    Pending,
+    Passed,
+    /// Failed `retries` times in a row.
+    Failed,
 }
 
 #[derive(Clone)]
@@ -258,8 +323,17 @@ pub struct StatusEntry {
     pub exit_code: Option<i32>,
     pub action_type: Option<crate::config::TaskAction>,
     pub dependencies: Vec<String>,
-    /// None = no healthcheck configured, Some(false) = pending, Some(true) = passed
-    pub healthcheck_passed: Option<bool>,
+    /// None = no healthcheck configured
+    pub healthcheck: Option<HealthcheckState>,
+    /// A dependency that failed or became unhealthy, which keeps this task
+    /// from starting.
+    pub blocked_by: Option<String>,
+    /// Automatic restarts since the task last ran successfully.
+    pub restarts: u32,
+    /// Whether automatic restarts stopped after hitting `max_restarts`.
+    pub crash_looping: bool,
+    /// Whether an `ensure` task was skipped because it was up to date.
+    pub skipped: bool,
 }
 
 impl StatusPanel {
@@ -279,7 +353,11 @@ impl StatusPanel {
                     exit_code: None,
                     action_type: None,
                     dependencies: Vec::new(),
-                    healthcheck_passed: None,
+                    healthcheck: None,
+                    blocked_by: None,
+                    restarts: 0,
+                    crash_looping: false,
+                    skipped: false,
                 });
                 self.entries.last_mut().unwrap()
             }
@@ -313,16 +391,75 @@ impl StatusPanel {
         }
     }
 
+    pub fn set_skipped(&mut self, task_name: &str, skipped: bool) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.task_name == task_name) {
+            entry.skipped = skipped;
+        }
+    }
+
+    pub fn remove_entry(&mut self, task_name: &str) {
+        self.entries.retain(|e| e.task_name != task_name);
+        self.entry_indices.remove(task_name);
+    }
+
+    /// Mark a task as having no healthcheck.
+    pub fn clear_healthcheck(&mut self, task_name: &str) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.task_name == task_name) {
+            entry.healthcheck = None;
+        }
+    }
+
     pub fn set_has_healthcheck(&mut self, task_name: &str) {
+        self.update_healthcheck(task_name, HealthcheckState::Pending);
+    }
+
+    pub fn update_healthcheck(&mut self, task_name: &str, state: HealthcheckState) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.task_name == task_name) {
+            entry.healthcheck = Some(state);
+        }
+    }
+
+    /// Mark a task as unable to start because `blocked_by` failed or is
+    /// unhealthy, or clear that with `None`. Only tasks that haven't started
+    /// change status.
+    pub fn set_blocked(&mut self, task_name: &str, blocked_by: Option<String>) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.task_name == task_name) {
+            if matches!(
+                entry.status,
+                crate::ui::ProcessStatus::Pending | crate::ui::ProcessStatus::Blocked
+            ) {
+                entry.status = if blocked_by.is_some() {
+                    crate::ui::ProcessStatus::Blocked
+                } else {
+                    crate::ui::ProcessStatus::Pending
+                };
+            }
+            entry.blocked_by = blocked_by;
+        }
+    }
+
+    /// Unblock every task that was blocked by `task_name`.
+    pub fn clear_blocked_by(&mut self, task_name: &str) {
+        for entry in &mut self.entries {
+            if entry.blocked_by.as_deref() == Some(task_name) {
+                entry.blocked_by = None;
+                if entry.status == crate::ui::ProcessStatus::Blocked {
+                    entry.status = crate::ui::ProcessStatus::Pending;
+                }
+            }
+        }
+    }
+
+    pub fn update_restarts(&mut self, task_name: &str, restarts: u32) {
         if let Some(entry) = self.entries.iter_mut().find(|e| e.task_name == task_name) {
-            // Initialize to Some(false) meaning healthcheck configured but not yet passed
-            entry.healthcheck_passed = Some(false);
+            entry.restarts = restarts;
+            entry.crash_looping = false;
         }
     }
 
-    pub fn update_healthcheck_passed(&mut self, task_name: &str) {
+    pub fn set_crash_looping(&mut self, task_name: &str) {
         if let Some(entry) = self.entries.iter_mut().find(|e| e.task_name == task_name) {
-            entry.healthcheck_passed = Some(true);
+            entry.crash_looping = true;
         }
     }
 
@@ -348,15 +485,16 @@ impl StatusPanel {
                     (
                         Some(crate::config::TaskAction::Ensure { .. }),
                         crate::ui::ProcessStatus::Exited,
-                    ) => entry.exit_code == Some(0),
+                    ) => entry.exit_code == Some(0) || entry.skipped,
                     (
                         Some(crate::config::TaskAction::Run { .. }),
                         crate::ui::ProcessStatus::Running,
                     ) => {
                         // If healthcheck is configured but not yet passed, not healthy
                         // If no healthcheck or healthcheck passed, healthy
-                        entry.healthcheck_passed != Some(false)
+                        matches!(entry.healthcheck, None | Some(HealthcheckState::Passed))
                     }
+                    // Pending and blocked tasks are wanted but not up yet
                     _ => false,
                 };
 
@@ -695,7 +833,11 @@ mod tests {
             exit_code: None,
             action_type: None,
             dependencies: Vec::new(),
-            healthcheck_passed: None,
+            healthcheck: None,
+            blocked_by: None,
+            restarts: 0,
+            crash_looping: false,
+            skipped: false,
         };
         let cloned = entry.clone();
         assert_eq!(entry.task_name, cloned.task_name);
@@ -714,6 +856,87 @@ mod tests {
         assert!(panel.entries.iter().any(|e| e.task_name == "task2"));
     }
 
+    #[test]
+    fn test_status_panel_restarts() {
+        let mut panel = StatusPanel::new();
+        panel.update_entry("task1".to_string(), crate::ui::ProcessStatus::Exited);
+        panel.update_restarts("task1", 3);
+        panel.set_crash_looping("task1");
+
+        let entry = panel.get_entry("task1").unwrap();
+        assert_eq!(entry.restarts, 3);
+        assert!(entry.crash_looping);
+
+        // A manual restart starts counting from scratch
+        panel.update_restarts("task1", 0);
+        let entry = panel.get_entry("task1").unwrap();
+        assert_eq!(entry.restarts, 0);
+        assert!(!entry.crash_looping);
+    }
+
+    #[test]
+    fn test_status_panel_healthcheck_and_blocked() {
+        let mut panel = StatusPanel::new();
+        panel.update_entry_with_action(
+            "db".to_string(),
+            crate::ui::ProcessStatus::Running,
+            TaskAction::Run {
+                command: crate::config::CommandValue::Bool(true),
+            },
+        );
+        panel.update_entry("api".to_string(), crate::ui::ProcessStatus::NotStarted);
+        panel.set_has_healthcheck("db");
+        assert_eq!(
+            panel.get_entry("db").unwrap().healthcheck,
+            Some(HealthcheckState::Pending)
+        );
+        assert_eq!(panel.get_health_status(), (0, 1, true));
+
+        panel.update_healthcheck("db", HealthcheckState::Failed);
+        panel.set_blocked("api", Some("db".to_string()));
+        assert_eq!(
+            panel.get_entry("api").unwrap().blocked_by.as_deref(),
+            Some("db")
+        );
+        assert_eq!(panel.get_health_status(), (0, 1, true));
+
+        panel.update_healthcheck("db", HealthcheckState::Passed);
+        panel.clear_blocked_by("db");
+        assert!(panel.get_entry("api").unwrap().blocked_by.is_none());
+        assert_eq!(panel.get_health_status(), (1, 1, false));
+    }
+
+    #[test]
+    fn test_status_panel_skipped_counts_as_healthy() {
+        let mut panel = StatusPanel::new();
+        panel.update_entry_with_action(
+            "install".to_string(),
+            crate::ui::ProcessStatus::Exited,
+            crate::config::TaskAction::Ensure {
+                command: crate::config::CommandValue::String("npm install".into()),
+            },
+        );
+        assert_eq!(panel.get_health_status(), (0, 1, true));
+
+        panel.set_skipped("install", true);
+        assert!(panel.get_entry("install").unwrap().skipped);
+        assert_eq!(panel.get_health_status(), (1, 1, false));
+    }
+
+    #[test]
+    fn test_status_panel_remove_entry_and_clear_healthcheck() {
+        let mut panel = StatusPanel::new();
+        panel.update_entry("db".to_string(), crate::ui::ProcessStatus::Running);
+        panel.update_entry("api".to_string(), crate::ui::ProcessStatus::Running);
+        panel.set_has_healthcheck("db");
+        panel.clear_healthcheck("db");
+        assert_eq!(panel.get_entry("db").unwrap().healthcheck, None);
+
+        panel.remove_entry("db");
+        assert!(panel.get_entry("db").is_none());
+        assert_eq!(panel.entries.len(), 1);
+    }
+
     #[test]
     fn test_status_panel_update_dependencies() {
         let mut panel = StatusPanel::new();
@@ -968,6 +1191,40 @@ mod tests {
         assert!(!has_issues);
     }
 
+    #[test]
+    fn test_get_health_status_counts_pending_and_blocked() {
+        let mut panel = StatusPanel::new();
+        for name in ["migrate", "api", "web"] {
+            panel.update_entry_with_action(
+                name.to_string(),
+                crate::ui::ProcessStatus::Pending,
+                TaskAction::Run {
+                    command: crate::config::CommandValue::Bool(true),
+                },
+            );
+        }
+        panel.update_entry("migrate".to_string(), crate::ui::ProcessStatus::Running);
+        assert_eq!(panel.get_health_status(), (1, 3, true));
+
+        // Only tasks that haven't started can be blocked
+        panel.set_blocked("api", Some("migrate".to_string()));
+        panel.set_blocked("migrate", Some("db".to_string()));
+        assert_eq!(
+            panel.get_entry("api").unwrap().status,
+            crate::ui::ProcessStatus::Blocked
+        );
+        assert_eq!(
+            panel.get_entry("migrate").unwrap().status,
+            crate::ui::ProcessStatus::Running
+        );
+        assert_eq!(panel.get_health_status(), (1, 3, true));
+
+        panel.clear_blocked_by("migrate");
+        let api = panel.get_entry("api").unwrap();
+        assert_eq!(api.status, crate::ui::ProcessStatus::Pending);
+        assert!(api.blocked_by.is_none());
+    }
+
     #[test]
     fn test_panel_index_new() {
         let idx = PanelIndex::new(5);
@@ -1,23 +1,218 @@
 use nix::sys::signal::{Signal, kill};
 use nix::unistd::Pid;
+use std::collections::BTreeSet;
+use std::os::fd::OwnedFd;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::{
-    io::{AsyncBufReadExt, BufReader},
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
     process::Command,
     sync::{Notify, mpsc},
     task::JoinHandle,
 };
 
+use crate::config::{DEFAULT_STOP_SIGNAL, DEFAULT_STOP_TIMEOUT, StdinMode};
 use crate::panel::{PanelIndex, StreamKind};
-use crate::signals::is_process_exited;
+use crate::pty::{self, PtyMaster, TerminalSize};
+use crate::signals::{is_process_group_exited, stop_process_groups};
 use crate::ui::UiEvent;
 
+/// Interval at which a stopping process is polled for exit.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long to wait after the intermediate `SIGTERM` before `SIGKILL`.
+const SIGTERM_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// How often the process group of a task that exited on its own is checked
+/// while background processes it started keep the group alive.
+const GROUP_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The generation given to the next spawned process.
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// The process groups of tasks that have been started and may still have
+/// processes running, for [`stop_all`].
+static PROCESS_GROUPS: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+
+/// Stop every task process group that may still be running, waiting up to
+/// `timeout` after `SIGTERM` before killing them. This is for when rote
+/// can't shut down normally, like after a panic.
+pub fn stop_all(timeout: Duration) {
+    // The panicking thread may hold the lock
+    let pids: Vec<Pid> = match PROCESS_GROUPS.try_lock() {
+        Ok(groups) => groups
+            .iter()
+            .map(|pid| Pid::from_raw(*pid as i32))
+            .collect(),
+        Err(_) => return,
+    };
+    stop_process_groups(&pids, timeout);
+}
+
+/// Forget the process group led by `pid` once every process in it has
+/// exited, since the system may then give the id to an unrelated group that
+/// [`stop_all`] must leave alone.
+fn forget_process_group(pid: u32) {
+    let pgid = Pid::from_raw(pid as i32);
+    if is_process_group_exited(pgid) {
+        PROCESS_GROUPS.lock().unwrap().remove(&pid);
+        return;
+    }
+    tokio::spawn(async move {
+        while !is_process_group_exited(pgid) {
+            tokio::time::sleep(GROUP_POLL_INTERVAL).await;
+        }
+        PROCESS_GROUPS.lock().unwrap().remove(&pid);
+    });
+}
+
+/// How a process is stopped: `signal` is sent first, and if the process is
+/// still running after `timeout` it is sent `SIGTERM` (unless `signal` already
+/// was `SIGTERM`) and finally `SIGKILL`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StopPolicy {
+    pub signal: Signal,
+    pub timeout: Duration,
+}
+
+impl StopPolicy {
+    /// The signals to send in order, each with how long to wait for the
+    /// process to exit before moving on. Ends with `SIGKILL`, after which
+    /// there is nothing left to wait for.
+    fn stages(&self) -> Vec<(Signal, Duration)> {
+        let mut stages = vec![(self.signal, self.timeout)];
+        if !matches!(self.signal, Signal::SIGTERM | Signal::SIGKILL) {
+            stages.push((Signal::SIGTERM, SIGTERM_TIMEOUT));
+        }
+        if self.signal != Signal::SIGKILL {
+            stages.push((Signal::SIGKILL, Duration::ZERO));
+        }
+        stages
+    }
+}
+
+impl Default for StopPolicy {
+    fn default() -> Self {
+        Self {
+            signal: DEFAULT_STOP_SIGNAL,
+            timeout: DEFAULT_STOP_TIMEOUT,
+        }
+    }
+}
+
+/// Progress of stopping a process, as reported by
+/// [`TaskInstance::terminate_reporting`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopStage {
+    /// `signal` was sent, and the process gets up to `timeout` to exit.
+    Signalled { signal: Signal, timeout: Duration },
+    /// `SIGKILL` was sent.
+    Killed,
+    /// The process has exited.
+    Exited,
+}
+
+impl std::fmt::Display for StopStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StopStage::Signalled { signal, timeout } => {
+                write!(
+                    f,
+                    "sent {}, waiting up to {:.1}s",
+                    signal.as_str(),
+                    timeout.as_secs_f64()
+                )
+            }
+            StopStage::Killed => write!(f, "sent SIGKILL"),
+            StopStage::Exited => write!(f, "exited"),
+        }
+    }
+}
+
+/// How a task's process is connected to rote.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProcessIo {
+    pub stdin: StdinMode,
+    /// Run the process under a pseudo-terminal of this size instead of with
+    /// pipes. Its stdout and stderr are then one stream, and its input always
+    /// comes from rote, whatever `stdin` says.
+    pub terminal: Option<TerminalSize>,
+}
+
+/// Stops a [`TaskInstance`]'s process, so that can happen in the background
+/// while the instance stays where it is.
+#[derive(Clone)]
+pub struct StopHandle {
+    pid: Option<u32>,
+    stop_policy: StopPolicy,
+    exit_status: Arc<Mutex<Option<std::io::Result<std::process::ExitStatus>>>>,
+}
+
+impl StopHandle {
+    pub async fn terminate(&self) {
+        self.terminate_reporting(|_| {}).await;
+    }
+
+    /// Stop the process according to its [`StopPolicy`], calling `report`
+    /// as each stage is reached.
+    pub async fn terminate_reporting(&self, mut report: impl FnMut(StopStage)) {
+        let Some(pid) = self.pid else {
+            return;
+        };
+        let raw_pid = pid;
+        let pid = Pid::from_raw(pid as i32);
+        // A negative pid signals every process in the group, and the group is
+        // only gone once none of them are left. The system doesn't reuse the
+        // pid while the group exists, and the process itself is known to
+        // have exited once it has been reaped.
+        let target = Pid::from_raw(-pid.as_raw());
+        let exited = || self.exit_status.lock().unwrap().is_some() && is_process_group_exited(pid);
+
+        for (signal, timeout) in self.stop_policy.stages() {
+            if exited() {
+                break;
+            }
+            let _ = kill(target, signal);
+            if signal == Signal::SIGKILL {
+                PROCESS_GROUPS.lock().unwrap().remove(&raw_pid);
+                report(StopStage::Killed);
+                return;
+            }
+            report(StopStage::Signalled { signal, timeout });
+
+            let deadline = tokio::time::Instant::now() + timeout;
+            while !exited() && tokio::time::Instant::now() < deadline {
+                tokio::time::sleep(STOP_POLL_INTERVAL).await;
+            }
+        }
+        PROCESS_GROUPS.lock().unwrap().remove(&raw_pid);
+        report(StopStage::Exited);
+    }
+}
+
 pub struct TaskInstance {
+    /// The process, which leads its own process group. Stopping the task
+    /// signals the whole group.
     pub pid: Option<u32>,
+    /// Identifies this process among every one rote has spawned, unlike the
+    /// pid, which the system may reuse. Events about the process carry it, so
+    /// late events from a process that has since been replaced can be told
+    /// apart.
+    pub generation: u64,
     pub stdout_task: JoinHandle<()>,
-    pub stderr_task: JoinHandle<()>,
+    /// Reads stderr, which a process under a pseudo-terminal doesn't have
+    /// apart from its terminal output.
+    pub stderr_task: Option<JoinHandle<()>>,
     pub wait_task: JoinHandle<()>,
+    /// When the process was spawned.
+    pub started_at: std::time::Instant,
+    stop_policy: StopPolicy,
+    /// Writes to the process's stdin, if it was started with `StdinMode::Pipe`
+    /// or under a pseudo-terminal, and it hasn't been closed.
+    stdin: Option<mpsc::UnboundedSender<Vec<u8>>>,
+    /// The controlling side of the process's pseudo-terminal, if it has one.
+    terminal: Option<OwnedFd>,
     exit_status: Arc<Mutex<Option<std::io::Result<std::process::ExitStatus>>>>,
     exit_done: Arc<tokio::sync::Notify>,
 }
@@ -27,10 +222,31 @@ impl TaskInstance {
         panel: PanelIndex,
         cmd: &[String],
         cwd: Option<&str>,
+        env: &[(String, String)],
         tx: mpsc::Sender<UiEvent>,
         shutdown_rx: tokio::sync::broadcast::Receiver<()>,
     ) -> std::io::Result<Self> {
-        spawn_process(panel, cmd, cwd, tx, shutdown_rx)
+        spawn_process(panel, cmd, cwd, env, ProcessIo::default(), tx, shutdown_rx)
+    }
+
+    /// Like [`spawn`](Self::spawn), with the process's input and output set
+    /// up as `io` says.
+    pub fn spawn_with_io(
+        panel: PanelIndex,
+        cmd: &[String],
+        cwd: Option<&str>,
+        env: &[(String, String)],
+        io: ProcessIo,
+        tx: mpsc::Sender<UiEvent>,
+        shutdown_rx: tokio::sync::broadcast::Receiver<()>,
+    ) -> std::io::Result<Self> {
+        spawn_process(panel, cmd, cwd, env, io, tx, shutdown_rx)
+    }
+
+    /// Use `stop_policy` instead of the default when terminating the process.
+    pub fn with_stop_policy(mut self, stop_policy: StopPolicy) -> Self {
+        self.stop_policy = stop_policy;
+        self
     }
 
     pub async fn wait(&mut self) -> std::io::Result<std::process::ExitStatus> {
@@ -53,35 +269,74 @@ impl TaskInstance {
         }
     }
 
+    /// Whether the process itself has exited and been reaped, even if it
+    /// left background processes running in its group.
+    pub fn has_exited(&self) -> bool {
+        self.exit_status.lock().unwrap().is_some()
+    }
+
+    /// Whether the process's stdin is a pipe or terminal that can still be
+    /// written to.
+    pub fn accepts_input(&self) -> bool {
+        self.stdin.as_ref().is_some_and(|stdin| !stdin.is_closed())
+    }
+
+    /// Write `bytes` to the process's stdin. Returns false if rote has no way
+    /// to write to it or it has been closed, for example because the process
+    /// exited.
+    pub fn send_input(&self, bytes: Vec<u8>) -> bool {
+        self.stdin
+            .as_ref()
+            .is_some_and(|stdin| stdin.send(bytes).is_ok())
+    }
+
+    /// Close the process's stdin once everything sent so far is written, so
+    /// it reads end of file. A terminal stays open, and is sent the end of
+    /// file character instead.
+    pub fn close_input(&mut self) {
+        if self.terminal.is_some() {
+            self.send_input(vec![0x04]);
+        } else {
+            self.stdin = None;
+        }
+    }
+
+    /// Change the size of the process's pseudo-terminal, if it has one.
+    pub fn resize(&self, size: TerminalSize) -> std::io::Result<()> {
+        match &self.terminal {
+            Some(master) => pty::resize(master, size),
+            None => Ok(()),
+        }
+    }
+
     /// Get the exit status Arc for use after partial moves
     pub fn exit_status_arc(&self) -> Arc<Mutex<Option<std::io::Result<std::process::ExitStatus>>>> {
         self.exit_status.clone()
     }
 
     pub async fn terminate(&self) {
-        let Some(pid) = self.pid else {
-            return;
-        };
-        let pid = Pid::from_raw(pid as i32);
+        self.terminate_reporting(|_| {}).await;
+    }
 
-        let _ = kill(pid, Signal::SIGINT);
-        tokio::time::sleep(Duration::from_millis(300)).await;
-        if is_process_exited(pid) {
-            return;
-        }
+    /// Stop the process according to its [`StopPolicy`], calling `report`
+    /// as each stage is reached.
+    pub async fn terminate_reporting(&self, report: impl FnMut(StopStage)) {
+        self.stop_handle().terminate_reporting(report).await;
+    }
 
-        let _ = kill(pid, Signal::SIGTERM);
-        tokio::time::sleep(Duration::from_millis(300)).await;
-        if is_process_exited(pid) {
-            return;
+    /// A handle that stops the process without borrowing this instance.
+    pub fn stop_handle(&self) -> StopHandle {
+        StopHandle {
+            pid: self.pid,
+            stop_policy: self.stop_policy,
+            exit_status: self.exit_status.clone(),
         }
-
-        let _ = kill(pid, Signal::SIGKILL);
     }
 
     fn send_exit_event(
         tx: &mpsc::Sender<UiEvent>,
         panel: PanelIndex,
+        generation: u64,
         result: &std::io::Result<std::process::ExitStatus>,
     ) {
         use std::os::unix::process::ExitStatusExt;
@@ -103,12 +358,14 @@ impl TaskInstance {
         if is_ok {
             let _ = tx.try_send(UiEvent::Exited {
                 panel,
+                generation,
                 status,
                 exit_code,
             });
         } else {
             let _ = tx.try_send(UiEvent::Exited {
                 panel,
+                generation,
                 status: None,
                 exit_code: None,
             });
@@ -120,77 +377,80 @@ impl TaskInstance {
 fn spawn_stream_reader(
     panel: PanelIndex,
     stream: StreamKind,
-    lines: tokio::io::Lines<BufReader<tokio::process::ChildStdout>>,
+    lines: tokio::io::Lines<BufReader<impl AsyncRead + Unpin + Send + 'static>>,
     tx: mpsc::Sender<UiEvent>,
     mut shutdown_rx: tokio::sync::broadcast::Receiver<()>,
 ) -> JoinHandle<()> {
     tokio::spawn(async move {
         let mut lines = lines;
+        // After shutdown, keep reading (and discarding) output until the
+        // process closes it, so a task that logs while stopping doesn't die
+        // from SIGPIPE
+        let mut forward = true;
         loop {
             tokio::select! {
                 result = lines.next_line() => {
                     match result {
-                        Ok(Some(line)) => {
-                            let _ = tx.send(UiEvent::Line { panel, stream, text: line }).await;
+                        Ok(Some(line)) if forward => {
+                            let text = match stream {
+                                StreamKind::Terminal => pty::terminal_line(&line),
+                                _ => line,
+                            };
+                            let _ = tx.send(UiEvent::Line { panel, stream, text }).await;
                         }
+                        Ok(Some(_)) => {}
                         _ => break,
                     }
                 }
-                _ = shutdown_rx.recv() => break,
+                _ = shutdown_rx.recv(), if forward => forward = false,
             }
         }
     })
 }
 
-/// Spawn a task that reads lines from stderr and sends them as events.
-fn spawn_stderr_reader(
-    panel: PanelIndex,
-    lines: tokio::io::Lines<BufReader<tokio::process::ChildStderr>>,
-    tx: mpsc::Sender<UiEvent>,
-    mut shutdown_rx: tokio::sync::broadcast::Receiver<()>,
-) -> JoinHandle<()> {
+/// Spawn a task that writes what is sent to it to the child's stdin, until
+/// writing fails or the sender is dropped.
+fn spawn_stdin_writer(
+    mut stdin: impl AsyncWrite + Unpin + Send + 'static,
+) -> mpsc::UnboundedSender<Vec<u8>> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<Vec<u8>>();
     tokio::spawn(async move {
-        let mut lines = lines;
-        loop {
-            tokio::select! {
-                result = lines.next_line() => {
-                    match result {
-                        Ok(Some(line)) => {
-                            let _ = tx.send(UiEvent::Line { panel, stream: StreamKind::Stderr, text: line }).await;
-                        }
-                        _ => break,
-                    }
-                }
-                _ = shutdown_rx.recv() => break,
+        while let Some(bytes) = rx.recv().await {
+            if stdin.write_all(&bytes).await.is_err() || stdin.flush().await.is_err() {
+                break;
             }
         }
-    })
+    });
+    tx
 }
 
 /// Spawn a task that waits for the child process to exit and sends an exit event.
+/// It keeps waiting through shutdown, so that the exit status is only set once
+/// the process has really exited.
 fn spawn_exit_waiter(
     panel: PanelIndex,
+    generation: u64,
     mut child: tokio::process::Child,
     tx: mpsc::Sender<UiEvent>,
     exit_status: Arc<Mutex<Option<std::io::Result<std::process::ExitStatus>>>>,
     exit_done: Arc<Notify>,
-    mut shutdown_rx: tokio::sync::broadcast::Receiver<()>,
 ) -> JoinHandle<()> {
     tokio::spawn(async move {
-        let result = tokio::select! {
-            _ = shutdown_rx.recv() => {
-                Err(std::io::Error::new(
-                    std::io::ErrorKind::Interrupted,
-                    "Process was terminated",
-                ))
-            }
-            result = child.wait() => result,
-        };
+        let pid = child.id();
+        let result = child.wait().await;
+        if let Some(pid) = pid {
+            forget_process_group(pid);
+        }
 
         *exit_status.lock().unwrap() = Some(result);
         exit_done.notify_one();
 
-        TaskInstance::send_exit_event(&tx, panel, exit_status.lock().unwrap().as_ref().unwrap());
+        TaskInstance::send_exit_event(
+            &tx,
+            panel,
+            generation,
+            exit_status.lock().unwrap().as_ref().unwrap(),
+        );
     })
 }
 
@@ -198,36 +458,122 @@ fn spawn_process(
     panel: PanelIndex,
     cmd: &[String],
     cwd: Option<&str>,
+    env: &[(String, String)],
+    io: ProcessIo,
     tx: mpsc::Sender<UiEvent>,
     shutdown_rx: tokio::sync::broadcast::Receiver<()>,
 ) -> std::io::Result<TaskInstance> {
-    // Configure command
+    // Configure command. Tasks never read rote's terminal, which belongs to
+    // its keyboard handling.
     let mut command = Command::new(&cmd[0]);
     command
         .args(&cmd[1..])
-        .stdout(std::process::Stdio::piped())
-        .stderr(std::process::Stdio::piped());
+        .envs(env.iter().map(|(k, v)| (k, v)));
     if let Some(cwd) = cwd {
         command.current_dir(cwd);
     }
 
+    let terminal = io.terminal.map(pty::open).transpose()?;
+    match &terminal {
+        Some(terminal) => {
+            command
+                .stdin(terminal.slave.try_clone()?)
+                .stdout(terminal.slave.try_clone()?)
+                .stderr(terminal.slave.try_clone()?);
+            // A new session has its own process group, like other tasks, and
+            // can take the terminal as its controlling terminal
+            // SAFETY: only async-signal-safe system calls run in the child
+            unsafe {
+                command.pre_exec(|| {
+                    nix::unistd::setsid()?;
+                    pty::set_controlling_terminal()
+                });
+            }
+        }
+        None => {
+            command
+                .stdin(match io.stdin {
+                    StdinMode::Null => std::process::Stdio::null(),
+                    StdinMode::Pipe => std::process::Stdio::piped(),
+                })
+                .stdout(std::process::Stdio::piped())
+                .stderr(std::process::Stdio::piped());
+            // Each task gets its own process group, so that stopping it also
+            // stops anything it started, like the server behind `npm run dev`
+            command.process_group(0);
+        }
+    }
+
+    // Have Linux stop the task if rote dies without stopping it, for
+    // example because it was killed with SIGKILL
+    #[cfg(target_os = "linux")]
+    {
+        let parent = nix::unistd::getpid();
+        // SAFETY: only async-signal-safe system calls run in the child
+        unsafe {
+            command.pre_exec(move || {
+                nix::sys::prctl::set_pdeathsig(Signal::SIGTERM)?;
+                // rote may already have died before the signal was set up
+                if nix::unistd::getppid() != parent {
+                    return Err(std::io::Error::other("rote exited"));
+                }
+                Ok(())
+            });
+        }
+    }
+
     // Spawn process
     let mut child = command.spawn()?;
     let pid = child.id();
+    let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+    if let Some(pid) = pid {
+        PROCESS_GROUPS.lock().unwrap().insert(pid);
+    }
 
-    // Take stdout/stderr handles
-    let stdout = BufReader::new(child.stdout.take().expect("stdout should be piped")).lines();
-    let stderr = BufReader::new(child.stderr.take().expect("stderr should be piped")).lines();
+    // Only the process may keep the terminal open, so that reading it ends
+    // once the process and everything it started have exited
+    drop(command);
+    let (stdin, stdout_task, stderr_task, terminal) = match terminal {
+        Some(terminal) => {
+            drop(terminal.slave);
+            let resizer = terminal.master.try_clone()?;
+            let (output, input) = tokio::io::split(PtyMaster::new(terminal.master)?);
+            let output_task = spawn_stream_reader(
+                panel,
+                StreamKind::Terminal,
+                BufReader::new(output).lines(),
+                tx.clone(),
+                shutdown_rx.resubscribe(),
+            );
+            let stdin = spawn_stdin_writer(input);
+            (Some(stdin), output_task, None, Some(resizer))
+        }
+        None => {
+            // Take the stdin/stdout/stderr handles
+            let stdin = child.stdin.take().map(spawn_stdin_writer);
+            let stdout =
+                BufReader::new(child.stdout.take().expect("stdout should be piped")).lines();
+            let stderr =
+                BufReader::new(child.stderr.take().expect("stderr should be piped")).lines();
 
-    // Spawn stream reader tasks
-    let stdout_task = spawn_stream_reader(
-        panel,
-        StreamKind::Stdout,
-        stdout,
-        tx.clone(),
-        shutdown_rx.resubscribe(),
-    );
-    let stderr_task = spawn_stderr_reader(panel, stderr, tx.clone(), shutdown_rx.resubscribe());
+            // Spawn stream reader tasks
+            let stdout_task = spawn_stream_reader(
+                panel,
+                StreamKind::Stdout,
+                stdout,
+                tx.clone(),
+                shutdown_rx.resubscribe(),
+            );
+            let stderr_task = spawn_stream_reader(
+                panel,
+                StreamKind::Stderr,
+                stderr,
+                tx.clone(),
+                shutdown_rx.resubscribe(),
+            );
+            (stdin, stdout_task, Some(stderr_task), None)
+        }
+    };
 
     // Spawn exit waiter task
     let exit_status: Arc<Mutex<Option<std::io::Result<std::process::ExitStatus>>>> =
@@ -235,18 +581,23 @@ fn spawn_process(
     let exit_done = Arc::new(Notify::new());
     let wait_task = spawn_exit_waiter(
         panel,
+        generation,
         child,
         tx,
         exit_status.clone(),
         exit_done.clone(),
-        shutdown_rx.resubscribe(),
     );
 
     Ok(TaskInstance {
         pid,
+        generation,
         stdout_task,
         stderr_task,
         wait_task,
+        started_at: std::time::Instant::now(),
+        stop_policy: StopPolicy::default(),
+        stdin,
+        terminal,
         exit_status,
         exit_done,
     })
@@ -0,0 +1,206 @@
+use std::io;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use nix::fcntl::{FcntlArg, OFlag, fcntl};
+use nix::libc;
+use nix::pty::{OpenptyResult, Winsize, openpty};
+use tokio::io::unix::AsyncFd;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// The size of a pseudo-terminal, in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerminalSize {
+    pub cols: u16,
+    pub rows: u16,
+}
+
+impl From<TerminalSize> for Winsize {
+    fn from(size: TerminalSize) -> Self {
+        Winsize {
+            ws_row: size.rows,
+            ws_col: size.cols,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        }
+    }
+}
+
+/// Open a pseudo-terminal of the given size.
+pub(crate) fn open(size: TerminalSize) -> io::Result<OpenptyResult> {
+    Ok(openpty(&Winsize::from(size), None)?)
+}
+
+/// Change the size of the pseudo-terminal `master` belongs to. The processes
+/// using it are sent `SIGWINCH`.
+pub(crate) fn resize(master: &OwnedFd, size: TerminalSize) -> io::Result<()> {
+    let winsize = Winsize::from(size);
+    // SAFETY: TIOCSWINSZ only reads the winsize it is given
+    if unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &winsize) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Make the terminal on stdin the controlling terminal of the calling
+/// process, which must have just become a session leader. Only for use
+/// between fork and exec.
+pub(crate) fn set_controlling_terminal() -> io::Result<()> {
+    // SAFETY: TIOCSCTTY takes no pointer
+    if unsafe { libc::ioctl(0, libc::TIOCSCTTY as _, 0) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Turn a line read from a pseudo-terminal into what a terminal would show:
+/// without the carriage return ending it, with only what was written after
+/// the last carriage return inside it, like progress bars that redraw their
+/// line, and without the escape sequences that move the cursor or set the
+/// window title, which would garble the panel. Color sequences are kept, so
+/// the line reaches the panel in the same form as piped output.
+pub(crate) fn terminal_line(line: &str) -> String {
+    let line = line.trim_end_matches('\r');
+    let line = line.rsplit('\r').next().unwrap_or(line);
+
+    let mut text = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.next() {
+                // CSI: parameters, then a final byte in @..=~, which is `m`
+                // for colors
+                Some('[') => {
+                    let mut sequence = String::from("\x1b[");
+                    for c in chars.by_ref() {
+                        sequence.push(c);
+                        if ('@'..='~').contains(&c) {
+                            if c == 'm' {
+                                text.push_str(&sequence);
+                            }
+                            break;
+                        }
+                    }
+                }
+                // OSC, like window titles: ended by BEL or ESC \
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            },
+            '\t' => text.push(c),
+            c if c.is_control() => {}
+            c => text.push(c),
+        }
+    }
+    text
+}
+
+/// The controlling side of a pseudo-terminal, which reads the output of the
+/// processes running in it and writes their input without blocking.
+pub(crate) struct PtyMaster(AsyncFd<OwnedFd>);
+
+impl PtyMaster {
+    pub(crate) fn new(master: OwnedFd) -> io::Result<Self> {
+        let flags = OFlag::from_bits_truncate(fcntl(master.as_raw_fd(), FcntlArg::F_GETFL)?);
+        fcntl(
+            master.as_raw_fd(),
+            FcntlArg::F_SETFL(flags | OFlag::O_NONBLOCK),
+        )?;
+        Ok(Self(AsyncFd::new(master)?))
+    }
+}
+
+impl AsyncRead for PtyMaster {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            let mut guard = ready!(self.0.poll_read_ready(cx))?;
+            let unfilled = buf.initialize_unfilled();
+            match guard.try_io(|fd| Ok(nix::unistd::read(fd.as_raw_fd(), unfilled)?)) {
+                Ok(Ok(n)) => {
+                    buf.advance(n);
+                    return Poll::Ready(Ok(()));
+                }
+                // Linux reports that every process has closed the terminal as
+                // an error rather than end of file
+                Ok(Err(e)) if e.raw_os_error() == Some(libc::EIO) => return Poll::Ready(Ok(())),
+                Ok(Err(e)) => return Poll::Ready(Err(e)),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+impl AsyncWrite for PtyMaster {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        loop {
+            let mut guard = ready!(self.0.poll_write_ready(cx))?;
+            match guard.try_io(|fd| Ok(nix::unistd::write(fd.get_ref(), buf)?)) {
+                Ok(result) => return Poll::Ready(result),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terminal_line() {
+        assert_eq!(terminal_line("plain"), "plain");
+        assert_eq!(terminal_line("ends with crlf\r"), "ends with crlf");
+        assert_eq!(terminal_line(" 10%\r 50%\r100%\r"), "100%");
+        assert_eq!(terminal_line(""), "");
+        assert_eq!(
+            terminal_line("\x1b[1;32m   Compiling\x1b[0m rote\r"),
+            "\x1b[1;32m   Compiling\x1b[0m rote"
+        );
+        assert_eq!(terminal_line("\x1b[2K\x1b[1Gdone\x1b[?25h"), "done");
+        assert_eq!(
+            terminal_line("\x1b]0;title\x07a\tb\x1b]0;x\x1b\\c\x08"),
+            "a\tbc"
+        );
+    }
+
+    #[test]
+    fn test_resize() {
+        let pty = open(TerminalSize { cols: 80, rows: 24 }).unwrap();
+        resize(
+            &pty.master,
+            TerminalSize {
+                cols: 120,
+                rows: 40,
+            },
+        )
+        .unwrap();
+
+        let mut winsize = Winsize::from(TerminalSize { cols: 0, rows: 0 });
+        // SAFETY: TIOCGWINSZ writes to the winsize it is given
+        let result = unsafe { libc::ioctl(pty.slave.as_raw_fd(), libc::TIOCGWINSZ, &mut winsize) };
+        assert_eq!(result, 0);
+        assert_eq!((winsize.ws_col, winsize.ws_row), (120, 40));
+    }
+}
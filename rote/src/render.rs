@@ -1,28 +1,70 @@
 use ratatui::{
     Terminal,
-    layout::{Alignment, Constraint, Direction, Layout},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     prelude::CrosstermBackend,
     style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{
         Block, Borders, Cell, Paragraph, Row, Scrollbar, ScrollbarOrientation, ScrollbarState,
-        Table,
+        Table, Wrap,
     },
 };
-use std::io;
+use std::{collections::HashMap, io};
 
 use crate::{
     config::TaskAction,
-    panel::{Panel, StatusEntry, StatusPanel, WRAP_INDICATOR, wrap_line},
+    input::{Input, InputMode},
+    panel::{HealthcheckState, Panel, StatusEntry, StatusPanel, WRAP_INDICATOR, wrap_line},
+    process::StopStage,
+    pty::TerminalSize,
     ui::ProcessStatus,
 };
 
+/// The width of the sidebar with the task status and keys.
+const SIDEBAR_WIDTH: u16 = 22;
+
+/// Split the area [`draw`] fills into the panel's output, the input bar
+/// below it while in input mode, and the sidebar.
+fn panel_layout(area: Rect, with_input: bool) -> (Rect, Option<Rect>, Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .margin(0)
+        .constraints([Constraint::Min(0), Constraint::Length(SIDEBAR_WIDTH)].as_ref())
+        .split(area);
+    if !with_input {
+        return (chunks[0], None, chunks[1]);
+    }
+    let content_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(3)].as_ref())
+        .split(chunks[0]);
+    (content_chunks[0], Some(content_chunks[1]), chunks[1])
+}
+
+/// The size of the area inside a panel's border that [`draw`] shows its
+/// output in, when the whole terminal is `area`. Tasks running under a
+/// pseudo-terminal get this as their terminal size.
+pub fn output_size(area: Rect, with_input: bool) -> TerminalSize {
+    let (content_area, _, _) = panel_layout(area, with_input);
+    TerminalSize {
+        cols: content_area.width.saturating_sub(2),
+        rows: content_area.height.saturating_sub(2),
+    }
+}
+
 /// Get the health status (icon, text, color) for a task based on its StatusEntry.
 fn get_health_status(entry: &StatusEntry) -> (&'static str, &'static str, Color) {
+    if entry.crash_looping {
+        return ("✗", "Crash looping", Color::Red);
+    }
     match (&entry.action_type, entry.status) {
         (_, ProcessStatus::NotStarted) => ("○", "Not started", Color::Gray),
+        (_, ProcessStatus::Pending) => ("◌", "Pending", Color::Yellow),
+        (_, ProcessStatus::Blocked) => ("⊘", "Blocked", Color::Red),
         (Some(TaskAction::Ensure { .. }), ProcessStatus::Exited) => {
-            if entry.exit_code == Some(0) {
+            if entry.skipped {
+                ("✓", "Skipped (up to date)", Color::Cyan)
+            } else if entry.exit_code == Some(0) {
                 ("✓", "Completed", Color::Green)
             } else {
                 ("✗", "Failed", Color::Red)
@@ -30,9 +72,10 @@ fn get_health_status(entry: &StatusEntry) -> (&'static str, &'static str, Color)
         }
         (Some(TaskAction::Run { .. }), ProcessStatus::Running) => {
             // Check if healthcheck is configured and pending
-            match entry.healthcheck_passed {
-                Some(false) => ("⏳", "Starting", Color::Yellow),
-                Some(true) => ("✓", "Healthy", Color::Green),
+            match entry.healthcheck {
+                Some(HealthcheckState::Pending) => ("⏳", "Starting", Color::Yellow),
+                Some(HealthcheckState::Passed) => ("✓", "Healthy", Color::Green),
+                Some(HealthcheckState::Failed) => ("✗", "Unhealthy", Color::Red),
                 None => ("●", "Running", Color::Green),
             }
         }
@@ -50,34 +93,56 @@ fn get_default_health_status() -> (&'static str, &'static str, Color) {
 pub fn draw_shutdown(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     status_panel: &StatusPanel,
+    stop_stages: &HashMap<String, StopStage>,
 ) -> io::Result<()> {
     terminal.draw(|f| {
         let area = f.size();
 
-        let mut lines = vec![String::from("Shutting down...")];
-        lines.push(String::new());
+        let mut lines = vec![Line::from("Shutting down..."), Line::from("")];
 
         for entry in &status_panel.entries {
-            let status_str = match (&entry.action_type, entry.status) {
-                (_, ProcessStatus::NotStarted) => "○",
-                (Some(TaskAction::Ensure { .. }), ProcessStatus::Exited) => {
-                    if entry.exit_code == Some(0) {
-                        "✓"
+            let line = match stop_stages.get(&entry.task_name) {
+                Some(StopStage::Exited) => Line::from(vec![
+                    Span::styled("  ✓ ", Style::default().fg(Color::Green)),
+                    Span::raw(entry.task_name.clone()),
+                    Span::styled(" stopped", Style::default().fg(Color::DarkGray)),
+                ]),
+                Some(stage) => {
+                    let color = if *stage == StopStage::Killed {
+                        Color::Red
                     } else {
-                        "✗"
-                    }
+                        Color::Yellow
+                    };
+                    Line::from(vec![
+                        Span::styled("  ◌ ", Style::default().fg(color)),
+                        Span::raw(entry.task_name.clone()),
+                        Span::styled(format!(" {stage}"), Style::default().fg(color)),
+                    ])
+                }
+                None => {
+                    let status_str = match (&entry.action_type, entry.status) {
+                        (_, ProcessStatus::NotStarted | ProcessStatus::Pending) => "○",
+                        (_, ProcessStatus::Blocked) => "⊘",
+                        (Some(TaskAction::Ensure { .. }), ProcessStatus::Exited) => {
+                            if entry.exit_code == Some(0) || entry.skipped {
+                                "✓"
+                            } else {
+                                "✗"
+                            }
+                        }
+                        (_, ProcessStatus::Running) => "●",
+                        (_, ProcessStatus::Exited) => "✓",
+                    };
+                    Line::from(format!("  {} {}", status_str, entry.task_name))
                 }
-                (_, ProcessStatus::Running) => "●",
-                (_, ProcessStatus::Exited) => "✓",
             };
-            lines.push(format!("  {} {}", status_str, entry.task_name));
+            lines.push(line);
         }
 
-        lines.push(String::new());
-        lines.push(String::from("Waiting for all processes to exit..."));
+        lines.push(Line::from(""));
+        lines.push(Line::from("Waiting for all processes to exit..."));
 
-        let text = lines.join("\n");
-        let widget = Paragraph::new(text).block(
+        let widget = Paragraph::new(lines).block(
             Block::default()
                 .title("Shutdown Progress")
                 .borders(Borders::ALL),
@@ -122,7 +187,7 @@ pub fn draw_status(
         let chunks = Layout::default()
             .direction(Direction::Horizontal)
             .margin(0)
-            .constraints([Constraint::Min(0), Constraint::Length(22)].as_ref())
+            .constraints([Constraint::Min(0), Constraint::Length(SIDEBAR_WIDTH)].as_ref())
             .split(area);
 
         let main_area = chunks[0];
@@ -156,7 +221,15 @@ pub fn draw_status(
             .enumerate()
             .map(|(i, entry)| {
                 let (icon, text, status_color) = get_health_status(entry);
-                let status_text = format!("{} {}", icon, text);
+                let text = match (&entry.blocked_by, entry.status) {
+                    (Some(blocker), ProcessStatus::Blocked) => format!("{text} by {blocker}"),
+                    _ => text.to_string(),
+                };
+                let status_text = match entry.restarts {
+                    0 => format!("{} {}", icon, text),
+                    1 => format!("{} {} (1 restart)", icon, text),
+                    n => format!("{} {} ({n} restarts)", icon, text),
+                };
 
                 let (exit_code_text, exit_code_color) = match entry.exit_code {
                     Some(code) => {
@@ -177,13 +250,14 @@ pub fn draw_status(
                         let dep_status = status_panel.entries.iter().find(|e| e.task_name == *dep);
                         let is_down_or_failed = match dep_status {
                             Some(dep_entry) => match (&dep_entry.action_type, dep_entry.status) {
-                                (_, ProcessStatus::NotStarted) => false,
+                                (_, ProcessStatus::NotStarted | ProcessStatus::Pending) => false,
+                                (_, ProcessStatus::Blocked) => true,
                                 (Some(TaskAction::Ensure { .. }), ProcessStatus::Exited) => {
-                                    dep_entry.exit_code != Some(0)
+                                    dep_entry.exit_code != Some(0) && !dep_entry.skipped
                                 }
                                 (Some(TaskAction::Run { .. }), ProcessStatus::Exited) => true,
                                 (_, ProcessStatus::Exited) => true,
-                                _ => false,
+                                _ => dep_entry.healthcheck == Some(HealthcheckState::Failed),
                             },
                             None => true,
                         };
@@ -222,7 +296,25 @@ pub fn draw_status(
         .header(header)
         .block(Block::default().title("Tasks").borders(Borders::ALL));
 
-        f.render_widget(table, main_area);
+        // A failed reload is shown under the tasks until the next one works
+        if let Some(error) = &status_panel.config_error {
+            let [table_area, error_area] = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(5)])
+                .areas(main_area);
+            let error_widget = Paragraph::new(error.as_str())
+                .style(Style::default().fg(Color::Red))
+                .wrap(Wrap { trim: false })
+                .block(
+                    Block::default()
+                        .title("Config reload failed (still running the previous config)")
+                        .borders(Borders::ALL),
+                );
+            f.render_widget(table, table_area);
+            f.render_widget(error_widget, error_area);
+        } else {
+            f.render_widget(table, main_area);
+        }
 
         // Render task status
         let status_widget = render_task_status(status_panel);
@@ -232,6 +324,7 @@ pub fn draw_status(
             "1-9  view process",
             "←/→  navigate",
             "s    status",
+            "R    reload config",
             "q    quit",
         ]
         .join("\n");
@@ -245,22 +338,15 @@ pub fn draw_status(
     Ok(())
 }
 
+/// Draw `panel`'s output, with an input bar below it while in input mode.
 pub fn draw(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     panel: &Panel,
     status_panel: &StatusPanel,
+    input: Option<&Input>,
 ) -> io::Result<()> {
     terminal.draw(|f| {
-        let area = f.size();
-
-        let chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .margin(0)
-            .constraints([Constraint::Min(0), Constraint::Length(22)].as_ref())
-            .split(area);
-
-        let content_area = chunks[0];
-        let sidebar_area = chunks[1];
+        let (content_area, input_area, sidebar_area) = panel_layout(f.size(), input.is_some());
 
         // Split sidebar into status and help sections
         let sidebar_chunks = Layout::default()
@@ -271,6 +357,39 @@ pub fn draw(
         let status_area = sidebar_chunks[0];
         let help_area = sidebar_chunks[1];
 
+        if let (Some(input), Some(input_area)) = (input, input_area) {
+            let (title, text) = match input.mode {
+                InputMode::Line => (
+                    "Input [Enter: send, Esc: leave, Ctrl-D: close stdin]",
+                    format!("> {}", input.line),
+                ),
+                InputMode::Raw => (
+                    "Raw input [Ctrl-]: leave]",
+                    "Keys are sent to the task as they are typed".to_string(),
+                ),
+            };
+            // Keep the end of a long line in view
+            let inner_width = input_area.width.saturating_sub(2) as usize;
+            let text_width = text.chars().count();
+            let visible: String = text
+                .chars()
+                .skip((text_width + 1).saturating_sub(inner_width))
+                .collect();
+            if input.mode == InputMode::Line {
+                f.set_cursor(
+                    input_area.x + 1 + visible.chars().count() as u16,
+                    input_area.y + 1,
+                );
+            }
+            let input_widget = Paragraph::new(visible).block(
+                Block::default()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Yellow)),
+            );
+            f.render_widget(input_widget, input_area);
+        }
+
         let height = content_area.height.saturating_sub(2) as usize;
         // Inner width for text (subtract 2 for borders)
         let inner_width = content_area.width.saturating_sub(2) as usize;
@@ -354,23 +473,27 @@ pub fn draw(
         // Check if this task has a healthcheck configured
         let has_healthcheck = status_panel
             .get_entry(&panel.task_name)
-            .is_some_and(|e| e.healthcheck_passed.is_some());
+            .is_some_and(|e| e.healthcheck.is_some());
 
-        let title_text = if has_healthcheck {
+        let on_off = |show: bool| if show { "on" } else { "off" };
+        // A terminal's output is shown as stdout, since stderr is merged into it
+        let streams = if panel.tty {
+            format!("terminal: {}", on_off(panel.show_stdout))
+        } else {
             format!(
-                "{} [stdout: {}, stderr: {}, health: {}]",
-                panel.title,
-                if panel.show_stdout { "on" } else { "off" },
-                if panel.show_stderr { "on" } else { "off" },
-                if panel.show_healthcheck { "on" } else { "off" },
+                "stdout: {}, stderr: {}",
+                on_off(panel.show_stdout),
+                on_off(panel.show_stderr)
             )
-        } else {
+        };
+        let title_text = if has_healthcheck {
             format!(
-                "{} [stdout: {}, stderr: {}]",
+                "{} [{streams}, health: {}]",
                 panel.title,
-                if panel.show_stdout { "on" } else { "off" },
-                if panel.show_stderr { "on" } else { "off" },
+                on_off(panel.show_healthcheck)
             )
+        } else {
+            format!("{} [{streams}]", panel.title)
         };
 
         let title = Line::from(vec![
@@ -417,12 +540,21 @@ pub fn draw(
             "q    quit",
             "r    restart",
             "t    stop",
-            "o    toggle stdout",
-            "e    toggle stderr",
+            "R    reload config",
         ];
+        if panel.tty {
+            help_lines.push("o    toggle output");
+        } else {
+            help_lines.push("o    toggle stdout");
+            help_lines.push("e    toggle stderr");
+        }
         if has_healthcheck {
             help_lines.push("h    toggle health");
         }
+        if panel.accepts_input() {
+            help_lines.push("i    type input");
+            help_lines.push("I    send raw keys");
+        }
         let help_text = help_lines.join("\n");
 
         let help_widget = Paragraph::new(help_text)
@@ -0,0 +1,75 @@
+use indexmap::IndexMap;
+use schemars::generate::SchemaSettings;
+
+use crate::config::{Config, TaskConfiguration};
+
+/// Generate the JSON Schema for `rote.yaml`, derived from the config types.
+pub fn config_schema() -> serde_json::Value {
+    let mut generator = SchemaSettings::draft07().into_generator();
+    // Templates are merged into their tasks while loading, so `Config` has
+    // no field for them
+    let mut templates = generator.subschema_for::<IndexMap<String, TaskConfiguration>>();
+    templates.insert(
+        "description".to_string(),
+        "Reusable task settings that tasks can inherit with `extends`.".into(),
+    );
+
+    let mut schema = generator.into_root_schema_for::<Config>();
+    schema.insert("title".to_string(), "rote.yaml".into());
+    if let Some(properties) = schema
+        .get_mut("properties")
+        .and_then(|properties| properties.as_object_mut())
+    {
+        properties.insert("templates".to_string(), templates.to_value());
+    }
+    schema.to_value()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn validate(yaml: &str) -> Vec<String> {
+        let instance: serde_json::Value = serde_yaml::from_str(yaml).unwrap();
+        let validator = jsonschema::validator_for(&config_schema()).unwrap();
+        validator
+            .iter_errors(&instance)
+            .map(|e| format!("{}: {e}", e.instance_path))
+            .collect()
+    }
+
+    #[test]
+    fn test_example_yaml_validates() {
+        let yaml = fs::read_to_string("tests/data/example.yaml").unwrap();
+        assert_eq!(validate(&yaml), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_templates_and_custom_fields_validate() {
+        let yaml = fs::read_to_string("tests/data/overlay/templates.yaml").unwrap();
+        assert_eq!(validate(&yaml), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_require_conditions_validate() {
+        let yaml = "tasks:\n  api:\n    run: ./api\n    require:\n      - db\n      - task: seed\n        condition: completed_successfully\n";
+        assert_eq!(validate(yaml), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_invalid_configs_fail_validation() {
+        for yaml in [
+            "default: api\n",
+            "tasks:\n  api:\n    run: ./api\n    require: db\n",
+            "tasks:\n  api:\n    run: ./api\n    require:\n      - task: db\n        condition: ready\n",
+            "tasks:\n  api:\n    run: ./api\n    display: [stdout, logs]\n",
+            "tasks:\n  api:\n    run: ./api\n    healthcheck:\n      interval: 1\n",
+            "tasks:\n  api:\n    run: ./api\n    healthcheck:\n      cmd: 'true'\n      tool: is-port-open 80\n      interval: 1\n",
+            "tasks:\n  api:\n    run: ./api\n    env:\n      LIST: [a, b]\n",
+            "templates:\n  base:\n    require: db\ntasks:\n  api:\n    run: ./api\n",
+        ] {
+            assert!(!validate(yaml).is_empty(), "expected errors for:\n{yaml}");
+        }
+    }
+}
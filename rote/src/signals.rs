@@ -1,10 +1,26 @@
-use nix::sys::signal::kill;
+use nix::sys::signal::{Signal, kill};
 use nix::unistd::Pid;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// Interval at which groups being stopped by [`stop_process_groups`] are
+/// polled for exit.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 /// Check if a process has exited by sending signal 0.
-/// Returns true if the process does not exist (has exited).
+/// Returns true if neither the process nor a process group it leads exist,
+/// so that background processes a task leaves behind count as part of it.
 pub fn is_process_exited(pid: Pid) -> bool {
+    is_gone(pid) && is_process_group_exited(pid)
+}
+
+/// Check if every process in the process group led by `pgid` has exited.
+pub fn is_process_group_exited(pgid: Pid) -> bool {
+    is_gone(Pid::from_raw(-pgid.as_raw()))
+}
+
+/// Whether signal 0 finds nothing to send to. A negative pid means a
+/// process group.
+fn is_gone(pid: Pid) -> bool {
     match kill(pid, None) {
         Err(nix::Error::ESRCH) => true, // Process does not exist
         Ok(_) => false,                 // Process still exists
@@ -12,75 +28,64 @@ pub fn is_process_exited(pid: Pid) -> bool {
     }
 }
 
-/// Check if a process has exited by PID.
-/// Returns true if pid is None or the process does not exist.
-pub fn is_process_exited_by_pid(pid: Option<u32>) -> bool {
-    match pid {
-        None => true,
-        Some(p) => is_process_exited(Pid::from_raw(p as i32)),
+/// Stop the process groups led by `pids`: send them `SIGTERM`, give them up
+/// to `timeout` to exit and then send `SIGKILL` to whatever is left. This
+/// blocks, so it also works where there is no runtime, like in a panic hook.
+pub fn stop_process_groups(pids: &[Pid], timeout: Duration) {
+    let running: Vec<Pid> = pids
+        .iter()
+        .copied()
+        .filter(|pid| !is_process_exited(*pid))
+        .collect();
+    for pid in &running {
+        let _ = kill(Pid::from_raw(-pid.as_raw()), Signal::SIGTERM);
     }
-}
 
-pub async fn wait_for_child_exit(pid: Option<u32>) -> bool {
-    let Some(pid) = pid else {
-        return false;
-    };
-    let pid = Pid::from_raw(pid as i32);
-
-    for _ in 0..50 {
-        tokio::time::sleep(Duration::from_millis(100)).await;
-        if is_process_exited(pid) {
-            return true;
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline && !running.iter().all(|pid| is_process_exited(*pid)) {
+        std::thread::sleep(STOP_POLL_INTERVAL);
+    }
+    for pid in &running {
+        if !is_process_exited(*pid) {
+            let _ = kill(Pid::from_raw(-pid.as_raw()), Signal::SIGKILL);
         }
     }
-    false
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::process::{Command, Stdio};
-    use tokio::time;
+    use std::process::Command;
 
-    #[tokio::test]
-    async fn test_wait_for_child_exit_none_pid() {
-        let result = wait_for_child_exit(None).await;
-        assert!(!result);
-    }
-
-    #[tokio::test]
-    async fn test_wait_for_child_exit_nonexistent_process() {
-        let result = wait_for_child_exit(Some(999999)).await;
-        assert!(result, "Nonexistent process should be considered exited");
-    }
+    #[test]
+    fn test_stop_process_groups() {
+        use std::os::unix::process::CommandExt;
 
-    #[tokio::test]
-    async fn test_wait_for_child_exit_timeout() {
-        let mut child = Command::new("sleep")
-            .arg("10")
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
+        // One group exits on SIGTERM, the other has to be killed
+        let mut polite = Command::new("sleep")
+            .arg("30")
+            .process_group(0)
             .spawn()
             .expect("Failed to spawn sleep process");
+        let mut stubborn = Command::new("sh")
+            .args(["-c", "trap '' TERM; while true; do sleep 0.05; done"])
+            .process_group(0)
+            .spawn()
+            .expect("Failed to spawn shell");
+        let pids = [polite.id(), stubborn.id()].map(|pid| Pid::from_raw(pid as i32));
+        std::thread::sleep(Duration::from_millis(100));
 
-        let pid = child.id();
+        // Reap the processes as they exit, so their groups go away
+        let polite_status = std::thread::spawn(move || polite.wait().unwrap());
+        let stubborn_status = std::thread::spawn(move || stubborn.wait().unwrap());
 
-        let start = time::Instant::now();
-        let result = wait_for_child_exit(Some(pid)).await;
-        let elapsed = start.elapsed();
+        let start = Instant::now();
+        stop_process_groups(&pids, Duration::from_millis(300));
+        assert!(start.elapsed() < Duration::from_secs(2));
 
-        assert!(!result, "Wait should timeout");
-        assert!(
-            elapsed >= Duration::from_secs(4),
-            "Should have waited at least 4 seconds"
-        );
-        assert!(
-            elapsed < Duration::from_secs(6),
-            "Should not have waited more than 5 seconds"
-        );
-
-        child.kill().expect("Failed to kill process");
-        child.wait().expect("Failed to wait for process");
+        use std::os::unix::process::ExitStatusExt;
+        assert_eq!(polite_status.join().unwrap().signal(), Some(15));
+        assert_eq!(stubborn_status.join().unwrap().signal(), Some(9));
     }
 
     #[test]
@@ -91,20 +96,4 @@ mod tests {
             "Nonexistent process should be considered exited"
         );
     }
-
-    #[test]
-    fn test_is_process_exited_by_pid_none() {
-        assert!(
-            is_process_exited_by_pid(None),
-            "None pid should be considered exited"
-        );
-    }
-
-    #[test]
-    fn test_is_process_exited_by_pid_nonexistent() {
-        assert!(
-            is_process_exited_by_pid(Some(999999)),
-            "Nonexistent process should be considered exited"
-        );
-    }
 }
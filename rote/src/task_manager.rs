@@ -1,6 +1,6 @@
 use std::collections::{HashMap, HashSet};
 
-use crate::config::{Config, TaskAction};
+use crate::config::{Config, DependencyCondition};
 use crate::error::{Result, RoteError};
 use crate::panel::PanelIndex;
 
@@ -8,10 +8,12 @@ use crate::panel::PanelIndex;
 pub struct TaskManager {
     /// Tasks waiting to be started (in dependency order).
     pending_tasks: Vec<String>,
-    /// Ensure tasks that have completed successfully.
-    completed_ensure_tasks: HashSet<String>,
+    /// Tasks that have exited with status 0.
+    completed_tasks: HashSet<String>,
     /// Run tasks with healthchecks that have passed.
     healthy_tasks: HashSet<String>,
+    /// Every task being started, which decides which `after` entries count.
+    selected: HashSet<String>,
     /// Mapping from task name to panel index.
     task_to_panel: HashMap<String, PanelIndex>,
 }
@@ -20,16 +22,40 @@ impl TaskManager {
     /// Create a new TaskManager with the given list of tasks to start.
     pub fn new(tasks_to_start: Vec<String>, task_to_panel: HashMap<String, PanelIndex>) -> Self {
         Self {
+            selected: tasks_to_start.iter().cloned().collect(),
             pending_tasks: tasks_to_start,
-            completed_ensure_tasks: HashSet::new(),
+            completed_tasks: HashSet::new(),
             healthy_tasks: HashSet::new(),
             task_to_panel,
         }
     }
 
-    /// Mark an Ensure task as completed (exit code 0).
-    pub fn mark_ensure_completed(&mut self, task_name: &str) {
-        self.completed_ensure_tasks.insert(task_name.to_string());
+    /// Switch to a new list of tasks to start after the config was reloaded.
+    /// Tasks that weren't selected before are added to the pending list, and
+    /// tasks that are no longer selected are dropped from it.
+    pub fn reload(
+        &mut self,
+        tasks_to_start: Vec<String>,
+        task_to_panel: HashMap<String, PanelIndex>,
+    ) {
+        let selected: HashSet<String> = tasks_to_start.iter().cloned().collect();
+        self.pending_tasks.retain(|task| selected.contains(task));
+        for task in tasks_to_start {
+            if !self.selected.contains(&task) {
+                self.pending_tasks.push(task);
+            }
+        }
+        self.completed_tasks
+            .retain(|task| task_to_panel.contains_key(task));
+        self.healthy_tasks
+            .retain(|task| task_to_panel.contains_key(task));
+        self.selected = selected;
+        self.task_to_panel = task_to_panel;
+    }
+
+    /// Mark a task as completed (exit code 0).
+    pub fn mark_completed(&mut self, task_name: &str) {
+        self.completed_tasks.insert(task_name.to_string());
     }
 
     /// Mark a Run task with a healthcheck as healthy.
@@ -37,6 +63,12 @@ impl TaskManager {
         self.healthy_tasks.insert(task_name.to_string());
     }
 
+    /// Mark a Run task whose healthcheck failed after passing as unhealthy,
+    /// so tasks that require it to be healthy wait until it recovers.
+    pub fn mark_unhealthy(&mut self, task_name: &str) {
+        self.healthy_tasks.remove(task_name);
+    }
+
     /// Check if a task is marked as healthy.
     pub fn is_healthy(&self, task_name: &str) -> bool {
         self.healthy_tasks.contains(task_name)
@@ -65,37 +97,77 @@ impl TaskManager {
         ready
     }
 
-    /// Check if all blocking dependencies for a task have been satisfied.
-    /// A dependency blocks if it's an Ensure task (must complete with exit 0)
-    /// or a Run task with a healthcheck (must pass healthcheck).
+    /// Put a task taken from the pending list back at its front, to be taken
+    /// again once its dependencies are satisfied.
+    pub fn requeue(&mut self, task_name: &str) {
+        if !self.pending_tasks.iter().any(|task| task == task_name) {
+            self.pending_tasks.insert(0, task_name.to_string());
+        }
+    }
+
+    /// Check if all dependencies for a task have reached their condition
+    /// (see [`crate::config::Dependency::condition_for`]). A task counts as started once it
+    /// has been taken from the pending list.
     fn are_deps_satisfied(&self, task_name: &str, config: &Config) -> bool {
         let Some(task_config) = config.tasks.get(task_name) else {
             return true;
         };
 
-        task_config.require.iter().all(|dep| {
-            if let Some(dep_config) = config.tasks.get(dep) {
-                match &dep_config.action {
-                    Some(TaskAction::Ensure { .. }) => {
-                        // Ensure tasks must complete successfully
-                        self.completed_ensure_tasks.contains(dep)
-                    }
-                    Some(TaskAction::Run { .. }) => {
-                        // Run tasks with healthchecks must become healthy
-                        if dep_config.healthcheck.is_some() {
-                            self.healthy_tasks.contains(dep)
-                        } else {
-                            true // Run tasks without healthchecks don't block
-                        }
-                    }
-                    None => true, // No action, assume satisfied
+        task_config.dependencies(&self.selected).all(|dep| {
+            let Some(dep_config) = config.tasks.get(&dep.task) else {
+                return true; // Unknown dep, assume satisfied
+            };
+            match dep.condition_for(dep_config) {
+                DependencyCondition::Started => !self.pending_tasks.contains(&dep.task),
+                DependencyCondition::Healthy => self.healthy_tasks.contains(&dep.task),
+                DependencyCondition::CompletedSuccessfully => {
+                    self.completed_tasks.contains(&dep.task)
                 }
-            } else {
-                true // Unknown dep, assume satisfied
             }
         })
     }
 
+    /// Pending tasks that can't start until `task_name` is ready, either
+    /// because they wait for it or because they wait for another such task.
+    pub fn pending_dependents(&self, task_name: &str, config: &Config) -> Vec<String> {
+        let mut dependents: Vec<String> = Vec::new();
+        loop {
+            let found = dependents.len();
+            for task in &self.pending_tasks {
+                if dependents.contains(task) {
+                    continue;
+                }
+                let waits = config.tasks.get(task).is_some_and(|task_config| {
+                    task_config
+                        .dependencies(&self.selected)
+                        .any(|dep| dep.task == task_name || dependents.contains(&dep.task))
+                });
+                if waits {
+                    dependents.push(task.clone());
+                }
+            }
+            if dependents.len() == found {
+                return dependents;
+            }
+        }
+    }
+
+    /// Tasks being started that wait for `task_name` directly, in config
+    /// order.
+    pub fn dependents(&self, task_name: &str, config: &Config) -> Vec<String> {
+        config
+            .tasks
+            .iter()
+            .filter(|(name, task_config)| {
+                self.selected.contains(*name)
+                    && task_config
+                        .dependencies(&self.selected)
+                        .any(|dep| dep.task == task_name)
+            })
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
     /// Check if there are pending tasks.
     pub fn has_pending_tasks(&self) -> bool {
         !self.pending_tasks.is_empty()
@@ -103,14 +175,28 @@ impl TaskManager {
 }
 
 /// Resolve all dependencies for the target tasks using topological sort.
+///
+/// The result holds the targets and everything they `require`. Tasks listed
+/// in `after` aren't added, but are ordered first if they are in the result.
 pub fn resolve_dependencies(config: &Config, targets: &[String]) -> Result<Vec<String>> {
     let mut result = Vec::new();
     let mut visited = HashSet::new();
     let mut temp_mark = HashSet::new();
 
+    let mut selected = HashSet::new();
+    let mut stack: Vec<&str> = targets.iter().map(String::as_str).collect();
+    while let Some(task) = stack.pop() {
+        if selected.insert(task.to_string())
+            && let Some(task_config) = config.tasks.get(task)
+        {
+            stack.extend(task_config.require.iter().map(|dep| dep.task.as_str()));
+        }
+    }
+
     fn visit(
         task: &str,
         config: &Config,
+        selected: &HashSet<String>,
         result: &mut Vec<String>,
         visited: &mut HashSet<String>,
         temp_mark: &mut HashSet<String>,
@@ -133,8 +219,19 @@ pub fn resolve_dependencies(config: &Config, targets: &[String]) -> Result<Vec<S
             .ok_or_else(|| RoteError::Dependency(format!("Task '{task}' not found in config")))?;
 
         // Visit dependencies first
-        for dep in &task_config.require {
-            visit(dep, config, result, visited, temp_mark)?;
+        for dep in task_config.dependencies(selected) {
+            if dep.condition == Some(DependencyCondition::Healthy)
+                && config
+                    .tasks
+                    .get(&dep.task)
+                    .is_some_and(|dep_config| dep_config.healthcheck.is_none())
+            {
+                return Err(RoteError::Dependency(format!(
+                    "Task '{task}' requires '{}' to be healthy, but it has no healthcheck",
+                    dep.task
+                )));
+            }
+            visit(&dep.task, config, selected, result, visited, temp_mark)?;
         }
 
         temp_mark.remove(task);
@@ -145,7 +242,14 @@ pub fn resolve_dependencies(config: &Config, targets: &[String]) -> Result<Vec<S
     }
 
     for target in targets {
-        visit(target, config, &mut result, &mut visited, &mut temp_mark)?;
+        visit(
+            target,
+            config,
+            &selected,
+            &mut result,
+            &mut visited,
+            &mut temp_mark,
+        )?;
     }
 
     Ok(result)
@@ -154,7 +258,7 @@ pub fn resolve_dependencies(config: &Config, targets: &[String]) -> Result<Vec<S
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::{CommandValue, TaskConfiguration};
+    use crate::config::{CommandValue, Dependency, TaskAction, TaskConfiguration};
     use indexmap::IndexMap;
     use std::borrow::Cow;
 
@@ -167,16 +271,17 @@ mod tests {
                     action,
                     cwd: None,
                     display: None,
-                    require: require.into_iter().map(String::from).collect(),
-                    autorestart: false,
+                    require: require.into_iter().map(Dependency::from).collect(),
                     timestamps: false,
                     healthcheck: None,
+                    ..Default::default()
                 },
             );
         }
         Config {
             default: None,
             tasks: task_map,
+            ..Default::default()
         }
     }
 
@@ -256,10 +361,16 @@ mod tests {
         assert_eq!(tm.pending_tasks, vec!["task1"]);
 
         // After marking setup as complete, task1 should be ready
-        tm.mark_ensure_completed("setup");
+        tm.mark_completed("setup");
         let ready = tm.take_ready_tasks(&config);
         assert_eq!(ready, vec!["task1"]);
         assert!(tm.pending_tasks.is_empty());
+
+        // A requeued task is ready again, once
+        tm.requeue("task1");
+        tm.requeue("task1");
+        assert_eq!(tm.take_ready_tasks(&config), vec!["task1"]);
+        assert!(tm.pending_tasks.is_empty());
     }
 
     #[test]
@@ -285,9 +396,220 @@ mod tests {
         assert_eq!(ready.len(), 2);
     }
 
+    #[test]
+    fn test_task_manager_explicit_conditions() {
+        let run = |cmd: &'static str| {
+            Some(TaskAction::Run {
+                command: CommandValue::String(Cow::Borrowed(cmd)),
+            })
+        };
+        let mut config = make_config_with_tasks(vec![
+            ("logger", run("logger"), vec![]),
+            ("seed", run("seed"), vec![]),
+            ("tail", run("tail"), vec![]),
+            ("app", run("app"), vec![]),
+        ]);
+        config.tasks["tail"].require = vec![Dependency {
+            task: "logger".to_string(),
+            condition: Some(DependencyCondition::Started),
+        }];
+        config.tasks["app"].require = vec![Dependency {
+            task: "seed".to_string(),
+            condition: Some(DependencyCondition::CompletedSuccessfully),
+        }];
+
+        let mut tm = TaskManager::new(
+            vec![
+                "logger".to_string(),
+                "seed".to_string(),
+                "tail".to_string(),
+                "app".to_string(),
+            ],
+            HashMap::new(),
+        );
+
+        // `tail` only needs `logger` to have been started
+        assert_eq!(tm.take_ready_tasks(&config), vec!["logger", "seed", "tail"]);
+        assert_eq!(tm.pending_tasks, vec!["app"]);
+
+        // `app` waits for the `seed` run task to exit successfully
+        tm.mark_completed("seed");
+        assert_eq!(tm.take_ready_tasks(&config), vec!["app"]);
+    }
+
+    #[test]
+    fn test_task_manager_started_waits_for_pending_dependency() {
+        let config = make_config_with_tasks(vec![
+            (
+                "setup",
+                Some(TaskAction::Ensure {
+                    command: CommandValue::String(Cow::Borrowed("setup")),
+                }),
+                vec![],
+            ),
+            ("server", None, vec!["setup"]),
+            ("client", None, vec!["server"]),
+        ]);
+
+        let mut tm = TaskManager::new(
+            vec![
+                "setup".to_string(),
+                "server".to_string(),
+                "client".to_string(),
+            ],
+            HashMap::new(),
+        );
+
+        // `client` can't start before `server`, which is waiting for `setup`
+        assert_eq!(tm.take_ready_tasks(&config), vec!["setup"]);
+        tm.mark_completed("setup");
+        assert_eq!(tm.take_ready_tasks(&config), vec!["server", "client"]);
+    }
+
+    #[test]
+    fn test_resolve_dependencies_healthy_without_healthcheck() {
+        let mut config = make_config_with_tasks(vec![("db", None, vec![]), ("api", None, vec![])]);
+        config.tasks["api"].require = vec![Dependency {
+            task: "db".to_string(),
+            condition: Some(DependencyCondition::Healthy),
+        }];
+
+        let err = resolve_dependencies(&config, &["api".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("requires 'db' to be healthy"));
+    }
+
+    #[test]
+    fn test_resolve_dependencies_after_orders_without_pulling_in() {
+        let mut config = make_config_with_tasks(vec![
+            ("web", None, vec![]),
+            ("api", None, vec![]),
+            ("app", None, vec!["api", "web"]),
+        ]);
+        config.tasks["web"].after = vec![Dependency::from("api")];
+
+        // `web` alone doesn't start `api`
+        let result = resolve_dependencies(&config, &["web".to_string()]).unwrap();
+        assert_eq!(result, vec!["web"]);
+
+        // But when both are started, `api` comes first
+        let result =
+            resolve_dependencies(&config, &["web".to_string(), "api".to_string()]).unwrap();
+        assert_eq!(result, vec!["api", "web"]);
+        let result = resolve_dependencies(&config, &["app".to_string()]).unwrap();
+        assert_eq!(result, vec!["api", "web", "app"]);
+    }
+
+    #[test]
+    fn test_resolve_dependencies_after_cycle() {
+        let mut config = make_config_with_tasks(vec![("a", None, vec!["b"]), ("b", None, vec![])]);
+        config.tasks["b"].after = vec![Dependency::from("a")];
+
+        let result = resolve_dependencies(&config, &["a".to_string()]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_task_manager_after_only_blocks_selected_tasks() {
+        let mut config = make_config_with_tasks(vec![
+            (
+                "migrate",
+                Some(TaskAction::Ensure {
+                    command: CommandValue::String(Cow::Borrowed("migrate")),
+                }),
+                vec![],
+            ),
+            ("web", None, vec![]),
+        ]);
+        config.tasks["web"].after = vec![Dependency::from("migrate")];
+
+        let mut tm = TaskManager::new(vec!["web".to_string()], HashMap::new());
+        assert_eq!(tm.take_ready_tasks(&config), vec!["web"]);
+
+        let mut tm = TaskManager::new(
+            vec!["migrate".to_string(), "web".to_string()],
+            HashMap::new(),
+        );
+        assert_eq!(tm.take_ready_tasks(&config), vec!["migrate"]);
+        assert_eq!(tm.pending_dependents("migrate", &config), vec!["web"]);
+        tm.mark_completed("migrate");
+        assert_eq!(tm.take_ready_tasks(&config), vec!["web"]);
+    }
+
+    #[test]
+    fn test_task_manager_dependents() {
+        let mut config = make_config_with_tasks(vec![
+            ("codegen", None, vec![]),
+            ("api", None, vec!["codegen"]),
+            ("web", None, vec![]),
+            ("docs", None, vec!["codegen"]),
+        ]);
+        config.tasks["web"].after = vec![Dependency::from("codegen")];
+
+        let tm = TaskManager::new(
+            vec!["codegen".to_string(), "api".to_string(), "web".to_string()],
+            HashMap::new(),
+        );
+        // `docs` isn't being started
+        assert_eq!(tm.dependents("codegen", &config), vec!["api", "web"]);
+        assert!(tm.dependents("api", &config).is_empty());
+    }
+
+    #[test]
+    fn test_task_manager_reload() {
+        let config = make_config_with_tasks(vec![
+            ("db", None, vec![]),
+            ("cache", None, vec![]),
+            ("api", None, vec!["db", "cache"]),
+            ("old", None, vec![]),
+        ]);
+
+        let mut tm = TaskManager::new(
+            vec!["db".to_string(), "old".to_string(), "api".to_string()],
+            HashMap::new(),
+        );
+        assert_eq!(tm.take_ready_tasks(&config), vec!["db", "old", "api"]);
+        tm.mark_completed("old");
+
+        // `cache` is new, `old` was removed and `db` keeps running
+        let task_to_panel = HashMap::from([
+            ("db".to_string(), PanelIndex::new(0)),
+            ("cache".to_string(), PanelIndex::new(1)),
+            ("api".to_string(), PanelIndex::new(2)),
+        ]);
+        tm.reload(
+            vec!["db".to_string(), "cache".to_string(), "api".to_string()],
+            task_to_panel,
+        );
+        assert_eq!(tm.take_ready_tasks(&config), vec!["cache"]);
+        assert!(!tm.has_pending_tasks());
+        assert!(!tm.completed_tasks.contains("old"));
+        assert_eq!(tm.get_panel_index("cache"), Some(PanelIndex::new(1)));
+    }
+
+    #[test]
+    fn test_task_manager_pending_dependents() {
+        let config = make_config_with_tasks(vec![
+            ("db", None, vec![]),
+            ("api", None, vec!["db"]),
+            ("web", None, vec!["api"]),
+            ("docs", None, vec![]),
+        ]);
+
+        let tm = TaskManager::new(
+            vec!["web".to_string(), "api".to_string(), "docs".to_string()],
+            HashMap::new(),
+        );
+
+        assert_eq!(tm.pending_dependents("db", &config), vec!["api", "web"]);
+        assert_eq!(tm.pending_dependents("api", &config), vec!["web"]);
+        assert!(tm.pending_dependents("docs", &config).is_empty());
+    }
+
     #[test]
     fn test_task_manager_run_with_healthcheck_blocks() {
-        use crate::config::{Healthcheck, HealthcheckMethod};
+        use crate::config::{
+            DEFAULT_HEALTHCHECK_TIMEOUT, Healthcheck, HealthcheckMethod, HealthcheckMode,
+        };
         use std::time::Duration;
 
         let mut task_map = IndexMap::new();
@@ -300,12 +622,17 @@ mod tests {
                 cwd: None,
                 display: None,
                 require: vec![],
-                autorestart: false,
                 timestamps: false,
                 healthcheck: Some(Healthcheck {
                     method: HealthcheckMethod::Cmd("curl localhost:8080".to_string()),
                     interval: Duration::from_secs(1),
+                    timeout: DEFAULT_HEALTHCHECK_TIMEOUT,
+                    retries: None,
+                    start_period: Duration::ZERO,
+                    mode: HealthcheckMode::Readiness,
+                    restart_on_unhealthy: false,
                 }),
+                ..Default::default()
             },
         );
         task_map.insert(
@@ -316,16 +643,17 @@ mod tests {
                 }),
                 cwd: None,
                 display: None,
-                require: vec!["server".to_string()],
-                autorestart: false,
+                require: vec!["server".into()],
                 timestamps: false,
                 healthcheck: None,
+                ..Default::default()
             },
         );
 
         let config = Config {
             default: None,
             tasks: task_map,
+            ..Default::default()
         };
 
         let mut tm = TaskManager::new(
@@ -338,6 +666,12 @@ mod tests {
         assert_eq!(ready, vec!["server"]);
         assert_eq!(tm.pending_tasks, vec!["client"]);
 
+        // A liveness check failing before client got to start blocks it again
+        tm.mark_healthy("server");
+        tm.mark_unhealthy("server");
+        assert!(tm.take_ready_tasks(&config).is_empty());
+        assert_eq!(tm.pending_tasks, vec!["client"]);
+
         // After marking server as healthy, client should be ready
         tm.mark_healthy("server");
         let ready = tm.take_ready_tasks(&config);
@@ -1,9 +1,16 @@
+use crate::input::InputMode;
 use crate::panel::{PanelIndex, StreamKind};
+use crossterm::event::KeyEvent;
 use std::process::ExitStatus;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum ProcessStatus {
     NotStarted,
+    /// Being started, but waiting for its dependencies.
+    Pending,
+    /// Being started, but a dependency failed or became unhealthy, so it
+    /// can't start.
+    Blocked,
     Running,
     Exited,
 }
@@ -17,6 +24,9 @@ pub enum UiEvent {
     },
     Exited {
         panel: PanelIndex,
+        /// The generation of the process that exited, see
+        /// [`TaskInstance::generation`](crate::process::TaskInstance::generation)
+        generation: u64,
         status: Option<ExitStatus>,
         exit_code: Option<i32>,
     },
@@ -32,7 +42,17 @@ pub enum UiEvent {
     ToggleStderr,
     ToggleHealthcheck,
     Restart,
+    /// Restart a specific task, as `Restart` does for the active one
+    RestartTask {
+        panel: PanelIndex,
+    },
     Stop,
+    /// A process stopped in the background is gone, and its output has
+    /// been read
+    Stopped {
+        panel: PanelIndex,
+        exit_code: Option<i32>,
+    },
     Exit,
     /// Navigate to the previous panel (or status if at first panel)
     PrevPanel,
@@ -49,10 +69,57 @@ pub enum UiEvent {
         task_name: String,
         text: String,
     },
-    /// Healthcheck failed for a task
+    /// Healthcheck failed for a task and will be retried
     HealthcheckFailed {
         task_name: String,
+        /// How many more failures mark the task as unhealthy, if limited
+        retries_left: Option<u32>,
+    },
+    /// Healthcheck failed `retries` times in a row
+    HealthcheckUnhealthy {
+        task_name: String,
+    },
+    /// A scheduled automatic restart is due
+    AutoRestart {
+        panel: PanelIndex,
+    },
+    /// An `ensure` task's `timeout` ran out
+    TimedOut {
+        panel: PanelIndex,
+        /// The generation of the process the timeout was started for, so a
+        /// later run of the same task isn't stopped by it
+        generation: u64,
+    },
+    /// Whether an `ensure` task about to start can be skipped, or why that
+    /// couldn't be found out, in which case it runs
+    FreshnessChecked {
+        task_name: String,
+        up_to_date: Result<bool, String>,
+    },
+    /// The fingerprint of what a successful `ensure` task ran against, or
+    /// `None` if it couldn't be taken
+    Fingerprinted {
+        panel: PanelIndex,
+        /// The generation of the process that succeeded
+        generation: u64,
+        fingerprint: Option<String>,
+    },
+    /// Load the config files again and apply what changed
+    ReloadConfig,
+    /// Files matching a task's `watch` patterns changed
+    FilesChanged {
+        task_name: String,
+        /// The changed paths, relative to the task's directory
+        paths: Vec<String>,
     },
+    /// Start sending typed keys to the focused task
+    EnterInputMode(InputMode),
+    /// A key typed in input mode
+    Input(KeyEvent),
+    /// Stop sending typed keys to the focused task
+    LeaveInputMode,
+    /// The terminal was resized
+    Resize,
 }
 
 #[cfg(test)]
@@ -92,6 +159,8 @@ mod tests {
 
         assert_eq!(format!("{running:?}"), "Running");
         assert_eq!(format!("{exited:?}"), "Exited");
+        assert_eq!(format!("{:?}", ProcessStatus::Pending), "Pending");
+        assert_eq!(format!("{:?}", ProcessStatus::Blocked), "Blocked");
     }
 
     #[test]
@@ -120,6 +189,7 @@ mod tests {
     fn test_ui_event_exited() {
         let event = UiEvent::Exited {
             panel: PanelIndex::new(2),
+            generation: 7,
             status: None,
             exit_code: Some(0),
         };
@@ -127,10 +197,12 @@ mod tests {
         match event {
             UiEvent::Exited {
                 panel,
+                generation,
                 status,
                 exit_code,
             } => {
                 assert_eq!(*panel, 2);
+                assert_eq!(generation, 7);
                 assert_eq!(status, None);
                 assert_eq!(exit_code, Some(0));
             }
@@ -0,0 +1,387 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+
+use crate::ui::UiEvent;
+
+/// How long the files have to stay unchanged before a change is reported, so
+/// that saving many files at once (or a build writing them) triggers a single
+/// restart.
+pub const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Decides which paths (relative to the task's directory) count as changes.
+///
+/// A path matches a pattern if it or one of its parent directories does, so
+/// `src` covers everything below `src` and ignoring `node_modules` ignores
+/// everything in it.
+pub struct WatchFilter {
+    watch: GlobSet,
+    ignore: GlobSet,
+}
+
+impl WatchFilter {
+    pub fn new(watch: &[String], ignore: &[String]) -> Result<Self> {
+        Ok(Self {
+            watch: build_glob_set(watch)?,
+            ignore: build_glob_set(ignore)?,
+        })
+    }
+
+    pub fn matches(&self, relative: &Path) -> bool {
+        let mut watched = false;
+        for path in relative
+            .ancestors()
+            .filter(|path| !path.as_os_str().is_empty())
+        {
+            if self.ignore.is_match(path) {
+                return false;
+            }
+            watched |= self.watch.is_match(path);
+        }
+        watched
+    }
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(
+            GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("invalid glob '{pattern}'"))?,
+        );
+    }
+    Ok(builder.build()?)
+}
+
+/// Check that a `watch` or `watch_ignore` pattern is a valid glob.
+pub fn validate_glob(pattern: &str) -> Result<()> {
+    GlobBuilder::new(pattern).literal_separator(true).build()?;
+    Ok(())
+}
+
+/// The part of a pattern before its first wildcard, e.g. `src` for
+/// `src/**/*.rs`. The whole pattern if it has none.
+pub(crate) fn literal_prefix(pattern: &str) -> (PathBuf, bool) {
+    let mut prefix = PathBuf::new();
+    for component in Path::new(pattern).components() {
+        let text = component.as_os_str().to_string_lossy();
+        if text.contains(['*', '?', '[', '{']) {
+            return (prefix, false);
+        }
+        prefix.push(component);
+    }
+    (prefix, true)
+}
+
+/// The directories to hand to the OS watcher. Watching only what the
+/// patterns can match keeps large directories like `node_modules` or
+/// `target` out of the way unless a pattern asks for them.
+fn watch_targets(root: &Path, patterns: &[String]) -> Vec<(PathBuf, RecursiveMode)> {
+    let mut targets: Vec<(PathBuf, RecursiveMode)> = Vec::new();
+    for pattern in patterns {
+        let (prefix, literal) = literal_prefix(pattern);
+        let mut path = root.join(prefix);
+        // Files are watched through their directory, since editors often
+        // replace a file rather than writing to it
+        let mut mode = RecursiveMode::Recursive;
+        if literal && !path.is_dir() {
+            mode = RecursiveMode::NonRecursive;
+            path.pop();
+        }
+        while !path.is_dir() && path.starts_with(root) && path != root {
+            path.pop();
+            mode = RecursiveMode::Recursive;
+        }
+        if !targets.contains(&(path.clone(), mode)) {
+            targets.push((path, mode));
+        }
+    }
+
+    // Drop anything a recursive watch already covers
+    let recursive: Vec<PathBuf> = targets
+        .iter()
+        .filter(|(_, mode)| *mode == RecursiveMode::Recursive)
+        .map(|(path, _)| path.clone())
+        .collect();
+    targets.retain(|(path, mode)| {
+        !recursive.iter().any(|other| {
+            path.starts_with(other) && (path != other || *mode == RecursiveMode::NonRecursive)
+        })
+    });
+    targets
+}
+
+/// Watches files for as long as it is kept around.
+pub struct FileWatcher {
+    _watcher: RecommendedWatcher,
+    debounce_task: tokio::task::JoinHandle<()>,
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        self.debounce_task.abort();
+    }
+}
+
+impl FileWatcher {
+    /// Watch `targets`, and once changes to the paths `relevant` keeps
+    /// (under the name it gives them) have settled for [`WATCH_DEBOUNCE`],
+    /// send the event `make_event` builds from them.
+    fn spawn(
+        targets: Vec<(PathBuf, RecursiveMode)>,
+        relevant: impl Fn(PathBuf) -> Option<String> + Send + 'static,
+        make_event: impl Fn(Vec<String>) -> UiEvent + Send + 'static,
+        tx: mpsc::Sender<UiEvent>,
+    ) -> Result<Self> {
+        let (path_tx, mut path_rx) = mpsc::unbounded_channel::<PathBuf>();
+        let mut watcher =
+            notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+                if let Ok(event) = res
+                    && !matches!(event.kind, EventKind::Access(_))
+                {
+                    for path in event.paths {
+                        let _ = path_tx.send(path);
+                    }
+                }
+            })?;
+        for (path, mode) in targets {
+            watcher
+                .watch(&path, mode)
+                .with_context(|| format!("can't watch {}", path.display()))?;
+        }
+
+        let debounce_task = tokio::spawn(async move {
+            while let Some(path) = path_rx.recv().await {
+                let Some(first) = relevant(path) else {
+                    continue;
+                };
+                let mut changed = BTreeSet::from([first]);
+                while let Ok(Some(path)) =
+                    tokio::time::timeout(WATCH_DEBOUNCE, path_rx.recv()).await
+                {
+                    changed.extend(relevant(path));
+                }
+                if tx
+                    .send(make_event(changed.into_iter().collect()))
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            _watcher: watcher,
+            debounce_task,
+        })
+    }
+}
+
+/// Start watching `root` for changes matching `watch` but not `watch_ignore`.
+/// Sends a [`UiEvent::FilesChanged`] listing the changed paths once they have
+/// settled for [`WATCH_DEBOUNCE`].
+pub fn spawn_watcher(
+    task_name: String,
+    root: &Path,
+    watch: &[String],
+    watch_ignore: &[String],
+    tx: mpsc::Sender<UiEvent>,
+) -> Result<FileWatcher> {
+    let filter = WatchFilter::new(watch, watch_ignore)?;
+    let root = root
+        .canonicalize()
+        .with_context(|| format!("can't watch {}", root.display()))?;
+
+    FileWatcher::spawn(
+        watch_targets(&root, watch),
+        move |path| {
+            path.strip_prefix(&root)
+                .ok()
+                .filter(|relative| filter.matches(relative))
+                .map(|relative| relative.to_string_lossy().to_string())
+        },
+        move |paths| UiEvent::FilesChanged {
+            task_name: task_name.clone(),
+            paths,
+        },
+        tx,
+    )
+}
+
+/// Start watching the config files, sending [`UiEvent::ReloadConfig`] when
+/// one of them changes. Their directories are watched rather than the files
+/// themselves, so that editors replacing a file on save are noticed.
+pub fn spawn_config_watcher(files: &[PathBuf], tx: mpsc::Sender<UiEvent>) -> Result<FileWatcher> {
+    let files = files
+        .iter()
+        .map(|file| {
+            file.canonicalize()
+                .with_context(|| format!("can't watch {}", file.display()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut directories: Vec<PathBuf> = files
+        .iter()
+        .filter_map(|file| file.parent().map(Path::to_path_buf))
+        .collect();
+    directories.sort();
+    directories.dedup();
+
+    FileWatcher::spawn(
+        directories
+            .into_iter()
+            .map(|dir| (dir, RecursiveMode::NonRecursive))
+            .collect(),
+        move |path| {
+            files
+                .contains(&path)
+                .then(|| path.to_string_lossy().to_string())
+        },
+        |_| UiEvent::ReloadConfig,
+        tx,
+    )
+}
+
+/// Describe changed files for a panel's status line, e.g.
+/// `[changed: src/a.rs, src/b.rs and 2 more]`.
+pub fn describe_changes(paths: &[String]) -> String {
+    const SHOWN: usize = 3;
+    let listed = paths
+        .iter()
+        .take(SHOWN)
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(", ");
+    if paths.len() > SHOWN {
+        format!("[changed: {listed} and {} more]", paths.len() - SHOWN)
+    } else {
+        format!("[changed: {listed}]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(watch: &[&str], ignore: &[&str]) -> WatchFilter {
+        let strings =
+            |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        WatchFilter::new(&strings(watch), &strings(ignore)).unwrap()
+    }
+
+    #[test]
+    fn test_watch_filter_globs() {
+        let filter = filter(&["src/**/*.rs", "Cargo.toml"], &[]);
+        assert!(filter.matches(Path::new("src/main.rs")));
+        assert!(filter.matches(Path::new("src/app/mod.rs")));
+        assert!(filter.matches(Path::new("Cargo.toml")));
+        assert!(!filter.matches(Path::new("src/notes.txt")));
+        assert!(!filter.matches(Path::new("tests/Cargo.toml")));
+    }
+
+    #[test]
+    fn test_watch_filter_directories_and_ignore() {
+        let filter = filter(&["web"], &["web/node_modules", "**/*.log"]);
+        assert!(filter.matches(Path::new("web/index.js")));
+        assert!(filter.matches(Path::new("web/lib/util.js")));
+        assert!(!filter.matches(Path::new("web/node_modules/left-pad/index.js")));
+        assert!(!filter.matches(Path::new("web/debug.log")));
+        assert!(!filter.matches(Path::new("api/main.go")));
+    }
+
+    #[test]
+    fn test_invalid_glob() {
+        assert!(WatchFilter::new(&["src/[".to_string()], &[]).is_err());
+        assert!(validate_glob("src/[").is_err());
+        assert!(validate_glob("src/**/*.rs").is_ok());
+    }
+
+    #[test]
+    fn test_literal_prefix() {
+        assert_eq!(literal_prefix("src/**/*.rs"), (PathBuf::from("src"), false));
+        assert_eq!(literal_prefix("*.rs"), (PathBuf::new(), false));
+        assert_eq!(
+            literal_prefix("config/app.toml"),
+            (PathBuf::from("config/app.toml"), true)
+        );
+    }
+
+    #[test]
+    fn test_describe_changes() {
+        assert_eq!(describe_changes(&["a.rs".into()]), "[changed: a.rs]");
+        let paths: Vec<String> = ["a", "b", "c", "d", "e"].map(String::from).into();
+        assert_eq!(describe_changes(&paths), "[changed: a, b, c and 2 more]");
+    }
+
+    #[tokio::test]
+    async fn test_watcher_reports_changes_once() {
+        let dir = std::env::temp_dir().join(format!("rote-watch-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+
+        let (tx, mut rx) = mpsc::channel(10);
+        let _watcher = spawn_watcher(
+            "task".to_string(),
+            &dir,
+            &["src/*.rs".to_string()],
+            &["src/skip.rs".to_string()],
+            tx,
+        )
+        .unwrap();
+
+        std::fs::write(dir.join("src/skip.rs"), "").unwrap();
+        std::fs::write(dir.join("src/notes.txt"), "").unwrap();
+        std::fs::write(dir.join("src/a.rs"), "").unwrap();
+        std::fs::write(dir.join("src/b.rs"), "").unwrap();
+
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        let UiEvent::FilesChanged { task_name, paths } = event else {
+            panic!("expected FilesChanged");
+        };
+        assert_eq!(task_name, "task");
+        assert_eq!(paths, vec!["src/a.rs", "src/b.rs"]);
+
+        // Both writes were debounced into one event
+        let more = tokio::time::timeout(WATCH_DEBOUNCE * 2, rx.recv()).await;
+        assert!(more.is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_config_watcher() {
+        let dir = std::env::temp_dir().join(format!("rote-watch-config-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("rote.yaml");
+        std::fs::write(&config, "tasks: {}").unwrap();
+
+        let (tx, mut rx) = mpsc::channel(10);
+        let _watcher = spawn_config_watcher(std::slice::from_ref(&config), tx).unwrap();
+
+        // Other files next to it don't count
+        std::fs::write(dir.join("notes.txt"), "").unwrap();
+        let event = tokio::time::timeout(WATCH_DEBOUNCE * 3, rx.recv()).await;
+        assert!(event.is_err());
+
+        std::fs::write(&config, "tasks: {a: {run: a}}").unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap();
+        assert!(matches!(event, Some(UiEvent::ReloadConfig)));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
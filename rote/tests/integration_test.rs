@@ -112,9 +112,9 @@ async fn test_ensure_dependency_blocks_until_complete() {
             cwd: None,
             display: None,
             require: vec![],
-            autorestart: false,
             timestamps: false,
             healthcheck: None,
+            ..Default::default()
         },
     );
 
@@ -127,16 +127,17 @@ async fn test_ensure_dependency_blocks_until_complete() {
             }),
             cwd: None,
             display: None,
-            require: vec!["setup".to_string()],
-            autorestart: false,
+            require: vec!["setup".into()],
             timestamps: false,
             healthcheck: None,
+            ..Default::default()
         },
     );
 
     let config = Config {
         default: Some("main".to_string()),
         tasks,
+        ..Default::default()
     };
 
     let (tx, rx) = tokio::sync::mpsc::channel::<UiEvent>(100);
@@ -295,7 +296,8 @@ async fn test_switch_status_and_panel_views() {
 #[tokio::test]
 async fn test_healthcheck_blocks_dependent_until_passed() {
     use rote_mux::config::{
-        CommandValue, Healthcheck, HealthcheckMethod, TaskAction, TaskConfiguration,
+        CommandValue, DEFAULT_HEALTHCHECK_TIMEOUT, Healthcheck, HealthcheckMethod, HealthcheckMode,
+        TaskAction, TaskConfiguration,
     };
     use std::borrow::Cow;
 
@@ -311,12 +313,17 @@ async fn test_healthcheck_blocks_dependent_until_passed() {
             cwd: None,
             display: None,
             require: vec![],
-            autorestart: false,
             timestamps: false,
             healthcheck: Some(Healthcheck {
                 method: HealthcheckMethod::Cmd("true".to_string()),
                 interval: Duration::from_millis(100),
+                timeout: DEFAULT_HEALTHCHECK_TIMEOUT,
+                retries: None,
+                start_period: Duration::ZERO,
+                mode: HealthcheckMode::Readiness,
+                restart_on_unhealthy: false,
             }),
+            ..Default::default()
         },
     );
 
@@ -329,16 +336,17 @@ async fn test_healthcheck_blocks_dependent_until_passed() {
             }),
             cwd: None,
             display: None,
-            require: vec!["server".to_string()],
-            autorestart: false,
+            require: vec!["server".into()],
             timestamps: false,
             healthcheck: None,
+            ..Default::default()
         },
     );
 
     let config = Config {
         default: Some("client".to_string()),
         tasks,
+        ..Default::default()
     };
 
     let (tx, rx) = tokio::sync::mpsc::channel::<UiEvent>(100);
@@ -365,8 +373,8 @@ async fn test_healthcheck_blocks_dependent_until_passed() {
 #[tokio::test]
 async fn test_healthcheck_with_port_tool() {
     use rote_mux::config::{
-        CommandValue, Healthcheck, HealthcheckMethod, HealthcheckTool, TaskAction,
-        TaskConfiguration,
+        CommandValue, DEFAULT_HEALTHCHECK_TIMEOUT, Healthcheck, HealthcheckMethod, HealthcheckMode,
+        HealthcheckTool, TaskAction, TaskConfiguration,
     };
     use std::borrow::Cow;
     use std::net::TcpListener;
@@ -388,12 +396,17 @@ async fn test_healthcheck_with_port_tool() {
             cwd: None,
             display: None,
             require: vec![],
-            autorestart: false,
             timestamps: false,
             healthcheck: Some(Healthcheck {
                 method: HealthcheckMethod::Tool(HealthcheckTool::IsPortOpen { port }),
                 interval: Duration::from_millis(100),
+                timeout: DEFAULT_HEALTHCHECK_TIMEOUT,
+                retries: None,
+                start_period: Duration::ZERO,
+                mode: HealthcheckMode::Readiness,
+                restart_on_unhealthy: false,
             }),
+            ..Default::default()
         },
     );
 
@@ -406,16 +419,17 @@ async fn test_healthcheck_with_port_tool() {
             }),
             cwd: None,
             display: None,
-            require: vec!["server".to_string()],
-            autorestart: false,
+            require: vec!["server".into()],
             timestamps: false,
             healthcheck: None,
+            ..Default::default()
         },
     );
 
     let config = Config {
         default: Some("client".to_string()),
         tasks,
+        ..Default::default()
     };
 
     let (tx, rx) = tokio::sync::mpsc::channel::<UiEvent>(100);
@@ -445,8 +459,8 @@ async fn test_healthcheck_with_port_tool() {
 #[tokio::test]
 async fn test_healthcheck_delayed_port() {
     use rote_mux::config::{
-        CommandValue, Healthcheck, HealthcheckMethod, HealthcheckTool, TaskAction,
-        TaskConfiguration,
+        CommandValue, DEFAULT_HEALTHCHECK_TIMEOUT, Healthcheck, HealthcheckMethod, HealthcheckMode,
+        HealthcheckTool, TaskAction, TaskConfiguration,
     };
     use std::borrow::Cow;
     use std::net::TcpListener;
@@ -473,18 +487,24 @@ async fn test_healthcheck_delayed_port() {
             cwd: None,
             display: None,
             require: vec![],
-            autorestart: false,
             timestamps: false,
             healthcheck: Some(Healthcheck {
                 method: HealthcheckMethod::Tool(HealthcheckTool::IsPortOpen { port }),
                 interval: Duration::from_millis(100),
+                timeout: DEFAULT_HEALTHCHECK_TIMEOUT,
+                retries: None,
+                start_period: Duration::ZERO,
+                mode: HealthcheckMode::Readiness,
+                restart_on_unhealthy: false,
             }),
+            ..Default::default()
         },
     );
 
     let config = Config {
         default: Some("server".to_string()),
         tasks,
+        ..Default::default()
     };
 
     let (tx, rx) = tokio::sync::mpsc::channel::<UiEvent>(100);
@@ -519,3 +539,614 @@ async fn test_healthcheck_delayed_port() {
     assert!(result.is_ok(), "App should exit within 3 seconds");
     assert!(result.unwrap().is_ok(), "App should exit successfully");
 }
+
+#[tokio::test]
+async fn test_exit_uses_stop_signal() {
+    // The task ignores SIGINT, so only its stop signal can end it quickly;
+    // with the default SIGINT it would only exit once escalated to SIGTERM
+    let yaml = r#"
+default: server
+tasks:
+  server:
+    run: trap '' INT; trap 'echo draining; sleep 0.5; exit 0' HUP; while true; do sleep 0.1; done
+    shell: bash
+    stop_signal: SIGHUP
+    stop_timeout: 5
+"#;
+    let config: Config = serde_yaml::from_str(yaml).unwrap();
+    let (tx, rx) = tokio::sync::mpsc::channel::<UiEvent>(100);
+
+    let app_task = tokio::spawn(async move {
+        rote_mux::run_with_input(config, vec![], std::path::PathBuf::from("."), Some(rx)).await
+    });
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let start = std::time::Instant::now();
+    let _ = tx.send(UiEvent::Exit).await;
+    drop(tx);
+
+    let result = timeout(Duration::from_secs(4), app_task).await;
+    assert!(result.is_ok(), "App should exit before the stop timeout");
+    assert!(result.unwrap().is_ok(), "App should exit successfully");
+    assert!(
+        start.elapsed() >= Duration::from_millis(400),
+        "The task should have been given time to drain"
+    );
+}
+
+#[tokio::test]
+async fn test_slow_stop_doesnt_hold_up_other_tasks() {
+    let dir = std::env::temp_dir().join(format!("rote-slow-stop-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    // `slow` takes a second to stop, while `fast` is restarted
+    let yaml = r#"
+default: all
+tasks:
+  slow:
+    run: echo run >> slow.log; trap 'sleep 1; exit 0' INT; while true; do sleep 0.1; done
+    shell: bash
+    stop_timeout: 5
+  fast:
+    run: echo run >> fast.log; sleep 10
+    shell: true
+  all:
+    require: [slow, fast]
+"#;
+    let config: Config = serde_yaml::from_str(yaml).unwrap();
+    let (tx, rx) = tokio::sync::mpsc::channel::<UiEvent>(100);
+
+    let app_dir = dir.clone();
+    let app_task =
+        tokio::spawn(
+            async move { rote_mux::run_with_input(config, vec![], app_dir, Some(rx)).await },
+        );
+    let count = |file: &str| {
+        std::fs::read_to_string(dir.join(file))
+            .map(|contents| contents.lines().count())
+            .unwrap_or(0)
+    };
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let _ = tx
+        .send(UiEvent::RestartTask {
+            panel: PanelIndex::new(0),
+        })
+        .await;
+    let _ = tx
+        .send(UiEvent::RestartTask {
+            panel: PanelIndex::new(1),
+        })
+        .await;
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert_eq!((count("slow.log"), count("fast.log")), (1, 2));
+
+    // `slow` starts again once it's gone
+    tokio::time::sleep(Duration::from_millis(1200)).await;
+    assert_eq!(count("slow.log"), 2);
+
+    let _ = tx.send(UiEvent::Exit).await;
+    drop(tx);
+    let result = timeout(Duration::from_secs(4), app_task).await;
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(result.is_ok(), "App should exit within 4 seconds");
+    assert!(result.unwrap().is_ok(), "App should exit successfully");
+}
+
+#[tokio::test]
+async fn test_restart_gives_up_after_max_restarts() {
+    let runs = std::env::temp_dir().join(format!("rote-crash-loop-{}", std::process::id()));
+    let _ = std::fs::remove_file(&runs);
+
+    let yaml = format!(
+        r#"
+default: crasher
+tasks:
+  crasher:
+    run: echo run >> '{}'; exit 1
+    shell: true
+    restart: on-failure
+    max_restarts: 2
+    restart_delay: 0.05
+"#,
+        runs.display()
+    );
+    let config: Config = serde_yaml::from_str(&yaml).unwrap();
+    let (tx, rx) = tokio::sync::mpsc::channel::<UiEvent>(100);
+
+    let app_task = tokio::spawn(async move {
+        rote_mux::run_with_input(config, vec![], std::path::PathBuf::from("."), Some(rx)).await
+    });
+
+    // The first run and two restarts, 50ms and then 100ms apart
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    let _ = tx.send(UiEvent::Exit).await;
+    drop(tx);
+
+    let result = timeout(Duration::from_secs(3), app_task).await;
+    assert!(result.is_ok(), "App should exit within 3 seconds");
+    assert!(result.unwrap().is_ok(), "App should exit successfully");
+
+    let contents = std::fs::read_to_string(&runs).unwrap();
+    let _ = std::fs::remove_file(&runs);
+    assert_eq!(contents.lines().count(), 3);
+}
+
+#[tokio::test]
+async fn test_restart_on_unhealthy() {
+    let runs = std::env::temp_dir().join(format!("rote-unhealthy-{}", std::process::id()));
+    let _ = std::fs::remove_file(&runs);
+
+    let yaml = format!(
+        r#"
+default: server
+tasks:
+  server:
+    run: echo run >> '{}'; sleep 10
+    shell: true
+    healthcheck:
+      cmd: "false"
+      interval: 0.1
+      retries: 2
+      mode: continuous
+      restart_on_unhealthy: true
+"#,
+        runs.display()
+    );
+    let config: Config = serde_yaml::from_str(&yaml).unwrap();
+    let (tx, rx) = tokio::sync::mpsc::channel::<UiEvent>(100);
+
+    let app_task = tokio::spawn(async move {
+        rote_mux::run_with_input(config, vec![], std::path::PathBuf::from("."), Some(rx)).await
+    });
+
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    let _ = tx.send(UiEvent::Exit).await;
+    drop(tx);
+
+    let result = timeout(Duration::from_secs(3), app_task).await;
+    assert!(result.is_ok(), "App should exit within 3 seconds");
+    assert!(result.unwrap().is_ok(), "App should exit successfully");
+
+    let contents = std::fs::read_to_string(&runs).unwrap();
+    let _ = std::fs::remove_file(&runs);
+    assert!(
+        contents.lines().count() >= 2,
+        "The unhealthy task should have been restarted"
+    );
+}
+
+#[tokio::test]
+async fn test_watch_restarts_task_and_reruns_ensure() {
+    let dir = std::env::temp_dir().join(format!("rote-watch-app-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::create_dir_all(dir.join("schema")).unwrap();
+
+    let yaml = r#"
+default: server
+tasks:
+  codegen:
+    ensure: echo gen >> codegen.log
+    shell: true
+    watch: ["schema/*.json"]
+  server:
+    run: echo run >> server.log; sleep 10
+    shell: true
+    require: [codegen]
+    watch: ["src/**"]
+    watch_ignore: ["src/*.tmp"]
+"#;
+    let config: Config = serde_yaml::from_str(yaml).unwrap();
+    let (tx, rx) = tokio::sync::mpsc::channel::<UiEvent>(100);
+
+    let app_dir = dir.clone();
+    let app_task =
+        tokio::spawn(
+            async move { rote_mux::run_with_input(config, vec![], app_dir, Some(rx)).await },
+        );
+
+    let count = |file: &str| {
+        std::fs::read_to_string(dir.join(file))
+            .map(|contents| contents.lines().count())
+            .unwrap_or(0)
+    };
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!((count("codegen.log"), count("server.log")), (1, 1));
+
+    // Ignored files don't restart anything
+    std::fs::write(dir.join("src/scratch.tmp"), "").unwrap();
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+    assert_eq!(count("server.log"), 1);
+
+    std::fs::write(dir.join("src/main.rs"), "").unwrap();
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+    assert_eq!(count("server.log"), 2);
+
+    // Re-running codegen restarts the server as well
+    std::fs::write(dir.join("schema/api.json"), "{}").unwrap();
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    assert_eq!((count("codegen.log"), count("server.log")), (2, 3));
+
+    let _ = tx.send(UiEvent::Exit).await;
+    drop(tx);
+
+    let result = timeout(Duration::from_secs(3), app_task).await;
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(result.is_ok(), "App should exit within 3 seconds");
+    assert!(result.unwrap().is_ok(), "App should exit successfully");
+}
+
+#[tokio::test]
+async fn test_reload_config() {
+    let dir = std::env::temp_dir().join(format!("rote-reload-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("rote.yaml");
+
+    std::fs::write(
+        &path,
+        r#"
+default: app
+tasks:
+  db:
+    run: echo db >> db.log; sleep 10
+    shell: true
+  old:
+    run: echo $$ > old.pid; exec sleep 10
+    shell: true
+  app:
+    run: echo v1 >> app.log; sleep 10
+    shell: true
+    require: [db, old]
+"#,
+    )
+    .unwrap();
+    let loaded = rote_mux::loader::load_config(std::slice::from_ref(&path)).unwrap();
+    let (tx, rx) = tokio::sync::mpsc::channel::<UiEvent>(100);
+
+    let app_path = path.clone();
+    let app_task = tokio::spawn(async move {
+        rote_mux::run_reloadable(loaded, vec![app_path], vec![], Some(rx)).await
+    });
+
+    let read = |file: &str| std::fs::read_to_string(dir.join(file)).unwrap_or_default();
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(read("app.log"), "v1\n");
+    let old_pid: i32 = read("old.pid").trim().parse().unwrap();
+
+    // Change `app`, remove `old` and add `cache`, leaving `db` alone
+    std::fs::write(
+        &path,
+        r#"
+default: app
+tasks:
+  db:
+    run: echo db >> db.log; sleep 10
+    shell: true
+  cache:
+    run: echo cache >> cache.log; sleep 10
+    shell: true
+  app:
+    run: echo v2 >> app.log; sleep 10
+    shell: true
+    require: [db, cache]
+"#,
+    )
+    .unwrap();
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+
+    assert_eq!(read("db.log"), "db\n");
+    assert_eq!(read("app.log"), "v1\nv2\n");
+    assert_eq!(read("cache.log"), "cache\n");
+    assert!(
+        rote_mux::signals::is_process_exited(nix::unistd::Pid::from_raw(old_pid)),
+        "The removed task should have been stopped"
+    );
+
+    // A broken config keeps the old one running
+    std::fs::write(&path, "tasks: [").unwrap();
+    let _ = tx.send(UiEvent::ReloadConfig).await;
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+    assert_eq!(read("app.log"), "v1\nv2\n");
+    assert_eq!(read("db.log"), "db\n");
+
+    let _ = tx.send(UiEvent::Exit).await;
+    drop(tx);
+
+    let result = timeout(Duration::from_secs(3), app_task).await;
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(result.is_ok(), "App should exit within 3 seconds");
+    assert!(result.unwrap().is_ok(), "App should exit successfully");
+}
+
+#[tokio::test]
+async fn test_ensure_skipped_when_up_to_date() {
+    let dir = std::env::temp_dir().join(format!("rote-skip-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("package.json"), "{}").unwrap();
+
+    let yaml = r#"
+default: server
+tasks:
+  install:
+    ensure: echo install >> install.log; mkdir -p node_modules; touch node_modules/.done
+    shell: true
+    sources: [package.json]
+    outputs: [node_modules]
+  codegen:
+    ensure: echo codegen >> codegen.log
+    shell: true
+    skip_if: test -f codegen.log
+  server:
+    run: echo run >> server.log; sleep 10
+    shell: true
+    require: [install, codegen]
+"#;
+
+    let run_once = || async {
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let (tx, rx) = tokio::sync::mpsc::channel::<UiEvent>(100);
+        let app_dir = dir.clone();
+        let app_task = tokio::spawn(async move {
+            rote_mux::run_with_input(config, vec![], app_dir, Some(rx)).await
+        });
+        tokio::time::sleep(Duration::from_millis(700)).await;
+        let _ = tx.send(UiEvent::Exit).await;
+        let result = timeout(Duration::from_secs(3), app_task).await;
+        assert!(result.is_ok(), "App should exit within 3 seconds");
+        assert!(result.unwrap().is_ok(), "App should exit successfully");
+    };
+    let count = |file: &str| {
+        std::fs::read_to_string(dir.join(file))
+            .map(|contents| contents.lines().count())
+            .unwrap_or(0)
+    };
+
+    run_once().await;
+    assert_eq!(
+        (
+            count("install.log"),
+            count("codegen.log"),
+            count("server.log")
+        ),
+        (1, 1, 1)
+    );
+    assert!(dir.join(rote_mux::freshness::STATE_FILE_NAME).exists());
+
+    // Nothing changed, so both ensure tasks are skipped and the server
+    // still starts
+    run_once().await;
+    assert_eq!(
+        (
+            count("install.log"),
+            count("codegen.log"),
+            count("server.log")
+        ),
+        (1, 1, 2)
+    );
+
+    // A missing output runs the task again
+    std::fs::remove_dir_all(dir.join("node_modules")).unwrap();
+    run_once().await;
+    assert_eq!(count("install.log"), 2);
+
+    // And so does a changed source
+    std::fs::write(dir.join("package.json"), r#"{"name": "app"}"#).unwrap();
+    run_once().await;
+    assert_eq!(count("install.log"), 3);
+    assert_eq!(count("codegen.log"), 1);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_ensure_timeout() {
+    let dir = std::env::temp_dir().join(format!("rote-ensure-timeout-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    // The task exits cleanly when stopped, but still counts as failed
+    let yaml = r#"
+default: server
+tasks:
+  prompt:
+    ensure: trap 'exit 0' INT TERM; echo $$ > prompt.pid; while true; do sleep 0.1; done
+    shell: true
+    timeout: 0.3
+  server:
+    run: echo run >> server.log; sleep 10
+    shell: true
+    require: [prompt]
+"#;
+    let config: Config = serde_yaml::from_str(yaml).unwrap();
+    let (tx, rx) = tokio::sync::mpsc::channel::<UiEvent>(100);
+
+    let app_dir = dir.clone();
+    let app_task =
+        tokio::spawn(
+            async move { rote_mux::run_with_input(config, vec![], app_dir, Some(rx)).await },
+        );
+
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    let pid: i32 = std::fs::read_to_string(dir.join("prompt.pid"))
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+    assert!(
+        rote_mux::signals::is_process_exited(nix::unistd::Pid::from_raw(pid)),
+        "The timed out task should have been stopped"
+    );
+    assert!(
+        !dir.join("server.log").exists(),
+        "Dependents of a timed out task should not start"
+    );
+
+    let _ = tx.send(UiEvent::Exit).await;
+    drop(tx);
+
+    let result = timeout(Duration::from_secs(3), app_task).await;
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(result.is_ok(), "App should exit within 3 seconds");
+    assert!(result.unwrap().is_ok(), "App should exit successfully");
+}
+
+#[tokio::test]
+async fn test_ensure_retries() {
+    let dir = std::env::temp_dir().join(format!("rote-ensure-retries-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    // The first two attempts fail, the third succeeds
+    let yaml = r#"
+default: server
+tasks:
+  migrate:
+    ensure: echo try >> attempts.log; test $(wc -l < attempts.log) -ge 3
+    shell: true
+    retries: 2
+    retry_delay: 0.1
+  flaky:
+    ensure: echo try >> flaky.log; exit 1
+    shell: true
+    retries: 1
+    retry_delay: 0.1
+  server:
+    run: echo run >> server.log; sleep 10
+    shell: true
+    require: [migrate]
+  other:
+    run: echo run >> other.log; sleep 10
+    shell: true
+    require: [flaky]
+"#;
+    let config: Config = serde_yaml::from_str(yaml).unwrap();
+    let (tx, rx) = tokio::sync::mpsc::channel::<UiEvent>(100);
+
+    let app_dir = dir.clone();
+    let app_task = tokio::spawn(async move {
+        rote_mux::run_with_input(
+            config,
+            vec!["server".to_string(), "other".to_string()],
+            app_dir,
+            Some(rx),
+        )
+        .await
+    });
+
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    let attempts = std::fs::read_to_string(dir.join("attempts.log")).unwrap();
+    assert_eq!(
+        attempts.lines().count(),
+        3,
+        "migrate should run three times"
+    );
+    assert!(
+        dir.join("server.log").exists(),
+        "Dependents should start once a retry succeeds"
+    );
+
+    let flaky = std::fs::read_to_string(dir.join("flaky.log")).unwrap();
+    assert_eq!(
+        flaky.lines().count(),
+        2,
+        "flaky should stop after its retry"
+    );
+    assert!(
+        !dir.join("other.log").exists(),
+        "Dependents of a task that ran out of retries should not start"
+    );
+
+    let _ = tx.send(UiEvent::Exit).await;
+    drop(tx);
+
+    let result = timeout(Duration::from_secs(3), app_task).await;
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(result.is_ok(), "App should exit within 3 seconds");
+    assert!(result.unwrap().is_ok(), "App should exit successfully");
+}
+
+#[tokio::test]
+async fn test_input_mode_writes_to_stdin() {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use rote_mux::input::InputMode;
+
+    let dir = std::env::temp_dir().join(format!("rote-stdin-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let yaml = r#"
+tasks:
+  prompt:
+    run: read name; echo "hello $name" > greeting.txt; cat > rest.txt; echo done > done.txt; sleep 10
+    shell: true
+    stdin: pipe
+  quiet:
+    run: cat > quiet.txt; echo done > quiet-done.txt; sleep 10
+    shell: true
+"#;
+    let config: Config = serde_yaml::from_str(yaml).unwrap();
+    let (tx, rx) = tokio::sync::mpsc::channel::<UiEvent>(100);
+
+    let app_dir = dir.clone();
+    let app_task = tokio::spawn(async move {
+        rote_mux::run_with_input(
+            config,
+            vec!["prompt".to_string(), "quiet".to_string()],
+            app_dir,
+            Some(rx),
+        )
+        .await
+    });
+
+    let key = |code| UiEvent::Input(KeyEvent::new(code, KeyModifiers::NONE));
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(
+        dir.join("quiet-done.txt").exists(),
+        "A task without `stdin: pipe` should read end of file"
+    );
+
+    // A typed line is sent when Enter is pressed
+    let _ = tx.send(UiEvent::SwitchPanel(PanelIndex::new(0))).await;
+    let _ = tx.send(UiEvent::EnterInputMode(InputMode::Line)).await;
+    for c in "bob".chars() {
+        let _ = tx.send(key(KeyCode::Char(c))).await;
+    }
+    let _ = tx.send(key(KeyCode::Enter)).await;
+
+    // Raw keys are sent as they are typed
+    let _ = tx.send(UiEvent::LeaveInputMode).await;
+    let _ = tx.send(UiEvent::EnterInputMode(InputMode::Raw)).await;
+    for code in [KeyCode::Char('x'), KeyCode::Tab, KeyCode::Enter] {
+        let _ = tx.send(key(code)).await;
+    }
+
+    // Ctrl-D on an empty line closes stdin
+    let _ = tx.send(UiEvent::LeaveInputMode).await;
+    let _ = tx.send(UiEvent::EnterInputMode(InputMode::Line)).await;
+    let _ = tx
+        .send(UiEvent::Input(KeyEvent::new(
+            KeyCode::Char('d'),
+            KeyModifiers::CONTROL,
+        )))
+        .await;
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap_or_default();
+    assert_eq!(read("greeting.txt"), "hello bob\n");
+    assert_eq!(read("rest.txt"), "x\t\n");
+    assert_eq!(read("done.txt"), "done\n");
+
+    let _ = tx.send(UiEvent::Exit).await;
+    drop(tx);
+
+    let result = timeout(Duration::from_secs(3), app_task).await;
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(result.is_ok(), "App should exit within 3 seconds");
+    assert!(result.unwrap().is_ok(), "App should exit successfully");
+}
@@ -2,8 +2,10 @@ use std::time::Duration;
 use tokio::sync::{broadcast, mpsc};
 use tokio::time::timeout;
 
+use nix::sys::signal::Signal;
 use rote_mux::panel::{MessageKind, Panel, PanelIndex, StreamKind};
-use rote_mux::process::TaskInstance;
+use rote_mux::process::{ProcessIo, StopPolicy, StopStage, TaskInstance};
+use rote_mux::pty::TerminalSize;
 use rote_mux::ui::UiEvent;
 
 #[tokio::test]
@@ -26,8 +28,15 @@ async fn test_panel_stderr_buffer() {
         false, // timestamps
     );
 
-    let mut proc = TaskInstance::spawn(PanelIndex::new(0), &cmd, None, tx, shutdown_tx.subscribe())
-        .expect("spawn failed");
+    let mut proc = TaskInstance::spawn(
+        PanelIndex::new(0),
+        &cmd,
+        None,
+        &[],
+        tx,
+        shutdown_tx.subscribe(),
+    )
+    .expect("spawn failed");
 
     let status = timeout(Duration::from_secs(2), proc.wait())
         .await
@@ -45,7 +54,7 @@ async fn test_panel_stderr_buffer() {
             Some(event) = rx.recv() => {
                 if let UiEvent::Line { stream, text, .. } = event {
                     let kind = match stream {
-                        StreamKind::Stdout => MessageKind::Stdout,
+                        StreamKind::Stdout | StreamKind::Terminal => MessageKind::Stdout,
                         StreamKind::Stderr => MessageKind::Stderr,
                     };
                     panel.messages.push(kind, &text, None);
@@ -81,8 +90,15 @@ async fn test_continuous_output() {
         "for i in 1 2 3 4 5; do echo \"output $i\"; sleep 0.05; done".to_string(),
     ];
 
-    let mut proc = TaskInstance::spawn(PanelIndex::new(0), &cmd, None, tx, shutdown_tx.subscribe())
-        .expect("spawn failed");
+    let mut proc = TaskInstance::spawn(
+        PanelIndex::new(0),
+        &cmd,
+        None,
+        &[],
+        tx,
+        shutdown_tx.subscribe(),
+    )
+    .expect("spawn failed");
 
     // Don't wait for process to complete, but collect output as it comes
     let mut stdout_lines = Vec::new();
@@ -135,8 +151,15 @@ async fn test_visible_len_calculation() {
         false, // timestamps
     );
 
-    let mut proc = TaskInstance::spawn(PanelIndex::new(0), &cmd, None, tx, shutdown_tx.subscribe())
-        .expect("spawn failed");
+    let mut proc = TaskInstance::spawn(
+        PanelIndex::new(0),
+        &cmd,
+        None,
+        &[],
+        tx,
+        shutdown_tx.subscribe(),
+    )
+    .expect("spawn failed");
 
     let status = timeout(Duration::from_secs(2), proc.wait())
         .await
@@ -154,7 +177,7 @@ async fn test_visible_len_calculation() {
             Some(event) = rx.recv() => {
                 if let UiEvent::Line { stream, text, .. } = event {
                     let kind = match stream {
-                        StreamKind::Stdout => MessageKind::Stdout,
+                        StreamKind::Stdout | StreamKind::Terminal => MessageKind::Stdout,
                         StreamKind::Stderr => MessageKind::Stderr,
                     };
                     panel.messages.push(kind, &text, None);
@@ -203,8 +226,15 @@ async fn test_scroll_with_continuous_output() {
         false, // timestamps
     );
 
-    let mut proc = TaskInstance::spawn(PanelIndex::new(0), &cmd, None, tx, shutdown_tx.subscribe())
-        .expect("spawn failed");
+    let mut proc = TaskInstance::spawn(
+        PanelIndex::new(0),
+        &cmd,
+        None,
+        &[],
+        tx,
+        shutdown_tx.subscribe(),
+    )
+    .expect("spawn failed");
 
     // Simulate the scroll update logic from app.rs
     let mut scroll = 0;
@@ -220,7 +250,7 @@ async fn test_scroll_with_continuous_output() {
                     let at_bottom = follow;
 
                     let kind = match stream {
-                        StreamKind::Stdout => MessageKind::Stdout,
+                        StreamKind::Stdout | StreamKind::Terminal => MessageKind::Stdout,
                         StreamKind::Stderr => MessageKind::Stderr,
                     };
                     panel.messages.push(kind, &text, None);
@@ -269,8 +299,15 @@ async fn test_draw_logic_with_few_lines() {
 
     let mut panel = Panel::new("test".to_string(), cmd.clone(), None, true, false, false);
 
-    let mut proc = TaskInstance::spawn(PanelIndex::new(0), &cmd, None, tx, shutdown_tx.subscribe())
-        .expect("spawn failed");
+    let mut proc = TaskInstance::spawn(
+        PanelIndex::new(0),
+        &cmd,
+        None,
+        &[],
+        tx,
+        shutdown_tx.subscribe(),
+    )
+    .expect("spawn failed");
 
     let status = timeout(Duration::from_secs(2), proc.wait())
         .await
@@ -344,8 +381,15 @@ async fn test_draw_logic_with_scrolling() {
 
     let mut panel = Panel::new("test".to_string(), cmd.clone(), None, true, false, false);
 
-    let mut proc = TaskInstance::spawn(PanelIndex::new(0), &cmd, None, tx, shutdown_tx.subscribe())
-        .expect("spawn failed");
+    let mut proc = TaskInstance::spawn(
+        PanelIndex::new(0),
+        &cmd,
+        None,
+        &[],
+        tx,
+        shutdown_tx.subscribe(),
+    )
+    .expect("spawn failed");
 
     let status = timeout(Duration::from_secs(2), proc.wait())
         .await
@@ -419,8 +463,15 @@ async fn test_mixed_output_order_preservation() {
 
     let mut panel = Panel::new("test".to_string(), cmd.clone(), None, true, true, false);
 
-    let mut proc = TaskInstance::spawn(PanelIndex::new(0), &cmd, None, tx, shutdown_tx.subscribe())
-        .expect("spawn failed");
+    let mut proc = TaskInstance::spawn(
+        PanelIndex::new(0),
+        &cmd,
+        None,
+        &[],
+        tx,
+        shutdown_tx.subscribe(),
+    )
+    .expect("spawn failed");
 
     let status = timeout(Duration::from_secs(2), proc.wait())
         .await
@@ -440,7 +491,7 @@ async fn test_mixed_output_order_preservation() {
                 if let UiEvent::Line { stream, text, .. } = event {
                     all_events.push((stream, text.clone()));
                     let kind = match stream {
-                        StreamKind::Stdout => MessageKind::Stdout,
+                        StreamKind::Stdout | StreamKind::Terminal => MessageKind::Stdout,
                         StreamKind::Stderr => MessageKind::Stderr,
                     };
                     panel.messages.push(kind, &text, None);
@@ -593,8 +644,15 @@ async fn test_colored_output() {
 
     let mut panel = Panel::new("test".to_string(), cmd.clone(), None, true, false, false);
 
-    let mut proc = TaskInstance::spawn(PanelIndex::new(0), &cmd, None, tx, shutdown_tx.subscribe())
-        .expect("spawn failed");
+    let mut proc = TaskInstance::spawn(
+        PanelIndex::new(0),
+        &cmd,
+        None,
+        &[],
+        tx,
+        shutdown_tx.subscribe(),
+    )
+    .expect("spawn failed");
 
     let status = timeout(Duration::from_secs(2), proc.wait())
         .await
@@ -611,7 +669,7 @@ async fn test_colored_output() {
             Some(event) = rx.recv() => {
                 if let UiEvent::Line { stream, text, .. } = event {
                     let kind = match stream {
-                        StreamKind::Stdout => MessageKind::Stdout,
+                        StreamKind::Stdout | StreamKind::Terminal => MessageKind::Stdout,
                         StreamKind::Stderr => MessageKind::Stderr,
                     };
                     panel.messages.push(kind, &text, None);
@@ -671,8 +729,15 @@ async fn test_visible_len_with_stream_toggles() {
 
     let mut panel = Panel::new("test".to_string(), cmd.clone(), None, true, false, false);
 
-    let mut proc = TaskInstance::spawn(PanelIndex::new(0), &cmd, None, tx, shutdown_tx.subscribe())
-        .expect("spawn failed");
+    let mut proc = TaskInstance::spawn(
+        PanelIndex::new(0),
+        &cmd,
+        None,
+        &[],
+        tx,
+        shutdown_tx.subscribe(),
+    )
+    .expect("spawn failed");
 
     let status = timeout(Duration::from_secs(2), proc.wait())
         .await
@@ -690,7 +755,7 @@ async fn test_visible_len_with_stream_toggles() {
             Some(event) = rx.recv() => {
                 if let UiEvent::Line { stream, text, .. } = event {
                     let kind = match stream {
-                        StreamKind::Stdout => MessageKind::Stdout,
+                        StreamKind::Stdout | StreamKind::Terminal => MessageKind::Stdout,
                         StreamKind::Stderr => MessageKind::Stderr,
                     };
                     panel.messages.push(kind, &text, None);
@@ -761,6 +826,7 @@ async fn test_terminate_multiple_processes() {
         PanelIndex::new(0),
         &cmd1,
         None,
+        &[],
         tx.clone(),
         shutdown_tx.subscribe(),
     )
@@ -769,6 +835,7 @@ async fn test_terminate_multiple_processes() {
         PanelIndex::new(1),
         &cmd2,
         None,
+        &[],
         tx.clone(),
         shutdown_tx.subscribe().resubscribe(),
     )
@@ -777,6 +844,7 @@ async fn test_terminate_multiple_processes() {
         PanelIndex::new(2),
         &cmd3,
         None,
+        &[],
         tx.clone(),
         shutdown_tx.subscribe().resubscribe(),
     )
@@ -807,3 +875,437 @@ async fn test_terminate_multiple_processes() {
     // Clean up receiver
     drop(rx);
 }
+
+#[tokio::test]
+async fn test_spawn_with_env() {
+    let (tx, mut rx) = mpsc::channel::<UiEvent>(100);
+    let (shutdown_tx, _) = broadcast::channel::<()>(16);
+
+    let cmd = vec![
+        "bash".to_string(),
+        "-c".to_string(),
+        "echo \"$ROTE_TEST_GREETING $ROTE_TEST_NAME\"".to_string(),
+    ];
+    let env = vec![
+        ("ROTE_TEST_GREETING".to_string(), "hello".to_string()),
+        ("ROTE_TEST_NAME".to_string(), "world".to_string()),
+    ];
+
+    let mut proc = TaskInstance::spawn(
+        PanelIndex::new(0),
+        &cmd,
+        None,
+        &env,
+        tx,
+        shutdown_tx.subscribe(),
+    )
+    .expect("spawn failed");
+
+    let status = timeout(Duration::from_secs(2), proc.wait())
+        .await
+        .expect("Process timed out")
+        .expect("Failed to wait for process");
+    assert!(status.success());
+
+    let mut lines = Vec::new();
+    while let Ok(Some(event)) = timeout(Duration::from_millis(500), rx.recv()).await {
+        if let UiEvent::Line { text, .. } = event {
+            lines.push(text);
+        }
+    }
+
+    assert_eq!(lines, vec!["hello world"]);
+}
+
+/// Whether a process is still running (zombies count as exited, since they
+/// may never be reaped when their parent has already gone).
+fn is_running(pid: i32) -> bool {
+    match std::fs::read_to_string(format!("/proc/{pid}/stat")) {
+        Ok(stat) => !stat
+            .rsplit_once(')')
+            .is_some_and(|(_, rest)| rest.trim_start().starts_with('Z')),
+        Err(_) => false,
+    }
+}
+
+#[tokio::test]
+async fn test_terminate_process_group_reaches_children() {
+    let (tx, mut rx) = mpsc::channel::<UiEvent>(100);
+    let (shutdown_tx, _) = broadcast::channel::<()>(16);
+
+    // The shell prints the pid of its background child and waits for it
+    let cmd = vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        "sleep 30 & echo $!; wait".to_string(),
+    ];
+
+    let mut proc = TaskInstance::spawn(
+        PanelIndex::new(0),
+        &cmd,
+        None,
+        &[],
+        tx,
+        shutdown_tx.subscribe(),
+    )
+    .expect("spawn failed");
+
+    let child_pid = loop {
+        match timeout(Duration::from_secs(2), rx.recv()).await {
+            Ok(Some(UiEvent::Line { text, .. })) => break text.parse::<i32>().unwrap(),
+            Ok(Some(_)) => continue,
+            _ => panic!("shell did not print the child pid"),
+        }
+    };
+    assert!(is_running(child_pid));
+
+    proc.terminate().await;
+    let status = timeout(Duration::from_secs(2), proc.wait())
+        .await
+        .expect("Process timed out")
+        .expect("Failed to wait for process");
+    assert!(!status.success());
+
+    // Give the signal a moment to be delivered to the child
+    for _ in 0..20 {
+        if !is_running(child_pid) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert!(
+        !is_running(child_pid),
+        "child of the shell should be terminated with the group"
+    );
+}
+
+#[tokio::test]
+async fn test_terminate_reaches_children_left_behind() {
+    let (tx, mut rx) = mpsc::channel::<UiEvent>(100);
+    let (shutdown_tx, _) = broadcast::channel::<()>(16);
+
+    // The script forks a background child, prints its pid and exits, the
+    // way `npm run dev` can leave a server holding a port
+    let cmd = vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        "sleep 30 > /dev/null & echo $!".to_string(),
+    ];
+
+    let proc = TaskInstance::spawn(
+        PanelIndex::new(0),
+        &cmd,
+        None,
+        &[],
+        tx,
+        shutdown_tx.subscribe(),
+    )
+    .expect("spawn failed");
+
+    let mut child_pid = None;
+    loop {
+        match timeout(Duration::from_secs(2), rx.recv()).await {
+            Ok(Some(UiEvent::Line { text, .. })) => child_pid = text.parse::<i32>().ok(),
+            Ok(Some(UiEvent::Exited { .. })) => break,
+            Ok(Some(_)) => continue,
+            _ => panic!("script did not exit"),
+        }
+    }
+    let child_pid = child_pid.expect("script did not print the child pid");
+
+    // The script is gone, but its group lives on in the child
+    let pid = nix::unistd::Pid::from_raw(proc.pid.unwrap() as i32);
+    assert!(proc.has_exited());
+    assert!(is_running(child_pid));
+    assert!(!rote_mux::signals::is_process_exited(pid));
+
+    timeout(Duration::from_secs(2), proc.terminate())
+        .await
+        .expect("terminate timed out");
+    for _ in 0..20 {
+        if !is_running(child_pid) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert!(
+        !is_running(child_pid),
+        "child left behind should be terminated with the group"
+    );
+
+    // The group is gone once the child has been reaped
+    for _ in 0..40 {
+        if rote_mux::signals::is_process_exited(pid) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert!(rote_mux::signals::is_process_exited(pid));
+}
+
+/// Spawn a bash script that stops according to `stop_policy`. The shutdown
+/// sender is returned so the channel stays open.
+async fn spawn_with_policy(
+    script: &str,
+    stop_policy: StopPolicy,
+) -> (TaskInstance, broadcast::Sender<()>) {
+    let (tx, _rx) = mpsc::channel::<UiEvent>(100);
+    let (shutdown_tx, _) = broadcast::channel::<()>(16);
+    let cmd = vec!["bash".to_string(), "-c".to_string(), script.to_string()];
+    let proc = TaskInstance::spawn(
+        PanelIndex::new(0),
+        &cmd,
+        None,
+        &[],
+        tx,
+        shutdown_tx.subscribe(),
+    )
+    .expect("spawn failed")
+    .with_stop_policy(stop_policy);
+    // Give bash time to install its traps
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    (proc, shutdown_tx)
+}
+
+async fn terminate_stages(proc: &TaskInstance) -> Vec<StopStage> {
+    let mut stages = Vec::new();
+    timeout(
+        Duration::from_secs(5),
+        proc.terminate_reporting(|stage| stages.push(stage)),
+    )
+    .await
+    .expect("terminate timed out");
+    stages
+}
+
+#[tokio::test]
+async fn test_terminate_default_policy_escalates_to_sigterm() {
+    let (proc, _shutdown_tx) = spawn_with_policy(
+        "trap '' INT; while true; do sleep 0.05; done",
+        StopPolicy::default(),
+    )
+    .await;
+
+    assert_eq!(
+        terminate_stages(&proc).await,
+        vec![
+            StopStage::Signalled {
+                signal: Signal::SIGINT,
+                timeout: Duration::from_millis(300),
+            },
+            StopStage::Signalled {
+                signal: Signal::SIGTERM,
+                timeout: Duration::from_millis(300),
+            },
+            StopStage::Exited,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_terminate_waits_for_custom_stop_signal() {
+    let policy = StopPolicy {
+        signal: Signal::SIGTERM,
+        timeout: Duration::from_secs(3),
+    };
+    let (mut proc, _shutdown_tx) = spawn_with_policy(
+        "trap 'sleep 0.5; exit 0' TERM; while true; do sleep 0.05; done",
+        policy,
+    )
+    .await;
+
+    let start = std::time::Instant::now();
+    let stages = terminate_stages(&proc).await;
+    assert_eq!(
+        stages,
+        vec![
+            StopStage::Signalled {
+                signal: Signal::SIGTERM,
+                timeout: Duration::from_secs(3),
+            },
+            StopStage::Exited,
+        ]
+    );
+    assert!(start.elapsed() >= Duration::from_millis(400));
+    assert!(start.elapsed() < Duration::from_secs(3));
+
+    let status = proc.wait().await.expect("wait failed");
+    assert!(status.success(), "task should exit on its own: {status:?}");
+}
+
+#[tokio::test]
+async fn test_terminate_kills_after_stop_timeout() {
+    let policy = StopPolicy {
+        signal: Signal::SIGHUP,
+        timeout: Duration::from_millis(200),
+    };
+    let (proc, _shutdown_tx) = spawn_with_policy(
+        "trap '' INT TERM HUP; while true; do sleep 0.05; done",
+        policy,
+    )
+    .await;
+
+    assert_eq!(
+        terminate_stages(&proc).await,
+        vec![
+            StopStage::Signalled {
+                signal: Signal::SIGHUP,
+                timeout: Duration::from_millis(200),
+            },
+            StopStage::Signalled {
+                signal: Signal::SIGTERM,
+                timeout: Duration::from_millis(300),
+            },
+            StopStage::Killed,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_exit_events_carry_generation() {
+    let (tx, mut rx) = mpsc::channel::<UiEvent>(100);
+    let (shutdown_tx, _) = broadcast::channel::<()>(16);
+    let cmd = vec!["true".to_string()];
+
+    // Two runs of the same task on the same panel
+    let spawn = || {
+        TaskInstance::spawn(
+            PanelIndex::new(0),
+            &cmd,
+            None,
+            &[],
+            tx.clone(),
+            shutdown_tx.subscribe(),
+        )
+        .expect("spawn failed")
+    };
+    let first = spawn();
+    let second = spawn();
+    assert_ne!(first.generation, second.generation);
+
+    let mut generations = Vec::new();
+    while generations.len() < 2 {
+        match timeout(Duration::from_secs(2), rx.recv()).await {
+            Ok(Some(UiEvent::Exited { generation, .. })) => generations.push(generation),
+            Ok(Some(_)) => continue,
+            _ => panic!("processes did not exit"),
+        }
+    }
+    generations.sort();
+    assert_eq!(generations, vec![first.generation, second.generation]);
+}
+
+#[tokio::test]
+async fn test_shutdown_does_not_mark_process_exited() {
+    let (proc, shutdown_tx) = spawn_with_policy("sleep 30", StopPolicy::default()).await;
+
+    // Exit is only reported once the process has really exited, so stopping
+    // it after shutdown has begun still waits for it
+    let _ = shutdown_tx.send(());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!proc.has_exited());
+
+    let stages = terminate_stages(&proc).await;
+    assert_eq!(stages.last(), Some(&StopStage::Exited));
+    assert!(stages.len() > 1, "process was not signalled: {stages:?}");
+    assert!(proc.has_exited());
+}
+
+/// Spawn a bash script under a pseudo-terminal of 80x24. Output is forwarded
+/// until the returned shutdown sender is dropped.
+fn spawn_in_terminal(
+    script: &str,
+) -> (TaskInstance, mpsc::Receiver<UiEvent>, broadcast::Sender<()>) {
+    let (tx, rx) = mpsc::channel::<UiEvent>(100);
+    let (shutdown_tx, _) = broadcast::channel::<()>(16);
+    let cmd = vec!["bash".to_string(), "-c".to_string(), script.to_string()];
+    let io = ProcessIo {
+        terminal: Some(TerminalSize { cols: 80, rows: 24 }),
+        ..ProcessIo::default()
+    };
+    let proc = TaskInstance::spawn_with_io(
+        PanelIndex::new(0),
+        &cmd,
+        None,
+        &[],
+        io,
+        tx,
+        shutdown_tx.subscribe(),
+    )
+    .expect("spawn failed");
+    (proc, rx, shutdown_tx)
+}
+
+/// The next line of terminal output.
+async fn next_terminal_line(rx: &mut mpsc::Receiver<UiEvent>) -> String {
+    loop {
+        match timeout(Duration::from_secs(2), rx.recv()).await {
+            Ok(Some(UiEvent::Line { stream, text, .. })) => {
+                assert_eq!(stream, StreamKind::Terminal);
+                return text;
+            }
+            Ok(Some(_)) => continue,
+            _ => panic!("no more output"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_terminal_merges_output_and_follows_size() {
+    let (proc, mut rx, _shutdown_tx) = spawn_in_terminal(
+        "test -t 0 && test -t 1 && echo terminal; echo err >&2; stty size; read; stty size",
+    );
+
+    assert_eq!(next_terminal_line(&mut rx).await, "terminal");
+    assert_eq!(next_terminal_line(&mut rx).await, "err");
+    assert_eq!(next_terminal_line(&mut rx).await, "24 80");
+
+    proc.resize(TerminalSize {
+        cols: 100,
+        rows: 30,
+    })
+    .unwrap();
+    assert!(proc.send_input(b"\n".to_vec()));
+    // The terminal echoes the input
+    assert_eq!(next_terminal_line(&mut rx).await, "");
+    assert_eq!(next_terminal_line(&mut rx).await, "30 100");
+}
+
+#[tokio::test]
+async fn test_terminal_keeps_colors() {
+    let (proc, mut rx, _shutdown_tx) =
+        spawn_in_terminal("printf '\\e[31mred\\e[0m \\e[2Kplain\\n'; sleep 30");
+
+    // Colors stay, like in piped output, but cursor movement doesn't
+    assert_eq!(
+        next_terminal_line(&mut rx).await,
+        "\x1b[31mred\x1b[0m plain"
+    );
+    timeout(Duration::from_secs(2), proc.terminate())
+        .await
+        .expect("terminate timed out");
+}
+
+#[tokio::test]
+async fn test_terminal_input_and_stop() {
+    let (mut proc, mut rx, _shutdown_tx) =
+        spawn_in_terminal("read name; echo \"hello $name\"; cat; echo eof; sleep 30");
+
+    assert!(proc.accepts_input());
+    assert!(proc.send_input(b"bob\n".to_vec()));
+    assert_eq!(next_terminal_line(&mut rx).await, "bob");
+    assert_eq!(next_terminal_line(&mut rx).await, "hello bob");
+
+    // Closing input sends end of file, which ends `cat`
+    proc.close_input();
+    assert_eq!(next_terminal_line(&mut rx).await, "eof");
+
+    timeout(Duration::from_secs(2), proc.terminate())
+        .await
+        .expect("terminate timed out");
+    let status = timeout(Duration::from_secs(2), proc.wait())
+        .await
+        .expect("wait timed out")
+        .expect("wait failed");
+    assert!(!status.success());
+}